@@ -23,7 +23,22 @@ if [ ! -f "$INPUT_FILE" ]; then
     exit 1
 fi
 
-# Function to measure compression time
+# The JS CLI writes into an output directory; run it into a scratch one and
+# move the single file it produces to the path we asked for
+run_js() {
+    local operation=$1
+    local input=$2
+    local output=$3
+    local algo=$4
+    local dir=$(mktemp -d)
+
+    (cd js-compressor && node cli.js "$operation" "../$input" "$dir" "--$algo") > /dev/null 2>&1
+    mv "$dir"/* "$output" 2>/dev/null
+    rm -rf "$dir"
+}
+
+# Function to measure compression time. rs-jscmp is the Rust compressor
+# writing the JS format, so the JS decompressor can read it
 measure_compression() {
     local impl=$1
     local algo=$2
@@ -32,19 +47,11 @@ measure_compression() {
     local start_time=$(date +%s.%N)
     
     if [ "$impl" == "js" ]; then
-        cd js-compressor
-        if [ "$algo" == "rle" ]; then
-            node cli.js compress "../$input" "../$output" --rle > /dev/null 2>&1
-        else
-            node cli.js compress "../$input" "../$output" --lz > /dev/null 2>&1
-        fi
-        cd ..
+        run_js compress "$input" "$output" "$algo"
     elif [ "$impl" == "rs" ]; then
-        if [ "$algo" == "rle" ]; then
-            docker run -v $(pwd):/data rust-compressor compress "/data/$input" "/data/$output" --rle > /dev/null 2>&1
-        else
-            docker run -v $(pwd):/data rust-compressor compress "/data/$input" "/data/$output" --lz > /dev/null 2>&1
-        fi
+        docker run -v $(pwd):/data rust-compressor compress "/data/$input" "/data/$output" "--$algo" > /dev/null 2>&1
+    elif [ "$impl" == "rs-jscmp" ]; then
+        docker run -v $(pwd):/data rust-compressor compress "/data/$input" "/data/$output" "--$algo" --format jscmp > /dev/null 2>&1
     fi
     
     local end_time=$(date +%s.%N)
@@ -60,19 +67,9 @@ measure_decompression() {
     local start_time=$(date +%s.%N)
     
     if [ "$impl" == "js" ]; then
-        cd js-compressor
-        if [ "$algo" == "rle" ]; then
-            node cli.js decompress "../$compressed" "../$decompressed" --rle > /dev/null 2>&1
-        else
-            node cli.js decompress "../$compressed" "../$decompressed" --lz > /dev/null 2>&1
-        fi
-        cd ..
+        run_js decompress "$compressed" "$decompressed" "$algo"
     elif [ "$impl" == "rs" ]; then
-        if [ "$algo" == "rle" ]; then
-            docker run -v $(pwd):/data rust-compressor decompress "/data/$compressed" "/data/$decompressed" --rle > /dev/null 2>&1
-        else
-            docker run -v $(pwd):/data rust-compressor decompress "/data/$compressed" "/data/$decompressed" --lz > /dev/null 2>&1
-        fi
+        docker run -v $(pwd):/data rust-compressor decompress "/data/$compressed" "/data/$decompressed" "--$algo" > /dev/null 2>&1
     fi
     
     local end_time=$(date +%s.%N)
@@ -138,20 +135,27 @@ echo -e "${YELLOW}Testing $INPUT_FILE...${NC}"
 # Get original file size
 size=$(du -h "$INPUT_FILE" | cut -f1)
 
-# Test all combinations
+# Test all combinations: each implementation on its own, then each one
+# decompressing the other's JSCMP files
 for algo in "rle" "lz"; do
-    for impl in "js" "rs"; do
+    for impl in "js" "rs" "js->rs" "rs->js"; do
         echo -e "${GREEN}Testing $impl $algo...${NC}"
+
+        case "$impl" in
+            "js->rs") compressor="js"; decompressor="rs" ;;
+            "rs->js") compressor="rs-jscmp"; decompressor="js" ;;
+            *) compressor="$impl"; decompressor="$impl" ;;
+        esac
         
         # Create filenames
-        COMPRESSED="${COMPRESSED_FILE}.${impl}.${algo}"
-        DECOMPRESSED="${COMPRESSED_FILE}.${impl}.${algo}.decompressed"
+        COMPRESSED="${COMPRESSED_FILE}.${compressor}.${decompressor}.${algo}"
+        DECOMPRESSED="${COMPRESSED_FILE}.${compressor}.${decompressor}.${algo}.decompressed"
         
         # Measure compression
-        comp_time=$(measure_compression "$impl" "$algo" "$INPUT_FILE" "$COMPRESSED")
+        comp_time=$(measure_compression "$compressor" "$algo" "$INPUT_FILE" "$COMPRESSED")
         
         # Measure decompression
-        decomp_time=$(measure_decompression "$impl" "$algo" "$COMPRESSED" "$DECOMPRESSED")
+        decomp_time=$(measure_decompression "$decompressor" "$algo" "$COMPRESSED" "$DECOMPRESSED")
         
         # Get compressed size
         comp_size=$(get_file_size "$COMPRESSED")
@@ -171,7 +175,7 @@ done
 echo -e "\n## Summary\n" >> $REPORT_FILE
 echo "This report compares the performance of different compression algorithms and implementations." >> $REPORT_FILE
 echo "- Algorithms: RLE (Run-Length Encoding) and LZ (Lempel-Ziv)" >> $REPORT_FILE
-echo "- Implementations: JavaScript and Rust" >> $REPORT_FILE
+echo "- Implementations: JavaScript and Rust; js->rs and rs->js rows compress with one and decompress with the other via the JSCMP format" >> $REPORT_FILE
 echo "- Compression Time: Time taken to compress the input file" >> $REPORT_FILE
 echo "- Decompression Time: Time taken to restore the original file" >> $REPORT_FILE
 echo "- Compressed Size: Size of the compressed output file" >> $REPORT_FILE
@@ -37,14 +37,40 @@ async function writeOutput(output, data) {
     return fs.promises.writeFile(output, data);
 }
 
+// `algorithm` is the one actually used, 'RLE' or 'LZ', so --auto files
+// record their choice.
 function createCompressedHeader(algorithm) {
     const header = Buffer.alloc(8); // 5 bytes magic + 1 byte version + 1 byte algorithm + 1 byte reserved
     MAGIC_NUMBER.copy(header);
     header[5] = VERSION;
-    header[6] = algorithm === '--rle' ? 1 : 2; // 1 for RLE, 2 for LZ
+    header[6] = algorithm === 'RLE' ? 1 : 2; // 1 for RLE, 2 for LZ
     return header;
 }
 
+// LZ tokens are stored as u64 offset, u64 length and the next byte (0 for
+// none), little-endian; rs-compressor reads and writes the same layout.
+const TOKEN_SIZE = 17;
+
+function encodeTokens(tokens) {
+    const output = Buffer.alloc(tokens.length * TOKEN_SIZE);
+    tokens.forEach((token, i) => {
+        output.writeBigUInt64LE(BigInt(token.offset), i * TOKEN_SIZE);
+        output.writeBigUInt64LE(BigInt(token.length), i * TOKEN_SIZE + 8);
+        output[i * TOKEN_SIZE + 16] = token.nextChar;
+    });
+    return output;
+}
+
+function decodeTokens(data) {
+    const tokens = [];
+    for (let i = 0; i + TOKEN_SIZE <= data.length; i += TOKEN_SIZE) {
+        const offset = Number(data.readBigUInt64LE(i));
+        const length = Number(data.readBigUInt64LE(i + 8));
+        tokens.push(new Lz77Token(offset, length, data[i + 16]));
+    }
+    return tokens;
+}
+
 function readCompressedHeader(data) {
     if (data.length < 8) {
         throw new Error('Invalid compressed file format');
@@ -72,35 +98,16 @@ async function compressBatch(files, outputDir, algorithm) {
             const filename = path.basename(file);
             const outputPath = path.join(outputDir, filename + '.compressed');
             
-            let result;
+            let selectedAlgorithm = algorithm === '--rle' ? 'RLE' : 'LZ';
             if (algorithm === '--auto') {
                 const fileType = detectFileType(inputData);
-                const selectedAlgorithm = selectAlgorithm(fileType, inputData);
-                if (selectedAlgorithm === 'RLE') {
-                    result = Buffer.from(rleCompress(inputData));
-                } else {
-                    const tokens = lzCompress(inputData);
-                    const bytes = [];
-                    for (const token of tokens) {
-                        const offsetBytes = Buffer.alloc(8);
-                        offsetBytes.writeBigUInt64LE(BigInt(token.offset));
-                        bytes.push(...offsetBytes);
-                        
-                        const lengthBytes = Buffer.alloc(8);
-                        lengthBytes.writeBigUInt64LE(BigInt(token.length));
-                        bytes.push(...lengthBytes);
-                        
-                        bytes.push(token.next_char);
-                    }
-                    result = Buffer.from(bytes);
-                }
-            } else if (algorithm === '--rle') {
-                result = Buffer.from(rleCompress(inputData));
-            } else if (algorithm === '--lz') {
-                result = Buffer.from(lzCompress(inputData));
+                selectedAlgorithm = selectAlgorithm(fileType, inputData) === 'RLE' ? 'RLE' : 'LZ';
             }
+            const result = selectedAlgorithm === 'RLE'
+                ? Buffer.from(rleCompress(inputData))
+                : encodeTokens(lzCompress(inputData));
 
-            const header = createCompressedHeader(algorithm);
+            const header = createCompressedHeader(selectedAlgorithm);
             const finalResult = Buffer.concat([header, result]);
             await writeOutput(outputPath, finalResult);
             results.push({
@@ -128,26 +135,14 @@ async function decompressBatch(files, outputDir, algorithm) {
             const filename = path.basename(file, '.compressed');
             const outputPath = path.join(outputDir, filename);
             
-            let result;
-            if (algorithm === '--auto') {
-                const header = readHeader(inputData);
-                if (header.algorithm === 'RLE') {
-                    result = Buffer.from(rleDecompress(inputData.slice(header.size)));
-                } else {
-                    const tokens = [];
-                    for (let i = 0; i + 17 <= inputData.length; i += 17) {
-                        const offset = Number(inputData.readBigUInt64LE(i));
-                        const length = Number(inputData.readBigUInt64LE(i + 8));
-                        const next_char = inputData[i + 16];
-                        tokens.push(new Lz77Token(offset, length, next_char));
-                    }
-                    result = Buffer.from(lzDecompress(tokens));
-                }
-            } else if (algorithm === '--rle') {
-                result = Buffer.from(rleDecompress(inputData));
-            } else if (algorithm === '--lz') {
-                result = Buffer.from(lzDecompress(inputData));
-            }
+            // The header names the algorithm; --auto trusts it, the other
+            // flags override it.
+            const recorded = readCompressedHeader(inputData);
+            const payload = inputData.slice(8);
+            const selectedAlgorithm = algorithm === '--auto' ? recorded : algorithm;
+            const result = selectedAlgorithm === '--rle'
+                ? Buffer.from(rleDecompress(payload))
+                : Buffer.from(lzDecompress(decodeTokens(payload)));
 
             await writeOutput(outputPath, result);
             results.push({
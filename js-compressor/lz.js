@@ -7,8 +7,8 @@ class Lz77Token {
 }
 
 function compress(input) {
-    // Convert Buffer to string if needed
-    const inputStr = Buffer.isBuffer(input) ? input.toString('utf8') : input;
+    // Convert Buffer to string if needed; latin1 keeps one char per byte
+    const inputStr = Buffer.isBuffer(input) ? input.toString('latin1') : input;
     const output = [];
     let pos = 0;
     const windowSize = 10; // Adjust window size as needed
@@ -0,0 +1,33 @@
+// Generates the C header for the `ffi` module into OUT_DIR, where tests/ffi.rs
+// checks it against the committed include/rs_compressor.h. The source tree is
+// only written when RS_COMPRESSOR_WRITE_HEADER is set, to update the
+// committed header after changing the C API. Builds without the `ffi`
+// feature (the CLI and wasm crates) skip cbindgen.
+
+#[cfg(feature = "ffi")]
+fn main() {
+    use std::env;
+    use std::path::PathBuf;
+
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=RS_COMPRESSOR_WRITE_HEADER");
+
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).expect("reading cbindgen.toml");
+    let bindings = cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(crate_dir.join("src/ffi.rs"))
+        .generate()
+        .expect("generating the C header");
+    bindings.write_to_file(out_dir.join("rs_compressor.h"));
+    if env::var_os("RS_COMPRESSOR_WRITE_HEADER").is_some() {
+        bindings.write_to_file(crate_dir.join("include/rs_compressor.h"));
+    }
+}
+
+#[cfg(not(feature = "ffi"))]
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+}
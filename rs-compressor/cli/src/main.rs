@@ -0,0 +1,666 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use rs_compressor::algos::dictionary;
+use rs_compressor::algos::file_type::{select_filters, Algorithm as FileAlgorithm};
+use rs_compressor::algos::filters;
+use rs_compressor::archive::{Archive, EntryKind};
+use rs_compressor::codec;
+use rs_compressor::crypto;
+use rs_compressor::dedup::{self, DedupStats};
+use rs_compressor::framed::{self, FrameOptions};
+use rs_compressor::header::{read_header, Metadata};
+use rs_compressor::jscmp;
+use rs_compressor::progress::Progress;
+use rs_compressor::stats::{CompressionStats, Stopwatch};
+
+fn print_usage() -> ! {
+    println!("Usage: rs-compressor compress|decompress [input_file] [output_file] [--rle|--lz|--packbits|--bwt|--rc|--lz4|--auto] [--explain] [--dict dictionary]");
+    println!("       [--seekable] [--block-size bytes] [--parity blocks] [--keep-metadata] [--meta key=value]... on compress");
+    println!("       [--range start:length] [--restore-metadata] on decompress");
+    println!("       --auto on decompress reads the file's header; a codec flag decodes a bare payload with that codec");
+    println!("       [--encrypt] on compress, [--passphrase-file file] instead of prompting");
+    println!("       [--dedup] on compress to store repeated chunks once");
+    println!("       [--filter delta[:stride]|bcj-x86|bcj-arm64|transpose:width|none,...] on compress");
+    println!("       [--verbose] or [--json] on compress to print statistics about the run");
+    println!("       [--format rscmp|jscmp] on compress; jscmp writes js-compressor files (--rle, --lz or --auto only)");
+    println!("       rs-compressor train-dict [dictionary] [sample_file...] [--size bytes]");
+    println!("       rs-compressor diff [old_file] [new_file] [patch_file]");
+    println!("       rs-compressor patch [old_file] [patch_file] [new_file]");
+    println!("       rs-compressor create|add [archive.rsa] [path...] [--rle|--lz|--packbits|--bwt|--rc|--lz4|--auto] [--dedup]");
+    println!("       rs-compressor list [archive.rsa]");
+    println!("       rs-compressor test [compressed_file] [--dict dictionary] [--passphrase-file file]");
+    println!("       rs-compressor recover [damaged_file] [output_file] [--dict dictionary] [--passphrase-file file]");
+    println!("       rs-compressor extract [archive.rsa] [directory]");
+    println!("Example: rs-compressor compress input.txt output.txt --rle");
+    println!("Example with auto detection: rs-compressor compress input.txt output.txt --auto");
+    println!("Example explaining the auto choice: rs-compressor compress input.txt output.txt --auto --explain");
+    println!("Example with stdin/stdout: cat input.txt | rs-compressor compress - - --auto > output.txt");
+    println!("Example with a dictionary: rs-compressor train-dict json.dict samples/*.json && rs-compressor compress small.json small.rs --lz --dict json.dict");
+    println!("Example reading part of a large log: rs-compressor compress app.log app.rs --lz --seekable && rs-compressor decompress app.rs - --auto --range 1000000:4096");
+    println!("Example patching a release: rs-compressor diff app-1.0.bin app-1.1.bin app.patch && rs-compressor patch app-1.0.bin app.patch app-1.1.bin");
+    std::process::exit(1);
+}
+
+fn read_input(input: &str) -> io::Result<Vec<u8>> {
+    if input == "-" {
+        let mut buffer = Vec::new();
+        io::stdin().read_to_end(&mut buffer)?;
+        Ok(buffer)
+    } else {
+        fs::read(input)
+    }
+}
+
+fn write_output(output: &str, data: &[u8]) -> io::Result<()> {
+    if output == "-" {
+        io::stdout().write_all(data)?;
+        Ok(())
+    } else {
+        fs::write(output, data)
+    }
+}
+
+/// Prints a status line, on stderr when the output itself goes to stdout so
+/// the data stays clean.
+fn status(output: &str, message: impl fmt::Display) {
+    if output == "-" {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
+/// The value following `name`, as in `--dict FILE`.
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .map(|i| args.get(i + 1).map(String::as_str).unwrap_or_else(|| fail(&format!("Missing value for {}", name))))
+}
+
+/// `Some(None)` for `--auto`, `None` for an unknown flag.
+fn parse_algorithm(flag: &str) -> Option<Option<FileAlgorithm>> {
+    match flag {
+        "--auto" => Some(None),
+        "--rle" => Some(Some(FileAlgorithm::RLE)),
+        "--lz" => Some(Some(FileAlgorithm::LZ)),
+        "--packbits" => Some(Some(FileAlgorithm::PackBits)),
+        "--bwt" => Some(Some(FileAlgorithm::BWT)),
+        "--rc" => Some(Some(FileAlgorithm::RC)),
+        "--lz4" => Some(Some(FileAlgorithm::LZ4)),
+        _ => None,
+    }
+}
+
+fn train_dict(args: &[String]) -> io::Result<()> {
+    let size = match option_value(args, "--size") {
+        Some(size) => size.parse().unwrap_or_else(|_| fail("Invalid --size value")),
+        None => dictionary::DEFAULT_SIZE,
+    };
+    let positional: Vec<&String> = args
+        .iter()
+        .enumerate()
+        .filter(|(i, arg)| *arg != "--size" && (*i == 0 || args[i - 1] != "--size"))
+        .map(|(_, arg)| arg)
+        .collect();
+    if positional.len() < 2 {
+        print_usage();
+    }
+
+    let samples = positional[1..]
+        .iter()
+        .map(fs::read)
+        .collect::<io::Result<Vec<_>>>()?;
+    let trained = dictionary::train(&samples, size);
+    println!(
+        "Trained {} byte dictionary {:08x} from {} samples",
+        trained.len(),
+        dictionary::dictionary_id(&trained),
+        samples.len()
+    );
+    fs::write(positional[0], trained)
+}
+
+fn diff(args: &[String]) -> io::Result<()> {
+    if args.len() != 3 {
+        print_usage();
+    }
+    let old = read_input(&args[0])?;
+    let new = read_input(&args[1])?;
+    let patch = codec::diff(&old, &new);
+    println!("Patch is {} bytes for a {} byte file", patch.len(), new.len());
+    write_output(&args[2], &patch)
+}
+
+fn patch(args: &[String]) -> io::Result<()> {
+    if args.len() != 3 {
+        print_usage();
+    }
+    let old = read_input(&args[0])?;
+    let patch = read_input(&args[1])?;
+    let new = codec::patch(&old, &patch).unwrap_or_else(|e| fail(e));
+    write_output(&args[2], &new)
+}
+
+/// Archive path for a file on disk: its normal components joined with '/',
+/// so leading '/', '.' and '..' never end up in the archive.
+fn archive_path(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn mode_and_mtime(metadata: &fs::Metadata) -> (u32, u64) {
+    #[cfg(unix)]
+    let mode = std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o7777;
+    #[cfg(not(unix))]
+    let mode = match (metadata.is_dir(), metadata.permissions().readonly()) {
+        (true, _) => 0o755,
+        (false, true) => 0o444,
+        (false, false) => 0o644,
+    };
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_secs());
+    (mode, mtime)
+}
+
+/// A progress bar on stderr when it is a terminal; nothing otherwise, so
+/// redirected output and scripts stay clean.
+fn progress_bar(label: &'static str) -> Progress<'static> {
+    if !io::stderr().is_terminal() {
+        return Progress::none();
+    }
+    let mut shown = None;
+    Progress::new(move |done, total| {
+        let percent = (done * 100).checked_div(total).unwrap_or(100);
+        if shown == Some(percent) {
+            return;
+        }
+        shown = Some(percent);
+        let filled = percent as usize / 5;
+        eprint!("\r{} [{}{}] {:>3}% {}/{} bytes", label, "#".repeat(filled), " ".repeat(20 - filled), percent, done, total);
+        if done == total {
+            eprintln!();
+        }
+    })
+}
+
+fn print_dedup_stats(output: &str, stats: &DedupStats) {
+    status(
+        output,
+        format_args!(
+            "Deduplicated {} chunks to {} unique ({} to {} bytes), ratio {:.2}x",
+            stats.chunks,
+            stats.unique_chunks,
+            stats.bytes,
+            stats.unique_bytes,
+            stats.ratio()
+        ),
+    );
+}
+
+fn add_to_archive(archive: &mut Archive, path: &Path, algorithm: Option<FileAlgorithm>, dedup: bool) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    let (mode, mtime) = mode_and_mtime(&metadata);
+    let name = archive_path(path);
+    if metadata.is_dir() {
+        if !name.is_empty() {
+            archive.add_directory(&name, mode, mtime).unwrap_or_else(|e| fail(e));
+        }
+        let mut children = fs::read_dir(path)?.map(|entry| entry.map(|e| e.path())).collect::<io::Result<Vec<_>>>()?;
+        children.sort();
+        for child in children {
+            add_to_archive(archive, &child, algorithm, dedup)?;
+        }
+    } else if metadata.is_file() {
+        let contents = fs::read(path)?;
+        let added = match dedup {
+            true => archive.add_file_deduplicated(&name, &contents, mode, mtime, algorithm),
+            false => archive.add_file(&name, &contents, mode, mtime, algorithm),
+        };
+        added.unwrap_or_else(|e| fail(e));
+        println!("Added {}", name);
+    } else {
+        eprintln!("Skipping {}: not a regular file or directory", path.display());
+    }
+    Ok(())
+}
+
+/// Header metadata for compressing `input_file`: its name, mtime and mode
+/// with `--keep-metadata`, plus any `--meta KEY=VALUE` pairs.
+fn input_metadata(input_file: &str, options: &[String]) -> io::Result<Option<Metadata>> {
+    let mut metadata = Metadata::default();
+    if options.iter().any(|arg| arg == "--keep-metadata") && input_file != "-" {
+        let (mode, mtime) = mode_and_mtime(&fs::metadata(input_file)?);
+        metadata.filename = Path::new(input_file).file_name().map(|name| name.to_string_lossy().into_owned());
+        metadata.mtime = Some(mtime);
+        metadata.mode = Some(mode);
+    }
+    for (i, _) in options.iter().enumerate().filter(|(_, arg)| *arg == "--meta") {
+        let pair = options.get(i + 1).unwrap_or_else(|| fail("Missing value for --meta"));
+        let (key, value) = pair.split_once('=').unwrap_or_else(|| fail("Invalid --meta value, expected KEY=VALUE"));
+        metadata.fields.push((key.to_string(), value.to_string()));
+    }
+    Ok(Some(metadata).filter(|metadata| !metadata.is_empty()))
+}
+
+/// With a directory as output, the file goes inside it under its stored name.
+fn restored_path(output_file: &str, metadata: &Metadata) -> PathBuf {
+    let output = PathBuf::from(output_file);
+    let stored_name = metadata.filename.as_deref().and_then(|name| Path::new(name).file_name());
+    match stored_name {
+        Some(name) if output.is_dir() => output.join(name),
+        _ => output,
+    }
+}
+
+fn apply_metadata(path: &Path, metadata: &Metadata) -> io::Result<()> {
+    if let Some(mtime) = metadata.mtime {
+        fs::File::options().write(true).open(path)?.set_modified(UNIX_EPOCH + Duration::from_secs(mtime))?;
+    }
+    #[cfg(unix)]
+    if let Some(mode) = metadata.mode {
+        fs::set_permissions(path, std::os::unix::fs::PermissionsExt::from_mode(mode))?;
+    }
+    Ok(())
+}
+
+fn prompt_password(prompt: &str) -> io::Result<String> {
+    rpassword::prompt_password(prompt)
+}
+
+/// The passphrase from `--passphrase-file`, or typed at a prompt.
+fn read_passphrase(options: &[String], confirm: bool) -> io::Result<String> {
+    if let Some(path) = option_value(options, "--passphrase-file") {
+        let contents = fs::read_to_string(path)?;
+        return Ok(contents.trim_end_matches(['\r', '\n']).to_string());
+    }
+    let prompt = |text: &str| {
+        prompt_password(text)
+            .unwrap_or_else(|_| fail("No terminal to prompt for a passphrase; use --passphrase-file"))
+    };
+    let passphrase = prompt("Passphrase: ");
+    if confirm && prompt("Repeat passphrase: ") != passphrase {
+        fail("Passphrases do not match");
+    }
+    if passphrase.is_empty() {
+        fail("Passphrase must not be empty");
+    }
+    Ok(passphrase)
+}
+
+/// Decodes a compressed file without writing anything, reporting blocks that
+/// needed repair.
+fn test_file(args: &[String]) -> io::Result<()> {
+    let Some(input_file) = args.first() else {
+        print_usage();
+    };
+    let dictionary = match option_value(args, "--dict") {
+        Some(path) => Some(fs::read(path)?),
+        None => None,
+    };
+    let mut input = read_input(input_file)?;
+    if crypto::is_encrypted(&input) {
+        let passphrase = read_passphrase(args, false)?;
+        input = crypto::decrypt(&input, &passphrase).unwrap_or_else(|e| fail(&format!("{}: {}", input_file, e)));
+    }
+
+    let (output, repaired) = codec::decompress_with_repairs(&input, dictionary.as_deref())
+        .unwrap_or_else(|e| fail(&format!("{}: {}", input_file, e)));
+    for block in &repaired {
+        println!("{}: repaired block {} from parity", input_file, block);
+    }
+    println!("{}: OK, {} bytes", input_file, output.len());
+    Ok(())
+}
+
+/// Writes out whatever survives in a damaged file and reports what was lost.
+fn recover(args: &[String]) -> io::Result<()> {
+    if args.len() < 2 {
+        print_usage();
+    }
+    let (input_file, output_file) = (&args[0], &args[1]);
+    let dictionary = match option_value(args, "--dict") {
+        Some(path) => Some(fs::read(path)?),
+        None => None,
+    };
+    let mut input = read_input(input_file)?;
+    if crypto::is_encrypted(&input) {
+        let passphrase = read_passphrase(args, false)?;
+        input = crypto::decrypt(&input, &passphrase).unwrap_or_else(|e| fail(&format!("{}: {}", input_file, e)));
+    }
+    let header = read_header(&input).unwrap_or_else(|e| fail(&format!("{}: {}", input_file, e)));
+    codec::check_dictionary(&header, dictionary.as_deref()).unwrap_or_else(|e| fail(e));
+
+    if header.block_size.is_none() {
+        // Single-stream files have no block boundaries to resynchronise at.
+        return match codec::decompress(&input, dictionary.as_deref()) {
+            Ok(output) => {
+                println!("{}: not damaged, recovered all {} bytes", input_file, output.len());
+                write_output(output_file, &output)
+            }
+            Err(e) => fail(&format!(
+                "{}: {}; only block-framed (--seekable) files can be partially recovered",
+                input_file, e
+            )),
+        };
+    }
+
+    let recovery = framed::recover(&input, &header, dictionary.as_deref())
+        .unwrap_or_else(|e| fail(&format!("{}: {}", input_file, e)));
+    if recovery.repaired > 0 {
+        println!("{}: repaired {} blocks from parity", input_file, recovery.repaired);
+    }
+    for range in &recovery.lost {
+        println!("{}: lost bytes {}..{} ({} bytes)", input_file, range.start, range.end, range.end - range.start);
+    }
+    let lost: u64 = recovery.lost.iter().map(|range| range.end - range.start).sum();
+    println!("{}: recovered {} bytes, lost {}", input_file, recovery.data.len(), lost);
+    if recovery.total.is_none() {
+        println!("{}: index is damaged, so data after the last recovered block may be missing", input_file);
+    }
+    write_output(output_file, &recovery.data)
+}
+
+fn archive_command(operation: &str, args: &[String]) -> io::Result<()> {
+    let Some(archive_file) = args.first() else {
+        print_usage();
+    };
+
+    match operation {
+        "create" | "add" => {
+            let dedup = args.iter().any(|arg| arg == "--dedup");
+            let algorithm = match args.iter().find(|arg| arg.starts_with("--") && *arg != "--dedup") {
+                Some(flag) => parse_algorithm(flag).unwrap_or_else(|| fail("Invalid algorithm. Use --rle, --lz, --packbits, --bwt, --rc, --lz4, or --auto")),
+                None => None,
+            };
+            let paths: Vec<&String> = args[1..].iter().filter(|arg| !arg.starts_with("--")).collect();
+            if paths.is_empty() {
+                print_usage();
+            }
+            let mut archive = match operation {
+                "add" => Archive::open(fs::read(archive_file)?).unwrap_or_else(|e| fail(e)),
+                _ => Archive::new(),
+            };
+            for path in paths {
+                add_to_archive(&mut archive, Path::new(path), algorithm, dedup)?;
+            }
+            if dedup {
+                print_dedup_stats(archive_file, &archive.dedup_stats());
+            }
+            fs::write(archive_file, archive.to_bytes())
+        }
+        "list" => {
+            let archive = Archive::open(fs::read(archive_file)?).unwrap_or_else(|e| fail(e));
+            for entry in archive.entries() {
+                let codec = entry.algorithm.map_or("-".to_string(), |a| format!("{:?}", a));
+                println!(
+                    "{:o}\t{}\t{}\t{}\t{}\t{}",
+                    entry.mode,
+                    entry.size,
+                    entry.compressed_size(),
+                    codec,
+                    entry.mtime,
+                    entry.path
+                );
+            }
+            if archive.entries().iter().any(|entry| entry.deduplicated) {
+                print_dedup_stats(archive_file, &archive.dedup_stats());
+            }
+            Ok(())
+        }
+        _ => {
+            let archive = Archive::open(fs::read(archive_file)?).unwrap_or_else(|e| fail(e));
+            let root = Path::new(args.get(1).map_or(".", String::as_str));
+            for entry in archive.entries() {
+                let target = root.join(&entry.path);
+                match entry.kind {
+                    EntryKind::Directory => fs::create_dir_all(&target)?,
+                    EntryKind::File => {
+                        if let Some(parent) = target.parent() {
+                            fs::create_dir_all(parent)?;
+                        }
+                        let contents = archive
+                            .read(entry)
+                            .unwrap_or_else(|e| fail(&format!("Error extracting {}: {}", entry.path, e)));
+                        let file = fs::File::create(&target)?;
+                        (&file).write_all(&contents)?;
+                        file.set_modified(UNIX_EPOCH + Duration::from_secs(entry.mtime))?;
+                        #[cfg(unix)]
+                        fs::set_permissions(&target, std::os::unix::fs::PermissionsExt::from_mode(entry.mode))?;
+                        println!("Extracted {}", entry.path);
+                    }
+                }
+            }
+            // Directory permissions go last, deepest first, so a read-only
+            // directory doesn't stop its own contents from being written.
+            #[cfg(unix)]
+            for entry in archive.entries().iter().rev().filter(|entry| entry.kind == EntryKind::Directory) {
+                fs::set_permissions(root.join(&entry.path), std::os::unix::fs::PermissionsExt::from_mode(entry.mode))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() > 1 && args[1] == "train-dict" {
+        return train_dict(&args[2..]);
+    }
+    if args.len() > 1 && args[1] == "diff" {
+        return diff(&args[2..]);
+    }
+    if args.len() > 1 && args[1] == "patch" {
+        return patch(&args[2..]);
+    }
+    if args.len() > 1 && args[1] == "test" {
+        return test_file(&args[2..]);
+    }
+    if args.len() > 1 && args[1] == "recover" {
+        return recover(&args[2..]);
+    }
+    if args.len() > 1 && ["create", "add", "list", "extract"].contains(&args[1].as_str()) {
+        return archive_command(&args[1], &args[2..]);
+    }
+
+    if args.len() < 5 {
+        print_usage();
+    }
+
+    let operation = &args[1];
+    let input_file = &args[2];
+    let output_file = &args[3];
+    let algorithm = &args[4];
+    let explain = args[5..].iter().any(|arg| arg == "--explain");
+    let dictionary = match option_value(&args[5..], "--dict") {
+        Some(path) => Some(fs::read(path)?),
+        None => None,
+    };
+
+    let parity = match option_value(&args[5..], "--parity") {
+        Some(parity) => parity.parse().unwrap_or_else(|_| fail("Invalid --parity value")),
+        None => 0,
+    };
+    if parity > framed::MAX_PARITY {
+        fail(&format!("--parity can be at most {}", framed::MAX_PARITY));
+    }
+    let seekable = parity > 0 || args[5..].iter().any(|arg| arg == "--seekable");
+    let dedup = args[5..].iter().any(|arg| arg == "--dedup");
+    if seekable && dedup {
+        fail("--dedup cannot be combined with --seekable or --parity");
+    }
+    let block_size = match option_value(&args[5..], "--block-size") {
+        Some(size) => size.parse().unwrap_or_else(|_| fail("Invalid --block-size value")),
+        None => framed::DEFAULT_BLOCK_SIZE,
+    };
+    let range = option_value(&args[5..], "--range").map(|range| {
+        range
+            .split_once(':')
+            .and_then(|(start, length)| Some((start.parse::<u64>().ok()?, length.parse::<usize>().ok()?)))
+            .unwrap_or_else(|| fail("Invalid --range value, expected START:LEN"))
+    });
+
+    let restore_metadata = args[5..].iter().any(|arg| arg == "--restore-metadata");
+    let jscmp_format = match option_value(&args[5..], "--format") {
+        None | Some("rscmp") => false,
+        Some("jscmp") => true,
+        Some(_) => fail("Invalid --format value. Use rscmp or jscmp"),
+    };
+
+    let input_data = read_input(input_file)?;
+    let input_data = if operation == "decompress" && crypto::is_encrypted(&input_data) {
+        let passphrase = read_passphrase(&args[5..], false)?;
+        crypto::decrypt(&input_data, &passphrase).unwrap_or_else(|e| fail(&format!("Error decrypting: {}", e)))
+    } else {
+        input_data
+    };
+
+    let result = match operation.as_str() {
+        "compress" => {
+            let selected_algorithm = match parse_algorithm(algorithm) {
+                Some(Some(selected)) => selected,
+                // JSCMP only has RLE and LZ, and its LZ can't hold zero bytes.
+                Some(None) if jscmp_format => match codec::auto_select(&input_data, None) {
+                    FileAlgorithm::RLE => FileAlgorithm::RLE,
+                    _ if jscmp::supports(&input_data, FileAlgorithm::LZ) => FileAlgorithm::LZ,
+                    _ => FileAlgorithm::RLE,
+                },
+                Some(None) => codec::auto_select(&input_data, dictionary.as_deref()),
+                None => fail("Invalid algorithm. Use --rle, --lz, --packbits, --bwt, --rc, --lz4, or --auto"),
+            };
+
+            // Auto picks BCJ for executables; --filter overrides, and
+            // `--filter none` turns filtering off.
+            let filters = match option_value(&args[5..], "--filter") {
+                Some("none") => Vec::new(),
+                Some(spec) => filters::parse_chain(spec).unwrap_or_else(|e| fail(e)),
+                None if parse_algorithm(algorithm) == Some(None) && !jscmp_format => select_filters(&input_data),
+                None => Vec::new(),
+            };
+
+            if explain {
+                let mut report = codec::explain(&input_data, dictionary.as_deref(), selected_algorithm, &filters);
+                if jscmp_format {
+                    report.candidates = [FileAlgorithm::RLE, FileAlgorithm::LZ]
+                        .into_iter()
+                        .filter_map(|algorithm| Some((algorithm, jscmp::compress(&input_data, algorithm).ok()?.len())))
+                        .collect();
+                }
+                status(output_file, report);
+            }
+            let stopwatch = Stopwatch::start();
+            status(output_file, format_args!("Using {:?} compression", selected_algorithm));
+            if !filters.is_empty() {
+                let names: Vec<String> = filters.iter().map(|filter| filter.to_string()).collect();
+                status(output_file, format_args!("Filters: {}", names.join(",")));
+            }
+            let metadata = input_metadata(input_file, &args[5..])?;
+            let mut progress = progress_bar("Compressing");
+            let compressed = if jscmp_format {
+                if seekable || dedup || dictionary.is_some() || metadata.is_some() || !filters.is_empty() {
+                    fail("--format jscmp files have no room for framing, dedup, dictionaries, metadata or filters");
+                }
+                jscmp::compress(&input_data, selected_algorithm)
+            } else if seekable {
+                let options = FrameOptions { block_size, parity };
+                framed::compress(&input_data, selected_algorithm, &filters, dictionary.as_deref(), metadata.as_ref(), &options, &mut progress)
+            } else if dedup {
+                dedup::compress(&input_data, selected_algorithm, &filters, dictionary.as_deref(), metadata.as_ref(), &mut progress).map(|(compressed, stats)| {
+                    print_dedup_stats(output_file, &stats);
+                    compressed
+                })
+            } else {
+                codec::compress_with_progress(&input_data, selected_algorithm, &filters, dictionary.as_deref(), metadata.as_ref(), &mut progress)
+            };
+            let compressed = compressed.unwrap_or_else(|e| fail(&format!("Error compressing: {}", e)));
+            let json = args[5..].iter().any(|arg| arg == "--json");
+            let stats = (json || args[5..].iter().any(|arg| arg == "--verbose"))
+                .then(|| CompressionStats::collect(&input_data, &compressed, stopwatch.elapsed()));
+            let output = if args[5..].iter().any(|arg| arg == "--encrypt") {
+                let passphrase = read_passphrase(&args[5..], true)?;
+                crypto::encrypt(&compressed, &passphrase).unwrap_or_else(|e| fail(&format!("Error encrypting: {}", e)))
+            } else {
+                compressed
+            };
+            if let Some(mut stats) = stats {
+                // Blocks and matches come from the compressed data, but the
+                // size is that of the file written, encryption included. The
+                // time leaves out the passphrase prompt and key derivation.
+                stats.output_size = output.len() as u64;
+                let report = if json { stats.to_json() } else { stats.to_string() };
+                status(output_file, report);
+            }
+            output
+        }
+        "decompress" => {
+            let decompressed = match (parse_algorithm(algorithm), range) {
+                (Some(_), Some((start, length))) => {
+                    codec::decompress_range(&input_data, start, length, dictionary.as_deref())
+                }
+                (Some(None), None) => {
+                    if jscmp::is_jscmp(&input_data) {
+                        status(output_file, "Detected format: JSCMP");
+                    } else {
+                        let header = read_header(&input_data)
+                            .unwrap_or_else(|e| fail(&format!("Error reading header: {}", e)));
+                        status(output_file, format_args!("Detected algorithm: {:?}", header.algorithm));
+                    }
+                    let mut progress = progress_bar("Decompressing");
+                    codec::decompress_with_progress(&input_data, dictionary.as_deref(), &mut progress).map(|(output, repaired)| {
+                        for block in repaired {
+                            eprintln!("Repaired block {} from parity", block);
+                        }
+                        output
+                    })
+                }
+                (Some(Some(selected)), None) => {
+                    codec::decompress_payload(&input_data, codec::header_algorithm(selected), &[], dictionary.as_deref())
+                }
+                (None, _) => fail("Invalid algorithm. Use --rle, --lz, --packbits, --bwt, --rc, --lz4, or --auto"),
+            };
+
+            decompressed.unwrap_or_else(|e| fail(&format!("Error decompressing: {}", e)))
+        }
+        _ => {
+            eprintln!("Invalid operation. Use compress or decompress");
+            std::process::exit(1);
+        }
+    };
+
+    let metadata = match operation.as_str() {
+        "decompress" if restore_metadata => read_header(&input_data).ok().and_then(|header| header.metadata),
+        _ => None,
+    };
+    for (key, value) in metadata.iter().flat_map(|metadata| &metadata.fields) {
+        status(output_file, format_args!("{}: {}", key, value));
+    }
+    match metadata {
+        // Data written to stdout has no file to restore the rest onto.
+        Some(metadata) if output_file != "-" => {
+            let path = restored_path(output_file, &metadata);
+            fs::write(&path, &result)?;
+            apply_metadata(&path, &metadata)?;
+            println!("Restored {}", path.display());
+        }
+        _ => write_output(output_file, &result)?,
+    }
+    Ok(())
+}
\ No newline at end of file
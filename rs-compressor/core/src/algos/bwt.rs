@@ -0,0 +1,315 @@
+// bzip2-style block sorting: each block goes through a Burrows-Wheeler
+// transform, move-to-front, zero-run encoding and finally Huffman coding.
+//
+// Block layout:
+//   u32 LE  original block length
+//   u32 LE  primary index (row of the sentinel in the sorted matrix)
+//   129     nibble-packed Huffman code lengths for the 258 symbols
+//   ...     Huffman bit stream ending in EOB, padded to a byte boundary
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::huffman::{self, BitReader, BitWriter, Decoder};
+use crate::error;
+use crate::report::Report;
+
+const BLOCK_SIZE: usize = 256 * 1024;
+const RUN_A: u16 = 0;
+const RUN_B: u16 = 1;
+const EOB: u16 = 257;
+const SYMBOLS: usize = 258;
+
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    compress_with_report(input, &mut Report::none()).unwrap_or_default()
+}
+
+pub fn compress_with_report(input: &[u8], report: &mut Report) -> Result<Vec<u8>, &'static str> {
+    let mut compressed = Vec::new();
+    for (i, block) in input.chunks(BLOCK_SIZE).enumerate() {
+        report.at(i * BLOCK_SIZE)?;
+        compress_block(block, &mut compressed);
+    }
+    Ok(compressed)
+}
+
+pub fn decompress(compressed: &[u8]) -> Result<Vec<u8>, &'static str> {
+    decompress_with_report(compressed, &mut Report::none())
+}
+
+pub fn decompress_with_report(compressed: &[u8], report: &mut Report) -> Result<Vec<u8>, &'static str> {
+    let mut decompressed = Vec::new();
+    let mut pos = 0;
+    while pos < compressed.len() {
+        report.at(pos)?;
+        pos += decompress_block(&compressed[pos..], &mut decompressed)?;
+    }
+    Ok(decompressed)
+}
+
+fn compress_block(block: &[u8], output: &mut Vec<u8>) {
+    let (last_column, primary) = transform(block);
+    let symbols = zero_run_encode(&move_to_front(&last_column));
+
+    let mut freqs = vec![0u32; SYMBOLS];
+    for &symbol in &symbols {
+        freqs[symbol as usize] += 1;
+    }
+    let lengths = huffman::code_lengths(&freqs);
+    let codes = huffman::canonical_codes(&lengths);
+
+    output.extend_from_slice(&(block.len() as u32).to_le_bytes());
+    output.extend_from_slice(&(primary as u32).to_le_bytes());
+    huffman::write_lengths(output, &lengths);
+
+    let mut writer = BitWriter::new();
+    for &symbol in &symbols {
+        writer.write(codes[symbol as usize], lengths[symbol as usize]);
+    }
+    output.extend_from_slice(&writer.finish());
+}
+
+fn decompress_block(input: &[u8], output: &mut Vec<u8>) -> Result<usize, &'static str> {
+    let header = input.get(..8).ok_or(error::TRUNCATED_BWT_HEADER)?;
+    let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let primary = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    if len == 0 || len > BLOCK_SIZE || primary == 0 || primary > len {
+        return Err("Invalid BWT block header");
+    }
+
+    let (lengths, table_size) = huffman::read_lengths(&input[8..], SYMBOLS)?;
+    let decoder = Decoder::new(&lengths)?;
+    let mut reader = BitReader::new(&input[8 + table_size..]);
+
+    let mut symbols = Vec::new();
+    loop {
+        let symbol = decoder.decode(&mut reader)?;
+        if symbol == EOB {
+            break;
+        }
+        symbols.push(symbol);
+    }
+
+    let mtf = zero_run_decode(&symbols, len)?;
+    let last_column = move_to_front_decode(&mtf);
+    output.extend_from_slice(&inverse_transform(&last_column, primary)?);
+    Ok(8 + table_size + reader.bytes_consumed())
+}
+
+/// Suffix array by prefix doubling. A suffix that is a prefix of another
+/// sorts first, as if the input ended in a unique smallest sentinel.
+fn suffix_array(input: &[u8]) -> Vec<usize> {
+    let n = input.len();
+    let mut sa: Vec<usize> = (0..n).collect();
+    let mut rank: Vec<usize> = input.iter().map(|&b| b as usize).collect();
+    let mut next_rank = vec![0usize; n];
+    let mut k = 1;
+
+    loop {
+        let key = |i: usize| (rank[i], if i + k < n { rank[i + k] + 1 } else { 0 });
+        sa.sort_unstable_by_key(|&i| key(i));
+
+        next_rank[sa[0]] = 0;
+        for w in 1..n {
+            let step = (key(sa[w - 1]) < key(sa[w])) as usize;
+            next_rank[sa[w]] = next_rank[sa[w - 1]] + step;
+        }
+        core::mem::swap(&mut rank, &mut next_rank);
+
+        if rank[sa[n - 1]] == n - 1 {
+            return sa;
+        }
+        k *= 2;
+    }
+}
+
+/// Returns the last column of the sorted rotation matrix of `block` plus a
+/// sentinel, with the sentinel itself left out and its row returned instead.
+fn transform(block: &[u8]) -> (Vec<u8>, usize) {
+    let sa = suffix_array(block);
+    let mut last_column = Vec::with_capacity(block.len());
+    let mut primary = 0;
+
+    // Row 0 is the sentinel's own rotation, preceded by the final byte.
+    last_column.push(block[block.len() - 1]);
+    for (row, &start) in sa.iter().enumerate() {
+        if start == 0 {
+            primary = row + 1;
+        } else {
+            last_column.push(block[start - 1]);
+        }
+    }
+    (last_column, primary)
+}
+
+fn inverse_transform(last_column: &[u8], primary: usize) -> Result<Vec<u8>, &'static str> {
+    let n = last_column.len();
+    if primary == 0 || primary > n {
+        return Err("Invalid BWT primary index");
+    }
+    // Row r of the full matrix maps to last_column[r], or [r - 1] past the sentinel.
+    let byte_at = |row: usize| last_column[if row < primary { row } else { row - 1 }];
+
+    let mut counts = [0usize; 256];
+    let mut ranks = vec![0usize; n + 1];
+    for row in (0..=n).filter(|&row| row != primary) {
+        let byte = byte_at(row) as usize;
+        ranks[row] = counts[byte];
+        counts[byte] += 1;
+    }
+
+    // The sentinel sorts before every byte, so each byte's first row is offset by one.
+    let mut first_row = [0usize; 256];
+    let mut total = 1;
+    for (byte, &count) in counts.iter().enumerate() {
+        first_row[byte] = total;
+        total += count;
+    }
+
+    let mut output = vec![0u8; n];
+    let mut row = 0;
+    for slot in output.iter_mut().rev() {
+        if row == primary {
+            return Err("Corrupt BWT block");
+        }
+        let byte = byte_at(row);
+        *slot = byte;
+        row = first_row[byte as usize] + ranks[row];
+    }
+    Ok(output)
+}
+
+fn move_to_front(input: &[u8]) -> Vec<u8> {
+    let mut order: Vec<u8> = (0..=255).collect();
+    input
+        .iter()
+        .map(|&byte| {
+            let index = order.iter().position(|&b| b == byte).unwrap();
+            order.copy_within(0..index, 1);
+            order[0] = byte;
+            index as u8
+        })
+        .collect()
+}
+
+fn move_to_front_decode(input: &[u8]) -> Vec<u8> {
+    let mut order: Vec<u8> = (0..=255).collect();
+    input
+        .iter()
+        .map(|&index| {
+            let index = index as usize;
+            let byte = order[index];
+            order.copy_within(0..index, 1);
+            order[0] = byte;
+            byte
+        })
+        .collect()
+}
+
+/// Replaces runs of zeros with RUNA/RUNB digits (bijective base 2) and
+/// shifts every other MTF index up by one, then appends EOB.
+fn zero_run_encode(mtf: &[u8]) -> Vec<u16> {
+    let mut symbols = Vec::with_capacity(mtf.len() + 1);
+    let mut run = 0usize;
+
+    let flush_run = |symbols: &mut Vec<u16>, mut run: usize| {
+        while run > 0 {
+            if run & 1 == 1 {
+                symbols.push(RUN_A);
+                run = (run - 1) / 2;
+            } else {
+                symbols.push(RUN_B);
+                run = (run - 2) / 2;
+            }
+        }
+    };
+
+    for &index in mtf {
+        if index == 0 {
+            run += 1;
+        } else {
+            flush_run(&mut symbols, run);
+            run = 0;
+            symbols.push(index as u16 + 1);
+        }
+    }
+    flush_run(&mut symbols, run);
+    symbols.push(EOB);
+    symbols
+}
+
+fn zero_run_decode(symbols: &[u16], expected_len: usize) -> Result<Vec<u8>, &'static str> {
+    let mut mtf = Vec::with_capacity(expected_len);
+    let mut run = 0usize;
+    let mut weight = 1usize;
+
+    for &symbol in symbols {
+        match symbol {
+            RUN_A | RUN_B => {
+                run += weight * (symbol as usize + 1);
+                weight = weight.checked_mul(2).ok_or("Corrupt BWT zero run")?;
+                if run > expected_len {
+                    return Err("Corrupt BWT zero run");
+                }
+            }
+            _ => {
+                mtf.extend(core::iter::repeat_n(0, run));
+                run = 0;
+                weight = 1;
+                mtf.push((symbol - 1) as u8);
+            }
+        }
+    }
+    mtf.extend(core::iter::repeat_n(0, run));
+
+    if mtf.len() != expected_len {
+        return Err(error::BWT_LENGTH_MISMATCH);
+    }
+    Ok(mtf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bwt_roundtrip() {
+        let input = b"banana bandana banana bandana";
+        let compressed = compress(input);
+        assert_eq!(decompress(&compressed).unwrap(), input.to_vec());
+    }
+
+    #[test]
+    fn test_transform_matches_rotation_sort() {
+        let (last_column, primary) = transform(b"banana");
+        // Sorted rotations of "banana$": $banana, a$banan, ana$ban, anana$b,
+        // banana$, na$bana, nana$ba -> last column "annb$aa".
+        assert_eq!(last_column, b"annbaa".to_vec());
+        assert_eq!(primary, 4);
+        assert_eq!(inverse_transform(&last_column, primary).unwrap(), b"banana".to_vec());
+    }
+
+    #[test]
+    fn test_multiple_blocks_and_runs() {
+        let mut input = vec![b'x'; BLOCK_SIZE + 10];
+        input.extend_from_slice(b"tail of the second block");
+        let compressed = compress(&input);
+        assert!(compressed.len() < 400);
+        assert_eq!(decompress(&compressed).unwrap(), input);
+    }
+
+    #[test]
+    fn test_beats_lz_on_logs() {
+        let input: String = (0..2000)
+            .map(|i| format!("2024-01-01 12:{:02}:{:02} INFO request {} served in {}ms\n", i % 60, i % 59, i, i % 97))
+            .collect();
+        let compressed = compress(input.as_bytes());
+        assert!(compressed.len() * 2 < super::super::lz::compress(input.as_bytes()).len());
+        assert_eq!(decompress(&compressed).unwrap(), input.into_bytes());
+    }
+
+    #[test]
+    fn test_corrupt_header() {
+        assert!(decompress(&[1, 0, 0, 0, 9, 0, 0, 0]).is_err());
+    }
+}
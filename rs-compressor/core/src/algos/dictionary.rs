@@ -0,0 +1,102 @@
+// Dictionary training for the LZ codecs. A dictionary is plain bytes that
+// the encoder and decoder both treat as already-seen history, so even the
+// first bytes of a small payload can be coded as matches.
+//
+// Training is a simplified COVER: every k-mer is scored by how many samples
+// contain it, and fixed-size segments are picked greedily by the total score
+// of the k-mers they cover that no earlier segment already covers. The best
+// segments go last, closest to the data, where short offsets can reach them.
+
+use alloc::collections::{BTreeMap, BTreeSet, BinaryHeap};
+use alloc::vec::Vec;
+
+use crate::checksum::xxh32;
+
+pub const DEFAULT_SIZE: usize = 4096;
+const KMER: usize = 8;
+const SEGMENT: usize = 64;
+const STEP: usize = 16;
+
+/// Identifies a dictionary in compressed headers.
+pub fn dictionary_id(dictionary: &[u8]) -> u32 {
+    xxh32(dictionary, 0)
+}
+
+pub fn train(samples: &[Vec<u8>], size: usize) -> Vec<u8> {
+    // Number of samples each k-mer appears in.
+    let mut frequency: BTreeMap<&[u8], u32> = BTreeMap::new();
+    for sample in samples {
+        let kmers: BTreeSet<&[u8]> = sample.windows(KMER).collect();
+        for kmer in kmers {
+            *frequency.entry(kmer).or_insert(0) += 1;
+        }
+    }
+
+    let score = |segment: &[u8], frequency: &BTreeMap<&[u8], u32>| -> u32 {
+        let kmers: BTreeSet<&[u8]> = segment.windows(KMER).collect();
+        kmers.iter().map(|kmer| frequency.get(kmer).copied().unwrap_or(0)).sum()
+    };
+
+    let mut candidates: BinaryHeap<(u32, usize, usize)> = BinaryHeap::new();
+    for (index, sample) in samples.iter().enumerate() {
+        let mut start = 0;
+        while start + KMER <= sample.len() {
+            let end = (start + SEGMENT).min(sample.len());
+            candidates.push((score(&sample[start..end], &frequency), index, start));
+            start += STEP;
+        }
+    }
+
+    // Scores only go down as k-mers get covered, so a popped candidate whose
+    // refreshed score still beats the next best is the true maximum.
+    let mut segments: Vec<&[u8]> = Vec::new();
+    let mut total = 0;
+    while total < size {
+        let Some((stale, index, start)) = candidates.pop() else {
+            break;
+        };
+        let segment = &samples[index][start..(start + SEGMENT).min(samples[index].len())];
+        let fresh = score(segment, &frequency);
+        if fresh == 0 {
+            continue;
+        }
+        if fresh < stale && candidates.peek().is_some_and(|&(next, _, _)| next > fresh) {
+            candidates.push((fresh, index, start));
+            continue;
+        }
+
+        for kmer in segment.windows(KMER) {
+            frequency.insert(kmer, 0);
+        }
+        segments.push(segment);
+        total += segment.len();
+    }
+
+    let mut dictionary: Vec<u8> = segments.iter().rev().flat_map(|segment| segment.iter().copied()).collect();
+    if dictionary.len() > size {
+        dictionary.drain(..dictionary.len() - size);
+    }
+    dictionary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_training_picks_shared_content() {
+        let samples: Vec<Vec<u8>> = (0..50)
+            .map(|i| format!("{{\"id\":{},\"status\":\"active\",\"region\":\"eu-west-1\",\"tags\":[\"x{}\"]}}", i, i * 7).into_bytes())
+            .collect();
+        let dictionary = train(&samples, 256);
+        assert!(!dictionary.is_empty() && dictionary.len() <= 256);
+        let text = String::from_utf8_lossy(&dictionary);
+        assert!(text.contains("\"status\":\"active\""));
+    }
+
+    #[test]
+    fn test_empty_samples() {
+        assert!(train(&[], 128).is_empty());
+        assert!(train(&[b"abc".to_vec()], 128).is_empty());
+    }
+}
@@ -0,0 +1,282 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::filters::Filter;
+use super::range_coder::{self, Mode};
+use super::{bwt, le, lz, lz4, packbits};
+
+const BWT_MIN_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileType {
+    Text,
+    Binary,
+    Image,
+    Executable,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    RLE,
+    LZ,
+    PackBits,
+    BWT,
+    RC,
+    LZ4,
+}
+
+/// Instruction sets the BCJ filters understand.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Machine {
+    X86,
+    Arm64,
+}
+
+fn u16_at(data: &[u8], pos: usize, big_endian: bool) -> Option<u16> {
+    let bytes = data.get(pos..pos + 2)?.try_into().unwrap();
+    Some(if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+}
+
+/// Reads the target machine from an ELF or PE header. `None` means not an
+/// executable; `Some(None)` one for a machine without a BCJ filter.
+fn detect_executable(data: &[u8]) -> Option<Option<Machine>> {
+    let machine = if data.starts_with(b"\x7FELF") {
+        u16_at(data, 18, data.get(5) == Some(&2))?
+    } else if data.starts_with(b"MZ") {
+        let pe_offset = u32::from_le_bytes(data.get(0x3C..0x40)?.try_into().unwrap()) as usize;
+        if data.get(pe_offset..pe_offset.checked_add(4)?)? != b"PE\0\0" {
+            return None;
+        }
+        u16_at(data, pe_offset + 4, false)?
+    } else {
+        return None;
+    };
+
+    // ELF e_machine 3 / 62 / 183, PE machine 0x14C / 0x8664 / 0xAA64.
+    Some(match machine {
+        3 | 62 | 0x14C | 0x8664 => Some(Machine::X86),
+        183 | 0xAA64 => Some(Machine::Arm64),
+        _ => None,
+    })
+}
+
+/// Filters `Auto` runs before the codec: the BCJ filter matching an
+/// executable's machine, nothing otherwise.
+pub fn select_filters(data: &[u8]) -> Vec<Filter> {
+    match detect_executable(data) {
+        Some(Some(Machine::X86)) => vec![Filter::BcjX86],
+        Some(Some(Machine::Arm64)) => vec![Filter::BcjArm64],
+        _ => Vec::new(),
+    }
+}
+
+pub fn detect_file_type(data: &[u8]) -> FileType {
+    if detect_executable(data).is_some() {
+        return FileType::Executable;
+    }
+
+    // Check for common text file characteristics
+    let is_text = data.iter().all(|&b| b.is_ascii() || b == b'\n' || b == b'\r' || b == b'\t');
+    if is_text {
+        return FileType::Text;
+    }
+
+    // Check for common image file signatures
+    if data.len() >= 8 {
+        let header = &data[0..8];
+        if header.starts_with(b"\x89PNG\r\n\x1a\n") || 
+           header.starts_with(b"\xFF\xD8\xFF") || // JPEG
+           header.starts_with(b"GIF87a") || 
+           header.starts_with(b"GIF89a") {
+            return FileType::Image;
+        }
+    }
+
+    // If not text or image, consider it binary
+    FileType::Binary
+}
+
+pub fn select_algorithm(file_type: FileType, data: &[u8]) -> Algorithm {
+    match file_type {
+        FileType::Text => {
+            // Block sorting wins on text once there is enough of it to
+            // amortise the per-block Huffman table; below that use LZ
+            if data.len() >= BWT_MIN_SIZE {
+                Algorithm::BWT
+            } else {
+                Algorithm::LZ
+            }
+        },
+        FileType::Binary => {
+            // For binary files, check if RLE would be more efficient
+            let rle_ratio = calculate_rle_ratio(data);
+            if rle_ratio > 0.5 {
+                Algorithm::PackBits
+            } else {
+                Algorithm::LZ
+            }
+        },
+        FileType::Image => {
+            // For images, use RLE as they often have repeated pixels
+            Algorithm::PackBits
+        },
+        FileType::Executable => {
+            // Machine code is full of repeated instruction sequences, more
+            // so once BCJ has made call targets absolute
+            Algorithm::LZ
+        },
+        FileType::Unknown => {
+            // Default to LZ for unknown types
+            Algorithm::LZ
+        }
+    }
+}
+
+pub fn calculate_rle_ratio(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+
+    let mut current_byte = data[0];
+    let mut total_runs = 1;
+    let mut total_bytes = 1;
+
+    for &byte in &data[1..] {
+        if byte != current_byte {
+            current_byte = byte;
+            total_runs += 1;
+        }
+        total_bytes += 1;
+    }
+
+    // Calculate compression ratio (lower is better)
+    (total_runs * 2) as f64 / total_bytes as f64
+}
+
+/// Shannon entropy of the byte distribution, in bits per byte (0.0 to 8.0).
+pub fn calculate_entropy(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+
+    let mut counts = [0usize; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+
+    let total = data.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * libm::log2(p)
+        })
+        .sum()
+}
+
+/// Everything `Auto` looked at when picking an algorithm, plus the payload
+/// size each codec actually produces for the same input.
+#[derive(Debug)]
+pub struct SelectionReport {
+    pub file_type: FileType,
+    pub entropy: f64,
+    pub run_ratio: f64,
+    pub filters: Vec<Filter>,
+    pub candidates: Vec<(Algorithm, usize)>,
+    pub selected: Algorithm,
+}
+
+impl SelectionReport {
+    /// The candidate with the smallest compressed payload.
+    pub fn smallest(&self) -> Option<Algorithm> {
+        self.candidates
+            .iter()
+            .min_by_key(|(_, size)| *size)
+            .map(|(algorithm, _)| *algorithm)
+    }
+}
+
+impl fmt::Display for SelectionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Detected type: {:?}", self.file_type)?;
+        writeln!(f, "Entropy: {:.3} bits/byte", self.entropy)?;
+        writeln!(f, "Run ratio: {:.3}", self.run_ratio)?;
+        if !self.filters.is_empty() {
+            let names: Vec<String> = self.filters.iter().map(Filter::to_string).collect();
+            writeln!(f, "Filters: {}", names.join(","))?;
+        }
+        writeln!(f, "Candidates:")?;
+        for (algorithm, size) in &self.candidates {
+            writeln!(f, "  {:?}: {} bytes", algorithm, size)?;
+        }
+        write!(f, "Selected: {:?}", self.selected)
+    }
+}
+
+/// Runs the same detection as `Auto` and compresses the input with every
+/// codec so the decision can be compared against the real sizes.
+pub fn explain_selection(data: &[u8]) -> SelectionReport {
+    let file_type = detect_file_type(data);
+    let selected = select_algorithm(file_type, data);
+
+    SelectionReport {
+        file_type,
+        entropy: calculate_entropy(data),
+        run_ratio: calculate_rle_ratio(data),
+        filters: select_filters(data),
+        candidates: vec![
+            (Algorithm::RLE, le::compress(data).len()),
+            (Algorithm::LZ, lz::compress(data).len()),
+            (Algorithm::PackBits, packbits::compress(data).len()),
+            (Algorithm::BWT, bwt::compress(data).len()),
+            (Algorithm::RC, range_coder::compress(data, Mode::LZ).len()),
+            (Algorithm::LZ4, lz4::compress(data).len()),
+        ],
+        selected,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entropy_bounds() {
+        assert_eq!(calculate_entropy(b""), 0.0);
+        assert_eq!(calculate_entropy(b"AAAA"), 0.0);
+        let all_bytes: Vec<u8> = (0..=255).collect();
+        assert!((calculate_entropy(&all_bytes) - 8.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_explain_matches_auto() {
+        let input = b"the quick brown fox jumps over the quick brown dog";
+        let report = explain_selection(input);
+        assert_eq!(report.file_type, FileType::Text);
+        assert_eq!(report.selected, select_algorithm(detect_file_type(input), input));
+        assert_eq!(report.candidates.len(), 6);
+        assert_eq!(report.candidates[1], (Algorithm::LZ, lz::compress(input).len()));
+    }
+
+    #[test]
+    fn test_executables_get_bcj() {
+        let mut elf = vec![0; 64];
+        elf[..6].copy_from_slice(b"\x7FELF\x02\x01");
+        elf[18] = 62;
+        assert_eq!(detect_file_type(&elf), FileType::Executable);
+        assert_eq!(select_filters(&elf), [Filter::BcjX86]);
+
+        let mut pe = vec![0; 256];
+        pe[..2].copy_from_slice(b"MZ");
+        pe[0x3C] = 0x80;
+        pe[0x80..0x84].copy_from_slice(b"PE\0\0");
+        pe[0x84..0x86].copy_from_slice(&0xAA64u16.to_le_bytes());
+        assert_eq!(select_filters(&pe), [Filter::BcjArm64]);
+
+        assert!(select_filters(b"MZ but not really a PE file").is_empty());
+    }
+}
@@ -0,0 +1,266 @@
+// Reversible preprocessing filters that run before the codec. None of them
+// change the size of the data; they rearrange it so the codec finds more
+// repetition:
+//
+// - Delta stores each byte minus the one `stride` bytes earlier, turning
+//   slowly changing samples into runs of small values.
+// - BCJ rewrites relative branch targets in machine code as absolute ones,
+//   so repeated calls to the same function become identical bytes. The x86
+//   form converts E8/E9 call and jump displacements, the ARM64 form BL
+//   instructions.
+// - Transpose splits fixed-width records into byte planes (every first byte,
+//   then every second byte, ...), grouping similar fields together.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::error;
+
+/// Filters a header can record.
+pub const MAX_FILTERS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    /// Byte distance to the value subtracted, 1 to 255.
+    Delta(u8),
+    BcjX86,
+    BcjArm64,
+    /// Record width in bytes, 2 to 255.
+    Transpose(u8),
+}
+
+impl Filter {
+    /// ID and parameter as stored in the header.
+    pub fn to_bytes(self) -> [u8; 2] {
+        match self {
+            Filter::Delta(stride) => [1, stride],
+            Filter::BcjX86 => [2, 0],
+            Filter::BcjArm64 => [3, 0],
+            Filter::Transpose(width) => [4, width],
+        }
+    }
+
+    pub fn from_bytes(bytes: [u8; 2]) -> Result<Self, &'static str> {
+        let filter = match bytes[0] {
+            1 => Filter::Delta(bytes[1]),
+            2 => Filter::BcjX86,
+            3 => Filter::BcjArm64,
+            4 => Filter::Transpose(bytes[1]),
+            _ => return Err(error::UNKNOWN_FILTER),
+        };
+        filter.check()?;
+        Ok(filter)
+    }
+
+    fn check(self) -> Result<(), &'static str> {
+        match self {
+            Filter::Delta(0) => Err("Delta stride must be at least 1"),
+            Filter::Transpose(0 | 1) => Err("Transpose width must be at least 2"),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn encode(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Filter::Delta(stride) => delta_encode(data, stride as usize),
+            Filter::BcjX86 => bcj_x86(data, true),
+            Filter::BcjArm64 => bcj_arm64(data, true),
+            Filter::Transpose(width) => transpose(data, width as usize),
+        }
+    }
+
+    pub fn decode(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Filter::Delta(stride) => delta_decode(data, stride as usize),
+            Filter::BcjX86 => bcj_x86(data, false),
+            Filter::BcjArm64 => bcj_arm64(data, false),
+            Filter::Transpose(width) => untranspose(data, width as usize),
+        }
+    }
+}
+
+/// Same syntax `parse_chain` accepts, e.g. `delta:4` or `bcj-x86`.
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::Delta(stride) => write!(f, "delta:{}", stride),
+            Filter::BcjX86 => write!(f, "bcj-x86"),
+            Filter::BcjArm64 => write!(f, "bcj-arm64"),
+            Filter::Transpose(width) => write!(f, "transpose:{}", width),
+        }
+    }
+}
+
+/// Parses a comma-separated chain such as `delta:2,transpose:4`. The
+/// filters run in the order given when compressing.
+pub fn parse_chain(spec: &str) -> Result<Vec<Filter>, &'static str> {
+    let mut filters = Vec::new();
+    for part in spec.split(',') {
+        let (name, param) = match part.split_once(':') {
+            Some((name, param)) => (name, Some(param.parse::<u8>().map_err(|_| "Invalid filter parameter")?)),
+            None => (part, None),
+        };
+        let filter = match (name, param) {
+            ("delta", stride) => Filter::Delta(stride.unwrap_or(1)),
+            ("bcj-x86", None) => Filter::BcjX86,
+            ("bcj-arm64", None) => Filter::BcjArm64,
+            ("transpose", Some(width)) => Filter::Transpose(width),
+            ("transpose", None) => return Err("Transpose needs a record width, e.g. transpose:4"),
+            _ => return Err(error::UNKNOWN_FILTER_SPEC),
+        };
+        filter.check()?;
+        filters.push(filter);
+    }
+    check_chain(&filters)?;
+    Ok(filters)
+}
+
+pub fn check_chain(filters: &[Filter]) -> Result<(), &'static str> {
+    if filters.len() > MAX_FILTERS {
+        return Err("Too many filters");
+    }
+    filters.iter().try_for_each(|filter| filter.check())
+}
+
+/// Runs `filters` in order.
+pub fn encode_chain(filters: &[Filter], data: &[u8]) -> Vec<u8> {
+    filters.iter().fold(data.to_vec(), |data, filter| filter.encode(&data))
+}
+
+/// Undoes `encode_chain`, running the filters in reverse.
+pub fn decode_chain(filters: &[Filter], data: Vec<u8>) -> Vec<u8> {
+    filters.iter().rev().fold(data, |data, filter| filter.decode(&data))
+}
+
+fn delta_encode(data: &[u8], stride: usize) -> Vec<u8> {
+    let mut output = data.to_vec();
+    for i in stride..data.len() {
+        output[i] = data[i].wrapping_sub(data[i - stride]);
+    }
+    output
+}
+
+fn delta_decode(data: &[u8], stride: usize) -> Vec<u8> {
+    let mut output = data.to_vec();
+    for i in stride..output.len() {
+        output[i] = output[i].wrapping_add(output[i - stride]);
+    }
+    output
+}
+
+/// Converts E8 (call) and E9 (jmp) rel32 displacements to absolute targets
+/// and back. Only displacements within +-16 MiB are touched, and the result
+/// is kept in that range by sign-extending from 25 bits, so decoding picks
+/// out exactly the instructions encoding converted.
+fn bcj_x86(data: &[u8], encode: bool) -> Vec<u8> {
+    let mut output = data.to_vec();
+    let mut i = 0;
+    while i + 5 <= output.len() {
+        if output[i] != 0xE8 && output[i] != 0xE9 {
+            i += 1;
+            continue;
+        }
+        let value = i32::from_le_bytes(output[i + 1..i + 5].try_into().unwrap());
+        if !matches!(output[i + 4], 0x00 | 0xFF) {
+            i += 1;
+            continue;
+        }
+        let position = (i + 5) as i32;
+        let converted = if encode { value.wrapping_add(position) } else { value.wrapping_sub(position) };
+        let converted = (converted << 7) >> 7;
+        output[i + 1..i + 5].copy_from_slice(&converted.to_le_bytes());
+        i += 5;
+    }
+    output
+}
+
+/// Converts the word offset of ARM64 BL instructions to an absolute target
+/// and back. Instructions are 4-byte aligned.
+fn bcj_arm64(data: &[u8], encode: bool) -> Vec<u8> {
+    let mut output = data.to_vec();
+    for (i, word) in output.chunks_exact_mut(4).enumerate() {
+        let instruction = u32::from_le_bytes(word.try_into().unwrap());
+        if instruction & 0xFC00_0000 != 0x9400_0000 {
+            continue;
+        }
+        let position = i as u32;
+        let offset = instruction & 0x03FF_FFFF;
+        let converted = if encode { offset.wrapping_add(position) } else { offset.wrapping_sub(position) };
+        word.copy_from_slice(&(0x9400_0000 | (converted & 0x03FF_FFFF)).to_le_bytes());
+    }
+    output
+}
+
+/// Byte planes of the whole records; a partial record at the end is kept
+/// as it is.
+fn transpose(data: &[u8], width: usize) -> Vec<u8> {
+    let records = data.len() / width;
+    let mut output = Vec::with_capacity(data.len());
+    for plane in 0..width {
+        output.extend((0..records).map(|record| data[record * width + plane]));
+    }
+    output.extend_from_slice(&data[records * width..]);
+    output
+}
+
+fn untranspose(data: &[u8], width: usize) -> Vec<u8> {
+    let records = data.len() / width;
+    let mut output = vec![0; data.len()];
+    for plane in 0..width {
+        for record in 0..records {
+            output[record * width + plane] = data[plane * records + record];
+        }
+    }
+    output[records * width..].copy_from_slice(&data[records * width..]);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filters_roundtrip() {
+        let data: Vec<u8> = (0..10_007u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+        let chains = [
+            vec![Filter::Delta(1)],
+            vec![Filter::Delta(4), Filter::Transpose(8)],
+            vec![Filter::BcjX86],
+            vec![Filter::BcjArm64],
+            vec![Filter::Transpose(3), Filter::BcjX86],
+        ];
+        for chain in chains {
+            let encoded = encode_chain(&chain, &data);
+            assert_eq!(encoded.len(), data.len());
+            assert_eq!(decode_chain(&chain, encoded), data, "{:?}", chain);
+        }
+    }
+
+    #[test]
+    fn test_bcj_makes_repeated_calls_identical() {
+        // The same call target from three places: only the absolute form
+        // repeats.
+        let mut code = vec![0x90; 64];
+        for site in [0usize, 20, 40] {
+            code[site] = 0xE8;
+            code[site + 1..site + 5].copy_from_slice(&(1000 - (site as i32 + 5)).to_le_bytes());
+        }
+        let encoded = Filter::BcjX86.encode(&code);
+        assert_eq!(encoded[1..5], encoded[21..25]);
+        assert_eq!(encoded[1..5], encoded[41..45]);
+        assert_eq!(Filter::BcjX86.decode(&encoded), code);
+    }
+
+    #[test]
+    fn test_parse_chain() {
+        assert_eq!(parse_chain("delta:2,transpose:4").unwrap(), [Filter::Delta(2), Filter::Transpose(4)]);
+        assert_eq!(parse_chain("bcj-arm64").unwrap(), [Filter::BcjArm64]);
+        for spec in ["delta:0", "transpose", "bcj-x86:1", "zip", "delta,delta,delta,delta,delta"] {
+            assert!(parse_chain(spec).is_err(), "{}", spec);
+        }
+        let chain = [Filter::Delta(3), Filter::BcjX86];
+        let text: Vec<String> = chain.iter().map(Filter::to_string).collect();
+        assert_eq!(parse_chain(&text.join(",")).unwrap(), chain);
+    }
+}
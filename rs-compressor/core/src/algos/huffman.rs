@@ -0,0 +1,261 @@
+// Canonical, length-limited Huffman coding over small alphabets, plus the
+// MSB-first bit writer and reader it is serialised with.
+
+use alloc::collections::BinaryHeap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+
+use crate::error;
+
+pub const MAX_CODE_LEN: u8 = 15;
+
+/// Builds code lengths for the given symbol frequencies, none longer than
+/// `MAX_CODE_LEN`. Unused symbols get length 0.
+pub fn code_lengths(freqs: &[u32]) -> Vec<u8> {
+    let mut scaled: Vec<u32> = freqs.to_vec();
+    loop {
+        let lengths = unlimited_code_lengths(&scaled);
+        if lengths.iter().all(|&len| len <= MAX_CODE_LEN) {
+            return lengths;
+        }
+        // Flatten the distribution and try again, keeping used symbols used.
+        for freq in scaled.iter_mut().filter(|freq| **freq > 0) {
+            *freq = (*freq >> 1) | 1;
+        }
+    }
+}
+
+fn unlimited_code_lengths(freqs: &[u32]) -> Vec<u8> {
+    let mut lengths = vec![0u8; freqs.len()];
+    let used: Vec<usize> = (0..freqs.len()).filter(|&s| freqs[s] > 0).collect();
+    if used.len() == 1 {
+        lengths[used[0]] = 1;
+    }
+    if used.len() <= 1 {
+        return lengths;
+    }
+
+    // Nodes 0..len are leaves; internal nodes are appended with their parent
+    // links filled in as the heap is merged.
+    let mut parent: Vec<usize> = vec![usize::MAX; freqs.len()];
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> = used
+        .iter()
+        .map(|&s| Reverse((freqs[s] as u64, s)))
+        .collect();
+
+    while heap.len() > 1 {
+        let Reverse((a_weight, a)) = heap.pop().unwrap();
+        let Reverse((b_weight, b)) = heap.pop().unwrap();
+        let node = parent.len();
+        parent.push(usize::MAX);
+        parent[a] = node;
+        parent[b] = node;
+        heap.push(Reverse((a_weight + b_weight, node)));
+    }
+
+    for &symbol in &used {
+        let mut depth = 0u32;
+        let mut node = symbol;
+        while parent[node] != usize::MAX {
+            node = parent[node];
+            depth += 1;
+        }
+        lengths[symbol] = depth.min(u8::MAX as u32) as u8;
+    }
+    lengths
+}
+
+/// Assigns canonical codes from code lengths: shorter codes first, ties
+/// broken by symbol order.
+pub fn canonical_codes(lengths: &[u8]) -> Vec<u32> {
+    let mut counts = [0u32; MAX_CODE_LEN as usize + 1];
+    for &len in lengths.iter().filter(|&&len| len > 0) {
+        counts[len as usize] += 1;
+    }
+
+    let mut next = [0u32; MAX_CODE_LEN as usize + 2];
+    let mut code = 0;
+    for len in 1..=MAX_CODE_LEN as usize {
+        code = (code + counts[len - 1]) << 1;
+        next[len] = code;
+    }
+
+    lengths
+        .iter()
+        .map(|&len| {
+            if len == 0 {
+                return 0;
+            }
+            let code = next[len as usize];
+            next[len as usize] += 1;
+            code
+        })
+        .collect()
+}
+
+/// Writes the lengths as packed nibbles, two symbols per byte.
+pub fn write_lengths(output: &mut Vec<u8>, lengths: &[u8]) {
+    for pair in lengths.chunks(2) {
+        let high = pair[0];
+        let low = pair.get(1).copied().unwrap_or(0);
+        output.push((high << 4) | low);
+    }
+}
+
+/// Reads `symbols` nibble-packed lengths, returning them and the bytes used.
+pub fn read_lengths(input: &[u8], symbols: usize) -> Result<(Vec<u8>, usize), &'static str> {
+    let size = symbols.div_ceil(2);
+    let packed = input.get(..size).ok_or(error::TRUNCATED_HUFFMAN_TABLE)?;
+    let lengths = packed
+        .iter()
+        .flat_map(|&byte| [byte >> 4, byte & 0x0F])
+        .take(symbols)
+        .collect();
+    Ok((lengths, size))
+}
+
+pub struct Decoder {
+    counts: [u32; MAX_CODE_LEN as usize + 1],
+    symbols: Vec<u16>,
+}
+
+impl Decoder {
+    pub fn new(lengths: &[u8]) -> Result<Self, &'static str> {
+        let mut counts = [0u32; MAX_CODE_LEN as usize + 1];
+        for &len in lengths {
+            if len > MAX_CODE_LEN {
+                return Err("Invalid Huffman code length");
+            }
+            if len > 0 {
+                counts[len as usize] += 1;
+            }
+        }
+
+        let mut symbols = Vec::new();
+        for len in 1..=MAX_CODE_LEN {
+            for (symbol, _) in lengths.iter().enumerate().filter(|(_, &l)| l == len) {
+                symbols.push(symbol as u16);
+            }
+        }
+        if symbols.is_empty() {
+            return Err("Empty Huffman table");
+        }
+
+        Ok(Self { counts, symbols })
+    }
+
+    pub fn decode(&self, reader: &mut BitReader) -> Result<u16, &'static str> {
+        let mut code = 0u32;
+        let mut first = 0u32;
+        let mut index = 0u32;
+        for len in 1..=MAX_CODE_LEN as usize {
+            code |= reader.read_bit()?;
+            let count = self.counts[len];
+            if code < first + count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("Invalid Huffman code")
+    }
+}
+
+#[derive(Default)]
+pub struct BitWriter {
+    output: Vec<u8>,
+    acc: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write(&mut self, value: u32, bits: u8) {
+        self.acc = (self.acc << bits) | value as u64;
+        self.bits += bits as u32;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.output.push((self.acc >> self.bits) as u8);
+        }
+    }
+
+    /// Pads the final byte with zero bits and returns the buffer.
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.output.push((self.acc << (8 - self.bits)) as u8);
+        }
+        self.output
+    }
+}
+
+pub struct BitReader<'a> {
+    input: &'a [u8],
+    pos: usize,
+    bit: u8,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Self { input, pos: 0, bit: 0 }
+    }
+
+    pub fn read_bit(&mut self) -> Result<u32, &'static str> {
+        let byte = *self.input.get(self.pos).ok_or(error::TRUNCATED_BIT_STREAM)?;
+        let bit = (byte >> (7 - self.bit)) & 1;
+        self.bit += 1;
+        if self.bit == 8 {
+            self.bit = 0;
+            self.pos += 1;
+        }
+        Ok(bit as u32)
+    }
+
+    /// Bytes consumed so far, counting a partially read byte as used.
+    pub fn bytes_consumed(&self) -> usize {
+        self.pos + (self.bit > 0) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_huffman_roundtrip() {
+        let symbols: Vec<u16> = b"abracadabra alakazam".iter().map(|&b| b as u16).collect();
+        let mut freqs = vec![0u32; 256];
+        for &s in &symbols {
+            freqs[s as usize] += 1;
+        }
+        let lengths = code_lengths(&freqs);
+        let codes = canonical_codes(&lengths);
+
+        let mut writer = BitWriter::new();
+        for &s in &symbols {
+            writer.write(codes[s as usize], lengths[s as usize]);
+        }
+        let encoded = writer.finish();
+
+        let decoder = Decoder::new(&lengths).unwrap();
+        let mut reader = BitReader::new(&encoded);
+        let decoded: Vec<u16> = symbols.iter().map(|_| decoder.decode(&mut reader).unwrap()).collect();
+        assert_eq!(decoded, symbols);
+    }
+
+    #[test]
+    fn test_lengths_are_limited() {
+        // Fibonacci frequencies produce the deepest possible tree.
+        let mut freqs = vec![1u32, 1];
+        while freqs.len() < 30 {
+            let next = freqs[freqs.len() - 1] + freqs[freqs.len() - 2];
+            freqs.push(next);
+        }
+        let lengths = code_lengths(&freqs);
+        assert!(lengths.iter().all(|&len| (1..=MAX_CODE_LEN).contains(&len)));
+    }
+}
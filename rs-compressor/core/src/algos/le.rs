@@ -1,13 +1,22 @@
+use alloc::vec::Vec;
+
+use crate::report::Report;
+
 pub fn compress(input: &[u8]) -> Vec<u8> {
+    compress_with_report(input, &mut Report::none()).unwrap_or_default()
+}
+
+pub fn compress_with_report(input: &[u8], report: &mut Report) -> Result<Vec<u8>, &'static str> {
     let mut compressed = Vec::new();
     if input.is_empty() {
-        return compressed;
+        return Ok(compressed);
     }
 
     let mut current = input[0];
     let mut count = 1u8;
 
-    for &byte in &input[1..] {
+    for (i, &byte) in input.iter().enumerate().skip(1) {
+        report.at(i)?;
         if byte == current && count < 255 {
             count += 1;
         } else {
@@ -22,22 +31,27 @@ pub fn compress(input: &[u8]) -> Vec<u8> {
     compressed.push(count);
     compressed.push(current);
 
-    compressed
+    Ok(compressed)
 }
 
 pub fn decompress(compressed: &[u8]) -> Vec<u8> {
+    decompress_with_report(compressed, &mut Report::none()).unwrap_or_default()
+}
+
+pub fn decompress_with_report(compressed: &[u8], report: &mut Report) -> Result<Vec<u8>, &'static str> {
     let mut decompressed = Vec::new();
     let mut i = 0;
     let len = compressed.len();
 
     while i + 1 < len {
+        report.at(i)?;
         let count = compressed[i] as usize;
         let byte = compressed[i + 1];
-        decompressed.extend(std::iter::repeat(byte).take(count));
+        decompressed.extend(core::iter::repeat_n(byte, count));
         i += 2;
     }
 
-    decompressed
+    Ok(decompressed)
 }
 
 #[cfg(test)]
@@ -0,0 +1,217 @@
+use alloc::vec::Vec;
+
+use crate::error;
+use crate::report::Report;
+
+const WINDOW_SIZE: usize = 4096;
+const MAX_MATCH: usize = 18;
+
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    compress_from(input, 0, &mut Report::none()).unwrap_or_default()
+}
+
+/// Compresses `input` as if `dictionary` had been seen just before it. Only
+/// the last 4095 bytes of the dictionary are reachable.
+pub fn compress_with_dictionary(input: &[u8], dictionary: &[u8]) -> Vec<u8> {
+    compress_with_report(input, dictionary, &mut Report::none()).unwrap_or_default()
+}
+
+/// Like `compress_with_dictionary` (pass an empty one for none), reporting
+/// to `report`.
+pub fn compress_with_report(input: &[u8], dictionary: &[u8], report: &mut Report) -> Result<Vec<u8>, &'static str> {
+    let history = &dictionary[dictionary.len().saturating_sub(WINDOW_SIZE - 1)..];
+    if history.is_empty() {
+        return compress_from(input, 0, report);
+    }
+    compress_from(&[history, input].concat(), history.len(), report)
+}
+
+// Encodes `input[start..]`, allowing matches into `input[..start]`.
+fn compress_from(input: &[u8], start: usize, report: &mut Report) -> Result<Vec<u8>, &'static str> {
+    let mut output = Vec::new();
+    let mut i = start;
+    
+    while i < input.len() {
+        report.at(i - start)?;
+        let mut best_match = (0, 0);
+        
+        // Search for the longest match in the sliding window. Offsets are
+        // stored in 12 bits, so the furthest usable position is 4095 back.
+        let start = i.saturating_sub(WINDOW_SIZE - 1);
+        for j in start..i {
+            let mut match_len = 0;
+            while i + match_len < input.len() 
+                  && input[j + match_len] == input[i + match_len] 
+                  && match_len < MAX_MATCH {
+                match_len += 1;
+            }
+            
+            // A length-3 match with an offset below 256 would encode as a
+            // zero flag byte, which the decoder reads as a literal.
+            let ambiguous = match_len == 3 && i - j < 256;
+            if match_len > best_match.1 && !ambiguous {
+                best_match = (i - j, match_len);
+            }
+        }
+        
+        if best_match.1 >= 3 {
+            // Encode as a match
+            output.push(((best_match.0 >> 4) & 0xF0) as u8 | (best_match.1 - 3) as u8);
+            output.push((best_match.0 & 0xFF) as u8);
+            i += best_match.1;
+        } else {
+            // Encode as a literal
+            output.push(0);
+            output.push(input[i]);
+            i += 1;
+        }
+    }
+    
+    Ok(output)
+}
+
+/// What an encoded stream is made of.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenStats {
+    pub matches: usize,
+    pub literals: usize,
+    /// Bytes covered by the matches.
+    pub match_bytes: usize,
+}
+
+impl TokenStats {
+    pub fn average_match_length(&self) -> f64 {
+        if self.matches == 0 {
+            return 0.0;
+        }
+        self.match_bytes as f64 / self.matches as f64
+    }
+
+    pub fn add(&mut self, other: TokenStats) {
+        self.matches += other.matches;
+        self.literals += other.literals;
+        self.match_bytes += other.match_bytes;
+    }
+}
+
+/// Counts the tokens of an encoded stream without decoding it.
+pub fn token_stats(input: &[u8]) -> TokenStats {
+    let mut stats = TokenStats::default();
+    for token in input.chunks_exact(2) {
+        match token[0] {
+            0 => stats.literals += 1,
+            flag => {
+                stats.matches += 1;
+                stats.match_bytes += (flag & 0x0F) as usize + 3;
+            }
+        }
+    }
+    stats
+}
+
+pub fn decompress(input: &[u8]) -> Result<Vec<u8>, &'static str> {
+    decompress_with_report(input, &[], &mut Report::none())
+}
+
+pub fn decompress_with_dictionary(input: &[u8], dictionary: &[u8]) -> Result<Vec<u8>, &'static str> {
+    decompress_with_report(input, dictionary, &mut Report::none())
+}
+
+/// Like `decompress_with_dictionary` (pass an empty one for none),
+/// reporting to `report`.
+pub fn decompress_with_report(input: &[u8], dictionary: &[u8], report: &mut Report) -> Result<Vec<u8>, &'static str> {
+    let history = &dictionary[dictionary.len().saturating_sub(WINDOW_SIZE - 1)..];
+    let mut output = history.to_vec();
+    decompress_into(input, &mut output, report)?;
+    Ok(output.split_off(history.len()))
+}
+
+fn decompress_into(input: &[u8], output: &mut Vec<u8>, report: &mut Report) -> Result<(), &'static str> {
+    // Every token is two bytes, so an odd length means the data was cut.
+    if !input.len().is_multiple_of(2) {
+        return Err(error::TRUNCATED_LZ);
+    }
+
+    let mut i = 0;
+    
+    while i + 1 < input.len() {
+        report.at(i)?;
+        let flag = input[i];
+        
+        if flag == 0 {
+            // Literal
+            output.push(input[i + 1]);
+            i += 2;
+        } else {
+            // Match
+            let length = ((flag & 0x0F) + 3) as usize;
+            let offset = ((flag as usize & 0xF0) << 4) | (input[i + 1] as usize);
+            
+            if offset == 0 || offset > output.len() {
+                return Err("Invalid LZ match offset");
+            }
+            let start = output.len() - offset;
+            for j in 0..length {
+                output.push(output[start + j]);
+            }
+            
+            i += 2;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rle_roundtrip() {
+        let input = b"AAABBBCCCCCDDDDE";
+        let compressed = compress(input);
+        let decompressed = decompress(&compressed).unwrap();
+        assert_eq!(input.to_vec(), decompressed);
+    }
+
+    #[test]
+    fn test_compress() {
+        let input = b"AAABBBCCCCCDDDDE";
+        let compressed = compress(input);
+        assert_eq!(compressed.len(), 26);
+    }
+
+    #[test]
+    fn test_decompress() {
+        let input = b"AAABBBCCCCCDDDDE";
+        let compressed = compress(input);
+        let decompressed = decompress(&compressed).unwrap();
+        assert_eq!(input.to_vec(), decompressed);
+    }
+
+    #[test]
+    fn test_dictionary_roundtrip() {
+        let dictionary = b"{\"status\":\"active\",\"region\":\"eu-west-1\"}";
+        let input = b"{\"id\":7,\"status\":\"active\",\"region\":\"eu-west-1\"}";
+        let compressed = compress_with_dictionary(input, dictionary);
+        assert!(compressed.len() < compress(input).len() / 2);
+        assert_eq!(decompress_with_dictionary(&compressed, dictionary).unwrap(), input.to_vec());
+    }
+
+    #[test]
+    fn test_corrupt_input_is_an_error() {
+        // A match before any output, and a token cut in half.
+        assert!(decompress(&[0x10, 0x05]).is_err());
+        assert!(decompress(&[0, b'a', 0]).is_err());
+    }
+
+    #[test]
+    fn test_token_stats() {
+        let input = b"abcabcabcabcabcabcXYZ";
+        let stats = token_stats(&compress(input));
+        assert_eq!(stats.literals + stats.match_bytes, input.len());
+        assert!(stats.matches >= 1);
+        assert_eq!(stats.average_match_length(), stats.match_bytes as f64 / stats.matches as f64);
+        assert_eq!(token_stats(&[]).average_match_length(), 0.0);
+    }
+}
@@ -0,0 +1,345 @@
+// LZ4 block and frame formats, compatible with the reference `lz4` tool.
+//
+// A block is a series of sequences: a token (literal length in the high
+// nibble, match length - 4 in the low nibble, 15 meaning "more bytes
+// follow"), the literals, a u16 LE offset and any extra match length bytes.
+// The last sequence carries literals only.
+//
+// Frames written here use independent 64 KiB blocks with block checksums,
+// the content size and a content checksum. The reader accepts any valid
+// frame, including linked blocks and skippable frames.
+
+use alloc::vec::Vec;
+
+use super::match_finder::HashChain;
+use crate::checksum::xxh32;
+use crate::error;
+use crate::report::Report;
+
+pub const FRAME_MAGIC: u32 = 0x184D_2204;
+const SKIPPABLE_MAGIC_MASK: u32 = 0xFFFF_FFF0;
+const SKIPPABLE_MAGIC: u32 = 0x184D_2A50;
+
+const MIN_MATCH: usize = 4;
+const MAX_DISTANCE: usize = 65_535;
+const MAX_CHAIN: usize = 32;
+// A match may not start within the last 12 bytes, and the last 5 bytes are
+// always literals.
+const MF_LIMIT: usize = 12;
+const LAST_LITERALS: usize = 5;
+
+const BLOCK_SIZE: usize = 64 * 1024;
+const FLG_VERSION: u8 = 0x40;
+const FLG_BLOCK_INDEPENDENT: u8 = 0x20;
+const FLG_BLOCK_CHECKSUM: u8 = 0x10;
+const FLG_CONTENT_SIZE: u8 = 0x08;
+const FLG_CONTENT_CHECKSUM: u8 = 0x04;
+const FLG_DICT_ID: u8 = 0x01;
+const BD_64KB: u8 = 4 << 4;
+const UNCOMPRESSED_BIT: u32 = 0x8000_0000;
+
+/// Compresses `input` into a single LZ4 block.
+pub fn compress_block(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() + input.len() / 255 + 16);
+    let mut chain = HashChain::new(input.len(), MAX_DISTANCE, MAX_CHAIN, MIN_MATCH);
+    let match_limit = input.len().saturating_sub(MF_LIMIT);
+    let mut anchor = 0;
+    let mut pos = 0;
+
+    while pos < match_limit {
+        let max_len = input.len() - LAST_LITERALS - pos;
+        match chain.find(input, pos, max_len) {
+            Some((distance, len)) => {
+                write_sequence(&mut output, &input[anchor..pos], Some((distance, len)));
+                for p in pos..pos + len {
+                    chain.insert(input, p);
+                }
+                pos += len;
+                anchor = pos;
+            }
+            None => {
+                chain.insert(input, pos);
+                pos += 1;
+            }
+        }
+    }
+
+    write_sequence(&mut output, &input[anchor..], None);
+    output
+}
+
+fn write_length(output: &mut Vec<u8>, mut len: usize) {
+    while len >= 255 {
+        output.push(255);
+        len -= 255;
+    }
+    output.push(len as u8);
+}
+
+fn write_sequence(output: &mut Vec<u8>, literals: &[u8], token_match: Option<(usize, usize)>) {
+    let lit_nibble = literals.len().min(15);
+    let match_extra = token_match.map_or(0, |(_, len)| len - MIN_MATCH);
+    output.push(((lit_nibble as u8) << 4) | match_extra.min(15) as u8);
+    if literals.len() >= 15 {
+        write_length(output, literals.len() - 15);
+    }
+    output.extend_from_slice(literals);
+
+    if let Some((distance, _)) = token_match {
+        output.extend_from_slice(&(distance as u16).to_le_bytes());
+        if match_extra >= 15 {
+            write_length(output, match_extra - 15);
+        }
+    }
+}
+
+fn read_length(input: &[u8], pos: &mut usize) -> Result<usize, &'static str> {
+    let mut len = 0usize;
+    loop {
+        let byte = *input.get(*pos).ok_or(error::TRUNCATED_LZ4_LENGTH)?;
+        *pos += 1;
+        len = len.checked_add(byte as usize).ok_or("LZ4 length overflow")?;
+        if byte != 255 {
+            return Ok(len);
+        }
+    }
+}
+
+/// Decodes one LZ4 block, appending to `output`. Matches may reach back
+/// into whatever `output` already holds, which is how linked blocks and
+/// dictionaries are handled.
+pub fn decompress_block_into(input: &[u8], output: &mut Vec<u8>) -> Result<(), &'static str> {
+    let mut pos = 0;
+    while pos < input.len() {
+        let token = input[pos];
+        pos += 1;
+
+        let mut lit_len = (token >> 4) as usize;
+        if lit_len == 15 {
+            lit_len += read_length(input, &mut pos)?;
+        }
+        let literals = input.get(pos..pos + lit_len).ok_or(error::TRUNCATED_LZ4_LITERALS)?;
+        output.extend_from_slice(literals);
+        pos += lit_len;
+
+        // The final sequence ends after its literals.
+        if pos == input.len() {
+            break;
+        }
+
+        let offset = input.get(pos..pos + 2).ok_or(error::TRUNCATED_LZ4_OFFSET)?;
+        let distance = u16::from_le_bytes([offset[0], offset[1]]) as usize;
+        pos += 2;
+        if distance == 0 || distance > output.len() {
+            return Err("Invalid LZ4 match offset");
+        }
+
+        let mut match_len = (token & 0x0F) as usize;
+        if match_len == 15 {
+            match_len += read_length(input, &mut pos)?;
+        }
+        match_len += MIN_MATCH;
+
+        let start = output.len() - distance;
+        for i in 0..match_len {
+            output.push(output[start + i]);
+        }
+    }
+    Ok(())
+}
+
+pub fn decompress_block(input: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let mut output = Vec::new();
+    decompress_block_into(input, &mut output)?;
+    Ok(output)
+}
+
+/// Compresses `input` into a complete LZ4 frame.
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    compress_with_report(input, &mut Report::none()).unwrap_or_default()
+}
+
+pub fn compress_with_report(input: &[u8], report: &mut Report) -> Result<Vec<u8>, &'static str> {
+    let mut output = Vec::new();
+    output.extend_from_slice(&FRAME_MAGIC.to_le_bytes());
+
+    let descriptor_start = output.len();
+    output.push(FLG_VERSION | FLG_BLOCK_INDEPENDENT | FLG_BLOCK_CHECKSUM | FLG_CONTENT_SIZE | FLG_CONTENT_CHECKSUM);
+    output.push(BD_64KB);
+    output.extend_from_slice(&(input.len() as u64).to_le_bytes());
+    let header_checksum = (xxh32(&output[descriptor_start..], 0) >> 8) as u8;
+    output.push(header_checksum);
+
+    for (i, block) in input.chunks(BLOCK_SIZE).enumerate() {
+        report.at(i * BLOCK_SIZE)?;
+        let compressed = compress_block(block);
+        let (size, data) = if compressed.len() < block.len() {
+            (compressed.len() as u32, &compressed[..])
+        } else {
+            (block.len() as u32 | UNCOMPRESSED_BIT, block)
+        };
+        output.extend_from_slice(&size.to_le_bytes());
+        output.extend_from_slice(data);
+        output.extend_from_slice(&xxh32(data, 0).to_le_bytes());
+    }
+
+    output.extend_from_slice(&0u32.to_le_bytes());
+    output.extend_from_slice(&xxh32(input, 0).to_le_bytes());
+    Ok(output)
+}
+
+fn read_u32(input: &[u8], pos: usize) -> Result<u32, &'static str> {
+    let bytes = input.get(pos..pos + 4).ok_or(error::TRUNCATED_LZ4_FRAME)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Returns true if `input` starts with an LZ4 frame or skippable frame.
+pub fn is_frame(input: &[u8]) -> bool {
+    read_u32(input, 0).is_ok_and(|magic| magic == FRAME_MAGIC || magic & SKIPPABLE_MAGIC_MASK == SKIPPABLE_MAGIC)
+}
+
+/// Decodes every frame in `input`, skipping skippable frames.
+pub fn decompress(input: &[u8]) -> Result<Vec<u8>, &'static str> {
+    decompress_with_report(input, &mut Report::none())
+}
+
+pub fn decompress_with_report(input: &[u8], report: &mut Report) -> Result<Vec<u8>, &'static str> {
+    let mut output = Vec::new();
+    let mut pos = 0;
+    while pos < input.len() {
+        pos += decompress_frame(&input[pos..], &mut output, pos, report)?;
+    }
+    Ok(output)
+}
+
+// Decodes the frame at the start of `input`, which sits `offset` bytes into
+// what the caller is decoding, and returns its length.
+fn decompress_frame(input: &[u8], output: &mut Vec<u8>, offset: usize, report: &mut Report) -> Result<usize, &'static str> {
+    let magic = read_u32(input, 0)?;
+    if magic & SKIPPABLE_MAGIC_MASK == SKIPPABLE_MAGIC {
+        let size = read_u32(input, 4)? as usize;
+        return if input.len() >= 8 + size { Ok(8 + size) } else { Err(error::TRUNCATED_LZ4_SKIPPABLE) };
+    }
+    if magic != FRAME_MAGIC {
+        return Err(error::INVALID_LZ4_MAGIC);
+    }
+
+    let flg = *input.get(4).ok_or(error::TRUNCATED_LZ4_FRAME)?;
+    let bd = *input.get(5).ok_or(error::TRUNCATED_LZ4_FRAME)?;
+    if flg & 0xC0 != FLG_VERSION || flg & 0x02 != 0 || bd & 0x8F != 0 {
+        return Err(error::UNSUPPORTED_LZ4_DESCRIPTOR);
+    }
+    let max_block_size = match (bd >> 4) & 0x07 {
+        4 => 64 * 1024,
+        5 => 256 * 1024,
+        6 => 1024 * 1024,
+        7 => 4 * 1024 * 1024,
+        _ => return Err("Invalid LZ4 block maximum size"),
+    };
+
+    let mut pos = 6;
+    let content_size = if flg & FLG_CONTENT_SIZE != 0 {
+        let bytes = input.get(pos..pos + 8).ok_or(error::TRUNCATED_LZ4_FRAME)?;
+        pos += 8;
+        let mut size = [0u8; 8];
+        size.copy_from_slice(bytes);
+        Some(u64::from_le_bytes(size))
+    } else {
+        None
+    };
+    if flg & FLG_DICT_ID != 0 {
+        return Err(error::UNSUPPORTED_LZ4_DICTIONARY);
+    }
+
+    let header_checksum = *input.get(pos).ok_or(error::TRUNCATED_LZ4_FRAME)?;
+    if header_checksum != (xxh32(&input[4..pos], 0) >> 8) as u8 {
+        return Err(error::LZ4_HEADER_CHECKSUM);
+    }
+    pos += 1;
+
+    let frame_start = output.len();
+    loop {
+        report.at(offset + pos)?;
+        let size = read_u32(input, pos)?;
+        pos += 4;
+        if size == 0 {
+            break;
+        }
+
+        let len = (size & !UNCOMPRESSED_BIT) as usize;
+        if len > max_block_size {
+            return Err("LZ4 block exceeds the frame's maximum block size");
+        }
+        let data = input.get(pos..pos + len).ok_or(error::TRUNCATED_LZ4_BLOCK)?;
+        pos += len;
+
+        if flg & FLG_BLOCK_CHECKSUM != 0 {
+            if read_u32(input, pos)? != xxh32(data, 0) {
+                return Err(error::LZ4_BLOCK_CHECKSUM);
+            }
+            pos += 4;
+        }
+
+        if size & UNCOMPRESSED_BIT != 0 {
+            output.extend_from_slice(data);
+        } else if flg & FLG_BLOCK_INDEPENDENT != 0 {
+            output.extend_from_slice(&decompress_block(data)?);
+        } else {
+            // Linked blocks may refer back into the previous 64 KiB of this frame.
+            let window_start = output.len().saturating_sub(MAX_DISTANCE).max(frame_start);
+            let mut window = output.split_off(window_start);
+            decompress_block_into(data, &mut window)?;
+            output.extend_from_slice(&window);
+        }
+    }
+
+    let content = &output[frame_start..];
+    if content_size.is_some_and(|size| size != content.len() as u64) {
+        return Err(error::LZ4_CONTENT_SIZE);
+    }
+    if flg & FLG_CONTENT_CHECKSUM != 0 {
+        if read_u32(input, pos)? != xxh32(content, 0) {
+            return Err(error::LZ4_CONTENT_CHECKSUM);
+        }
+        pos += 4;
+    }
+    Ok(pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_roundtrip() {
+        let input = b"abcabcabcabcabcabcabcabc hello hello hello hello world!".repeat(20);
+        let compressed = compress_block(&input);
+        assert!(compressed.len() < input.len() / 4);
+        assert_eq!(decompress_block(&compressed).unwrap(), input);
+    }
+
+    #[test]
+    fn test_short_and_empty_blocks() {
+        for input in [&b""[..], b"a", b"abcdabcdabcd", b"aaaaaaaaaaaaaaaaa"] {
+            assert_eq!(decompress_block(&compress_block(input)).unwrap(), input.to_vec());
+        }
+    }
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let mut input: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        input.extend((0..5000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8));
+        let frame = compress(&input);
+        assert!(is_frame(&frame));
+        assert_eq!(decompress(&frame).unwrap(), input);
+    }
+
+    #[test]
+    fn test_frame_checksum_detects_corruption() {
+        let input = b"some data that will be corrupted in transit".repeat(10);
+        let mut frame = compress(&input);
+        let last = frame.len() - 10;
+        frame[last] ^= 0x01;
+        assert!(decompress(&frame).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+// Hash-chain match finder shared by the LZ-family codecs. Every position is
+// hashed on its first `min_match` bytes; each hash bucket points at the most
+// recent position and `prev` links back to older positions with the same hash.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+const HASH_BITS: u32 = 16;
+const NONE: u32 = u32::MAX;
+
+pub struct HashChain {
+    head: Vec<u32>,
+    prev: Vec<u32>,
+    window: usize,
+    max_chain: usize,
+    min_match: usize,
+}
+
+impl HashChain {
+    /// `min_match` must be 3 or 4; `window` is the largest distance returned.
+    pub fn new(len: usize, window: usize, max_chain: usize, min_match: usize) -> Self {
+        Self {
+            head: vec![NONE; 1 << HASH_BITS],
+            prev: vec![NONE; len],
+            window,
+            max_chain,
+            min_match,
+        }
+    }
+
+    fn hash(&self, data: &[u8], pos: usize) -> usize {
+        let mut key = 0u32;
+        for &byte in &data[pos..pos + self.min_match] {
+            key = (key << 8) | byte as u32;
+        }
+        (key.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+    }
+
+    /// Records `pos` so later positions can match against it.
+    pub fn insert(&mut self, data: &[u8], pos: usize) {
+        if pos + self.min_match > data.len() {
+            return;
+        }
+        let hash = self.hash(data, pos);
+        self.prev[pos] = self.head[hash];
+        self.head[hash] = pos as u32;
+    }
+
+    /// Longest earlier match for `pos` as `(distance, length)`, capped at
+    /// `max_len`. Does not insert `pos` itself.
+    pub fn find(&self, data: &[u8], pos: usize, max_len: usize) -> Option<(usize, usize)> {
+        let max_len = max_len.min(data.len() - pos);
+        if max_len < self.min_match {
+            return None;
+        }
+
+        let mut best: Option<(usize, usize)> = None;
+        let mut candidate = self.head[self.hash(data, pos)];
+        let mut chain = 0;
+
+        while candidate != NONE && chain < self.max_chain {
+            let start = candidate as usize;
+            let distance = pos - start;
+            if distance > self.window {
+                break;
+            }
+
+            let best_len = best.map_or(self.min_match - 1, |(_, len)| len);
+            if data[start + best_len] == data[pos + best_len] {
+                let len = match_length(data, start, pos, max_len);
+                if len > best_len {
+                    best = Some((distance, len));
+                    if len == max_len {
+                        break;
+                    }
+                }
+            }
+
+            candidate = self.prev[start];
+            chain += 1;
+        }
+
+        best
+    }
+}
+
+/// Number of equal bytes at `a` and `b` (with `a < b`), up to `max_len`.
+pub fn match_length(data: &[u8], a: usize, b: usize, max_len: usize) -> usize {
+    data[a..]
+        .iter()
+        .zip(&data[b..b + max_len])
+        .take_while(|(x, y)| x == y)
+        .count()
+}
@@ -0,0 +1,11 @@
+pub mod bwt;
+pub mod dictionary;
+pub mod filters;
+pub mod huffman;
+pub mod le;
+pub mod lz;
+pub mod lz4;
+pub mod match_finder;
+pub mod packbits;
+pub mod range_coder;
+pub mod file_type;
@@ -0,0 +1,144 @@
+// PackBits-style RLE. Each control byte is followed by its payload:
+//   0x00..=0x7F  literal run, the next (c + 1) bytes are copied as-is
+//   0x80..=0xFE  repeat run, the next byte is repeated (c - 0x80 + 3) times
+//   0xFF         long repeat run, a u16 LE extra length and the byte follow,
+//                the byte is repeated (extra + LONG_RUN_MIN) times
+// Literal runs cost one control byte per 128 bytes, so incompressible input
+// grows by at most 1/128.
+
+use alloc::vec::Vec;
+
+use crate::error;
+use crate::report::Report;
+
+const MAX_LITERAL: usize = 128;
+const MIN_RUN: usize = 3;
+const MAX_SHORT_RUN: usize = 0xFE - 0x80 + MIN_RUN;
+const LONG_RUN_MIN: usize = MAX_SHORT_RUN + 1;
+const MAX_LONG_RUN: usize = u16::MAX as usize + LONG_RUN_MIN;
+const ESCAPE: u8 = 0xFF;
+
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    compress_with_report(input, &mut Report::none()).unwrap_or_default()
+}
+
+pub fn compress_with_report(input: &[u8], report: &mut Report) -> Result<Vec<u8>, &'static str> {
+    let mut compressed = Vec::with_capacity(input.len() + input.len() / MAX_LITERAL + 1);
+    let mut literal_start = 0;
+    let mut i = 0;
+
+    while i < input.len() {
+        report.at(i)?;
+        let byte = input[i];
+        let mut run = 1;
+        while i + run < input.len() && input[i + run] == byte && run < MAX_LONG_RUN {
+            run += 1;
+        }
+
+        if run >= MIN_RUN {
+            flush_literals(&mut compressed, &input[literal_start..i]);
+            if run <= MAX_SHORT_RUN {
+                compressed.push((run - MIN_RUN + 0x80) as u8);
+            } else {
+                compressed.push(ESCAPE);
+                compressed.extend_from_slice(&((run - LONG_RUN_MIN) as u16).to_le_bytes());
+            }
+            compressed.push(byte);
+            i += run;
+            literal_start = i;
+        } else {
+            i += run;
+        }
+    }
+
+    flush_literals(&mut compressed, &input[literal_start..]);
+    Ok(compressed)
+}
+
+fn flush_literals(output: &mut Vec<u8>, literals: &[u8]) {
+    for chunk in literals.chunks(MAX_LITERAL) {
+        output.push((chunk.len() - 1) as u8);
+        output.extend_from_slice(chunk);
+    }
+}
+
+pub fn decompress(compressed: &[u8]) -> Result<Vec<u8>, &'static str> {
+    decompress_with_report(compressed, &mut Report::none())
+}
+
+pub fn decompress_with_report(compressed: &[u8], report: &mut Report) -> Result<Vec<u8>, &'static str> {
+    let mut decompressed = Vec::new();
+    let mut i = 0;
+
+    while i < compressed.len() {
+        report.at(i)?;
+        let control = compressed[i];
+        i += 1;
+
+        match control {
+            0x00..=0x7F => {
+                let len = control as usize + 1;
+                let literals = compressed.get(i..i + len).ok_or(error::TRUNCATED_LITERAL_RUN)?;
+                decompressed.extend_from_slice(literals);
+                i += len;
+            }
+            ESCAPE => {
+                let extra = compressed.get(i..i + 2).ok_or(error::TRUNCATED_LONG_RUN)?;
+                let run = u16::from_le_bytes([extra[0], extra[1]]) as usize + LONG_RUN_MIN;
+                let byte = *compressed.get(i + 2).ok_or(error::TRUNCATED_LONG_RUN)?;
+                decompressed.extend(core::iter::repeat_n(byte, run));
+                i += 3;
+            }
+            _ => {
+                let run = (control - 0x80) as usize + MIN_RUN;
+                let byte = *compressed.get(i).ok_or(error::TRUNCATED_REPEAT_RUN)?;
+                decompressed.extend(core::iter::repeat_n(byte, run));
+                i += 1;
+            }
+        }
+    }
+
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packbits_roundtrip() {
+        let input = b"AAABBBCCCCCDDDDE";
+        let compressed = compress(input);
+        assert_eq!(decompress(&compressed).unwrap(), input.to_vec());
+    }
+
+    #[test]
+    fn test_empty_input() {
+        assert!(compress(b"").is_empty());
+        assert!(decompress(b"").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_bounded_expansion() {
+        let input: Vec<u8> = (0..10_000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let compressed = compress(&input);
+        assert!(compressed.len() <= input.len() + input.len().div_ceil(128));
+        assert_eq!(decompress(&compressed).unwrap(), input);
+    }
+
+    #[test]
+    fn test_long_runs() {
+        let mut input = vec![0u8; 70_000];
+        input.extend_from_slice(b"xy");
+        input.extend(vec![9u8; 300]);
+        let compressed = compress(&input);
+        assert!(compressed.len() < 20);
+        assert_eq!(decompress(&compressed).unwrap(), input);
+    }
+
+    #[test]
+    fn test_truncated_input() {
+        assert!(decompress(&[0x05, b'a']).is_err());
+        assert!(decompress(&[ESCAPE, 0x01]).is_err());
+    }
+}
@@ -0,0 +1,599 @@
+// Adaptive binary range coder in the style of LZMA, with bit-tree models for
+// multi-symbol alphabets. On top of it sit three codecs:
+//   Order0  every byte coded with one adaptive byte model
+//   Order1  the byte model is selected by the previous byte
+//   LZ      hash-chain LZ77 parse whose literals, lengths and distances are
+//           all range coded, for a high-ratio cold storage mode
+//
+// Payload layout: mode byte, u64 LE original length, range coder stream.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::match_finder::{match_length, HashChain};
+use crate::error;
+use crate::report::Report;
+
+const TOP: u32 = 1 << 24;
+const PROB_BITS: u32 = 11;
+const PROB_INIT: u16 = 1 << (PROB_BITS - 1);
+const MOVE_BITS: u32 = 5;
+
+const LZ_WINDOW: usize = 1 << 22;
+const LZ_MAX_CHAIN: usize = 256;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = MIN_MATCH + 8 + 8 + 255;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Order0,
+    Order1,
+    LZ,
+}
+
+pub struct Encoder {
+    output: Vec<u8>,
+    low: u64,
+    range: u32,
+    cache: u8,
+    cache_size: u64,
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self {
+            output: Vec::new(),
+            low: 0,
+            range: u32::MAX,
+            cache: 0,
+            cache_size: 1,
+        }
+    }
+
+    fn shift_low(&mut self) {
+        if self.low < 0xFF00_0000 || self.low > 0xFFFF_FFFF {
+            let carry = (self.low >> 32) as u8;
+            let mut byte = self.cache;
+            loop {
+                self.output.push(byte.wrapping_add(carry));
+                byte = 0xFF;
+                self.cache_size -= 1;
+                if self.cache_size == 0 {
+                    break;
+                }
+            }
+            self.cache = (self.low >> 24) as u8;
+        }
+        self.cache_size += 1;
+        self.low = (self.low & 0x00FF_FFFF) << 8;
+    }
+
+    pub fn encode_bit(&mut self, prob: &mut u16, bit: u32) {
+        let bound = (self.range >> PROB_BITS) * *prob as u32;
+        if bit == 0 {
+            self.range = bound;
+            *prob += ((1 << PROB_BITS) - *prob) >> MOVE_BITS;
+        } else {
+            self.low += bound as u64;
+            self.range -= bound;
+            *prob -= *prob >> MOVE_BITS;
+        }
+        while self.range < TOP {
+            self.range <<= 8;
+            self.shift_low();
+        }
+    }
+
+    /// Writes `bits` low bits of `value` with fixed 50% probability.
+    pub fn encode_direct(&mut self, value: u32, bits: u32) {
+        for i in (0..bits).rev() {
+            self.range >>= 1;
+            if (value >> i) & 1 == 1 {
+                self.low += self.range as u64;
+            }
+            while self.range < TOP {
+                self.range <<= 8;
+                self.shift_low();
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        for _ in 0..5 {
+            self.shift_low();
+        }
+        self.output
+    }
+}
+
+pub struct Decoder<'a> {
+    input: &'a [u8],
+    pos: usize,
+    range: u32,
+    code: u32,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(input: &'a [u8]) -> Result<Self, &'static str> {
+        if input.len() < 5 {
+            return Err(error::TRUNCATED_RC_STREAM);
+        }
+        let code = u32::from_be_bytes([input[1], input[2], input[3], input[4]]);
+        Ok(Self { input, pos: 5, range: u32::MAX, code })
+    }
+
+    fn next_byte(&mut self) -> u8 {
+        // Reading past the end yields zeros; callers bound the symbol count.
+        let byte = self.input.get(self.pos).copied().unwrap_or(0);
+        self.pos += 1;
+        byte
+    }
+
+    /// Whether decoding has run well past the end of the input. The encoder's
+    /// flush means a valid stream never does, so a corrupt length can't make
+    /// the decoder spin on zeros for gigabytes.
+    fn overrun(&self) -> bool {
+        self.pos > self.input.len() + 4
+    }
+
+    fn normalize(&mut self) {
+        while self.range < TOP {
+            self.range <<= 8;
+            self.code = (self.code << 8) | self.next_byte() as u32;
+        }
+    }
+
+    pub fn decode_bit(&mut self, prob: &mut u16) -> u32 {
+        let bound = (self.range >> PROB_BITS) * *prob as u32;
+        let bit = if self.code < bound {
+            self.range = bound;
+            *prob += ((1 << PROB_BITS) - *prob) >> MOVE_BITS;
+            0
+        } else {
+            self.code -= bound;
+            self.range -= bound;
+            *prob -= *prob >> MOVE_BITS;
+            1
+        };
+        self.normalize();
+        bit
+    }
+
+    pub fn decode_direct(&mut self, bits: u32) -> u32 {
+        let mut value = 0;
+        for _ in 0..bits {
+            self.range >>= 1;
+            let bit = (self.code >= self.range) as u32;
+            if bit == 1 {
+                self.code -= self.range;
+            }
+            value = (value << 1) | bit;
+            self.normalize();
+        }
+        value
+    }
+}
+
+/// Adaptive model for `BITS`-bit symbols, coded most significant bit first
+/// with each bit's probability conditioned on the bits above it.
+#[derive(Clone)]
+pub struct BitTree<const BITS: u32> {
+    probs: Vec<u16>,
+}
+
+impl<const BITS: u32> Default for BitTree<BITS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const BITS: u32> BitTree<BITS> {
+    pub fn new() -> Self {
+        Self { probs: vec![PROB_INIT; 1 << BITS] }
+    }
+
+    pub fn encode(&mut self, encoder: &mut Encoder, symbol: u32) {
+        let mut node = 1;
+        for i in (0..BITS).rev() {
+            let bit = (symbol >> i) & 1;
+            encoder.encode_bit(&mut self.probs[node], bit);
+            node = (node << 1) | bit as usize;
+        }
+    }
+
+    pub fn decode(&mut self, decoder: &mut Decoder) -> u32 {
+        let mut node = 1;
+        for _ in 0..BITS {
+            node = (node << 1) | decoder.decode_bit(&mut self.probs[node]) as usize;
+        }
+        (node - (1 << BITS)) as u32
+    }
+}
+
+pub fn compress(input: &[u8], mode: Mode) -> Vec<u8> {
+    compress_with_dictionary(input, mode, &[])
+}
+
+/// Like `compress`, with `dictionary` treated as history preceding the
+/// input. Only the LZ mode makes use of it.
+pub fn compress_with_dictionary(input: &[u8], mode: Mode, dictionary: &[u8]) -> Vec<u8> {
+    compress_with_report(input, mode, dictionary, &mut Report::none()).unwrap_or_default()
+}
+
+/// Like `compress_with_dictionary`, reporting to `report`.
+pub fn compress_with_report(input: &[u8], mode: Mode, dictionary: &[u8], report: &mut Report) -> Result<Vec<u8>, &'static str> {
+    let mut encoder = Encoder::new();
+    match mode {
+        Mode::Order0 => {
+            let mut model = BitTree::<8>::new();
+            for (i, &byte) in input.iter().enumerate() {
+                report.at(i)?;
+                model.encode(&mut encoder, byte as u32);
+            }
+        }
+        Mode::Order1 => {
+            let mut models = vec![BitTree::<8>::new(); 256];
+            let mut prev = 0usize;
+            for (i, &byte) in input.iter().enumerate() {
+                report.at(i)?;
+                models[prev].encode(&mut encoder, byte as u32);
+                prev = byte as usize;
+            }
+        }
+        Mode::LZ if dictionary.is_empty() => encode_lz(input, 0, &mut encoder, report)?,
+        Mode::LZ => encode_lz(&[dictionary, input].concat(), dictionary.len(), &mut encoder, report)?,
+    }
+
+    let mut compressed = vec![match mode {
+        Mode::Order0 => 0,
+        Mode::Order1 => 1,
+        Mode::LZ => 2,
+    }];
+    compressed.extend_from_slice(&(input.len() as u64).to_le_bytes());
+    compressed.extend_from_slice(&encoder.finish());
+    Ok(compressed)
+}
+
+pub fn decompress(compressed: &[u8]) -> Result<Vec<u8>, &'static str> {
+    decompress_with_dictionary(compressed, &[])
+}
+
+pub fn decompress_with_dictionary(compressed: &[u8], dictionary: &[u8]) -> Result<Vec<u8>, &'static str> {
+    decompress_with_report(compressed, dictionary, &mut Report::none())
+}
+
+/// Like `decompress_with_dictionary`, reporting to `report`.
+pub fn decompress_with_report(compressed: &[u8], dictionary: &[u8], report: &mut Report) -> Result<Vec<u8>, &'static str> {
+    if compressed.len() < 9 {
+        return Err(error::TRUNCATED_RC_HEADER);
+    }
+    let mut len_bytes = [0u8; 8];
+    len_bytes.copy_from_slice(&compressed[1..9]);
+    let len = usize::try_from(u64::from_le_bytes(len_bytes)).map_err(|_| "Range coder length too large")?;
+    let mut decoder = Decoder::new(&compressed[9..])?;
+
+    // Cap the up-front allocation; a corrupt length should fail, not abort.
+    let mut output = Vec::with_capacity(len.min(compressed.len() * 64));
+    match compressed[0] {
+        0 => {
+            let mut model = BitTree::<8>::new();
+            for _ in 0..len {
+                report.at(decoder.pos)?;
+                output.push(model.decode(&mut decoder) as u8);
+                if decoder.overrun() {
+                    return Err(error::TRUNCATED_RC_STREAM);
+                }
+            }
+        }
+        1 => {
+            let mut models = vec![BitTree::<8>::new(); 256];
+            let mut prev = 0usize;
+            for _ in 0..len {
+                report.at(decoder.pos)?;
+                let byte = models[prev].decode(&mut decoder) as u8;
+                output.push(byte);
+                prev = byte as usize;
+                if decoder.overrun() {
+                    return Err(error::TRUNCATED_RC_STREAM);
+                }
+            }
+        }
+        2 => {
+            let mut history = dictionary.to_vec();
+            decode_lz(&mut decoder, dictionary.len() + len, &mut history, report)?;
+            output = history.split_off(dictionary.len());
+        }
+        _ => return Err("Invalid range coder mode"),
+    }
+    Ok(output)
+}
+
+/// Adaptive models for LZ tokens. The match flags are conditioned on whether
+/// the previous token was a match; literals on the previous byte.
+struct LzModel {
+    is_match: [u16; 2],
+    is_rep: [u16; 2],
+    literals: Vec<BitTree<8>>,
+    len_choice: u16,
+    len_choice2: u16,
+    len_low: BitTree<3>,
+    len_mid: BitTree<3>,
+    len_high: BitTree<8>,
+    dist_slot: BitTree<6>,
+}
+
+impl LzModel {
+    fn new() -> Self {
+        Self {
+            is_match: [PROB_INIT; 2],
+            is_rep: [PROB_INIT; 2],
+            literals: vec![BitTree::new(); 256],
+            len_choice: PROB_INIT,
+            len_choice2: PROB_INIT,
+            len_low: BitTree::new(),
+            len_mid: BitTree::new(),
+            len_high: BitTree::new(),
+            dist_slot: BitTree::new(),
+        }
+    }
+
+    fn encode_len(&mut self, encoder: &mut Encoder, len: usize) {
+        let len = (len - MIN_MATCH) as u32;
+        if len < 8 {
+            encoder.encode_bit(&mut self.len_choice, 0);
+            self.len_low.encode(encoder, len);
+        } else if len < 16 {
+            encoder.encode_bit(&mut self.len_choice, 1);
+            encoder.encode_bit(&mut self.len_choice2, 0);
+            self.len_mid.encode(encoder, len - 8);
+        } else {
+            encoder.encode_bit(&mut self.len_choice, 1);
+            encoder.encode_bit(&mut self.len_choice2, 1);
+            self.len_high.encode(encoder, len - 16);
+        }
+    }
+
+    fn decode_len(&mut self, decoder: &mut Decoder) -> usize {
+        let len = if decoder.decode_bit(&mut self.len_choice) == 0 {
+            self.len_low.decode(decoder)
+        } else if decoder.decode_bit(&mut self.len_choice2) == 0 {
+            8 + self.len_mid.decode(decoder)
+        } else {
+            16 + self.len_high.decode(decoder)
+        };
+        len as usize + MIN_MATCH
+    }
+
+    /// Distances are split into a slot (two per power of two, bit-tree coded)
+    /// and the remaining low bits written directly.
+    fn encode_distance(&mut self, encoder: &mut Encoder, distance: usize) {
+        let value = (distance - 1) as u32;
+        if value < 4 {
+            self.dist_slot.encode(encoder, value);
+            return;
+        }
+        let top = 31 - value.leading_zeros();
+        let slot = (top << 1) | ((value >> (top - 1)) & 1);
+        self.dist_slot.encode(encoder, slot);
+        let extra_bits = top - 1;
+        encoder.encode_direct(value & ((1 << extra_bits) - 1), extra_bits);
+    }
+
+    /// The top slot reaches 2^32, which doesn't fit a 32-bit `usize`.
+    fn decode_distance(&mut self, decoder: &mut Decoder) -> Result<usize, &'static str> {
+        let slot = self.dist_slot.decode(decoder);
+        if slot < 4 {
+            return Ok(slot as usize + 1);
+        }
+        let extra_bits = (slot >> 1) - 1;
+        let base = (2 | (slot & 1)) << extra_bits;
+        usize::try_from(base + decoder.decode_direct(extra_bits))
+            .ok()
+            .and_then(|value| value.checked_add(1))
+            .ok_or("Corrupt range coded LZ stream")
+    }
+}
+
+// Encodes `input[start..]`, allowing matches into `input[..start]`.
+fn encode_lz(input: &[u8], start: usize, encoder: &mut Encoder, report: &mut Report) -> Result<(), &'static str> {
+    let mut model = LzModel::new();
+    // Dictionary or reference data has to stay reachable from anywhere in
+    // the input, however long the input is.
+    let window = if start > 0 { input.len() } else { LZ_WINDOW };
+    let mut chain = HashChain::new(input.len(), window, LZ_MAX_CHAIN, MIN_MATCH);
+    let mut state = 0;
+    let mut rep = 0usize;
+    for pos in 0..start {
+        chain.insert(input, pos);
+    }
+    let mut pos = start;
+
+    while pos < input.len() {
+        report.at(pos - start)?;
+        let mut token = chain.find(input, pos, MAX_MATCH);
+
+        // A match at the previous distance skips coding the distance, so it
+        // is preferred unless the regular match is clearly longer.
+        if rep > 0 && rep <= pos {
+            let rep_len = match_length(input, pos - rep, pos, MAX_MATCH.min(input.len() - pos));
+            if rep_len >= MIN_MATCH && token.is_none_or(|(_, len)| rep_len + 1 >= len) {
+                token = Some((rep, rep_len));
+            }
+        }
+        chain.insert(input, pos);
+
+        // Lazy evaluation: emit a literal instead when the next position
+        // starts a match at least two bytes longer.
+        if let Some((_, len)) = token {
+            let next = if pos + 1 < input.len() { chain.find(input, pos + 1, MAX_MATCH) } else { None };
+            if next.is_some_and(|(_, next_len)| next_len > len + 1) {
+                token = None;
+            }
+        }
+
+        encode_token(&mut model, encoder, input, pos, token, &mut state, &mut rep);
+        let advance = token.map_or(1, |(_, len)| len);
+        for p in pos + 1..pos + advance {
+            chain.insert(input, p);
+        }
+        pos += advance;
+    }
+    Ok(())
+}
+
+fn encode_token(
+    model: &mut LzModel,
+    encoder: &mut Encoder,
+    input: &[u8],
+    pos: usize,
+    token: Option<(usize, usize)>,
+    state: &mut usize,
+    rep: &mut usize,
+) {
+    match token {
+        None => {
+            encoder.encode_bit(&mut model.is_match[*state], 0);
+            let prev = if pos > 0 { input[pos - 1] as usize } else { 0 };
+            model.literals[prev].encode(encoder, input[pos] as u32);
+            *state = 0;
+        }
+        Some((distance, len)) => {
+            encoder.encode_bit(&mut model.is_match[*state], 1);
+            if distance == *rep {
+                encoder.encode_bit(&mut model.is_rep[*state], 1);
+            } else {
+                encoder.encode_bit(&mut model.is_rep[*state], 0);
+                model.encode_distance(encoder, distance);
+                *rep = distance;
+            }
+            model.encode_len(encoder, len);
+            *state = 1;
+        }
+    }
+}
+
+fn decode_lz(decoder: &mut Decoder, len: usize, output: &mut Vec<u8>, report: &mut Report) -> Result<(), &'static str> {
+    let mut model = LzModel::new();
+    let mut state = 0;
+    let mut rep = 0usize;
+
+    while output.len() < len {
+        report.at(decoder.pos)?;
+        if decoder.overrun() {
+            return Err(error::TRUNCATED_RC_STREAM);
+        }
+        if decoder.decode_bit(&mut model.is_match[state]) == 0 {
+            let prev = output.last().copied().unwrap_or(0) as usize;
+            output.push(model.literals[prev].decode(decoder) as u8);
+            state = 0;
+            continue;
+        }
+
+        if decoder.decode_bit(&mut model.is_rep[state]) == 0 {
+            rep = model.decode_distance(decoder)?;
+        }
+        let match_len = model.decode_len(decoder);
+        if rep == 0 || rep > output.len() || output.len() + match_len > len {
+            return Err("Corrupt range coded LZ stream");
+        }
+        let start = output.len() - rep;
+        for i in 0..match_len {
+            output.push(output[start + i]);
+        }
+        state = 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<u8> {
+        (0..500)
+            .flat_map(|i| format!("key{}=value{};", i % 37, i * 13 % 101).into_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_all_modes_roundtrip() {
+        let input = sample();
+        for mode in [Mode::Order0, Mode::Order1, Mode::LZ] {
+            let compressed = compress(&input, mode);
+            assert_eq!(decompress(&compressed).unwrap(), input, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn test_empty_input() {
+        for mode in [Mode::Order0, Mode::Order1, Mode::LZ] {
+            assert!(decompress(&compress(b"", mode)).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_context_modeling_helps() {
+        let input = sample();
+        let order0 = compress(&input, Mode::Order0).len();
+        let order1 = compress(&input, Mode::Order1).len();
+        let lz = compress(&input, Mode::LZ).len();
+        assert!(order1 < order0);
+        assert!(lz < order1);
+        assert!(lz < super::super::lz::compress(&input).len() / 2);
+    }
+
+    #[test]
+    fn test_long_distances_and_matches() {
+        let mut input: Vec<u8> = (0..100_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8).collect();
+        let copy = input[1000..50_000].to_vec();
+        input.extend_from_slice(&copy);
+        input.extend(vec![7u8; 5000]);
+        let compressed = compress(&input, Mode::LZ);
+        assert!(compressed.len() < 105_000);
+        assert_eq!(decompress(&compressed).unwrap(), input);
+    }
+
+    #[test]
+    fn test_dictionary_roundtrip() {
+        let dictionary = b"key1=value13;key2=value26;key3=value39;";
+        let input = b"key2=value26;key1=value13;";
+        let compressed = compress_with_dictionary(input, Mode::LZ, dictionary);
+        assert!(compressed.len() < compress(input, Mode::LZ).len());
+        assert_eq!(decompress_with_dictionary(&compressed, dictionary).unwrap(), input.to_vec());
+    }
+
+    #[test]
+    fn test_largest_distance_is_an_error() {
+        // Slot 63 with every direct bit set is distance 2^32.
+        let mut model = LzModel::new();
+        let mut encoder = Encoder::new();
+        encoder.encode_bit(&mut model.is_match[0], 1);
+        encoder.encode_bit(&mut model.is_rep[0], 0);
+        model.dist_slot.encode(&mut encoder, 63);
+        encoder.encode_direct((1 << 30) - 1, 30);
+        model.encode_len(&mut encoder, MIN_MATCH);
+        let stream = encoder.finish();
+
+        let mut decoder = Decoder::new(&stream).unwrap();
+        assert!(decode_lz(&mut decoder, 16, &mut Vec::new(), &mut Report::none()).is_err());
+    }
+
+    #[test]
+    fn test_corrupt_input() {
+        assert!(decompress(&[9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+        assert!(decompress(&[2, 1]).is_err());
+
+        // A corrupt length must fail quickly instead of decoding zeros forever.
+        for mode in [Mode::Order0, Mode::Order1, Mode::LZ] {
+            let mut compressed = compress(&sample(), mode);
+            compressed[8] = 0x40;
+            assert!(decompress(&compressed).is_err(), "{:?}", mode);
+        }
+    }
+}
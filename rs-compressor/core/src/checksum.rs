@@ -0,0 +1,77 @@
+// xxHash32, as used by the LZ4 frame format for its header, block and
+// content checksums.
+
+const PRIME1: u32 = 0x9E37_79B1;
+const PRIME2: u32 = 0x85EB_CA77;
+const PRIME3: u32 = 0xC2B2_AE3D;
+const PRIME4: u32 = 0x27D4_EB2F;
+const PRIME5: u32 = 0x1656_67B1;
+
+fn read_u32(data: &[u8]) -> u32 {
+    u32::from_le_bytes([data[0], data[1], data[2], data[3]])
+}
+
+fn round(acc: u32, lane: u32) -> u32 {
+    acc.wrapping_add(lane.wrapping_mul(PRIME2))
+        .rotate_left(13)
+        .wrapping_mul(PRIME1)
+}
+
+pub fn xxh32(data: &[u8], seed: u32) -> u32 {
+    let mut stripes = data.chunks_exact(16);
+    let mut hash = if data.len() >= 16 {
+        let mut acc = [
+            seed.wrapping_add(PRIME1).wrapping_add(PRIME2),
+            seed.wrapping_add(PRIME2),
+            seed,
+            seed.wrapping_sub(PRIME1),
+        ];
+        for stripe in &mut stripes {
+            for (lane, acc) in acc.iter_mut().enumerate() {
+                *acc = round(*acc, read_u32(&stripe[lane * 4..]));
+            }
+        }
+        acc[0]
+            .rotate_left(1)
+            .wrapping_add(acc[1].rotate_left(7))
+            .wrapping_add(acc[2].rotate_left(12))
+            .wrapping_add(acc[3].rotate_left(18))
+    } else {
+        seed.wrapping_add(PRIME5)
+    };
+    hash = hash.wrapping_add(data.len() as u32);
+
+    let tail = stripes.remainder();
+    let mut words = tail.chunks_exact(4);
+    for word in &mut words {
+        hash = hash
+            .wrapping_add(read_u32(word).wrapping_mul(PRIME3))
+            .rotate_left(17)
+            .wrapping_mul(PRIME4);
+    }
+    for &byte in words.remainder() {
+        hash = hash
+            .wrapping_add((byte as u32).wrapping_mul(PRIME5))
+            .rotate_left(11)
+            .wrapping_mul(PRIME1);
+    }
+
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(PRIME2);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(PRIME3);
+    hash ^ (hash >> 16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_values() {
+        assert_eq!(xxh32(b"", 0), 0x02CC_5D05);
+        assert_eq!(xxh32(b"a", 0), 0x550D_7456);
+        assert_eq!(xxh32(b"abc", 0), 0x32D1_53FF);
+        assert_eq!(xxh32(b"Nobody inspects the spammish repetition", 0), 0xE229_3B2F);
+    }
+}
@@ -0,0 +1,103 @@
+// Error messages that the wasm and C APIs sort into codes. Errors stay plain
+// `&'static str`s; the ones a caller may want to tell apart are named here,
+// raised through these constants and classified by `code`, so rewording a
+// message can't move it to another code. Messages not listed here, such as
+// the codecs' "Invalid ..." errors for corrupt data, get the caller's
+// fallback code.
+
+pub const CANCELLED: &str = "Cancelled";
+
+pub const DICTIONARY_UNSUPPORTED: &str = "Dictionaries are only supported with LZ and RC";
+pub const DICTIONARY_REQUIRED: &str = "File was compressed with a dictionary, but none was given";
+pub const DICTIONARY_UNEXPECTED: &str = "File was not compressed with a dictionary";
+pub const DICTIONARY_MISMATCH: &str = "Dictionary does not match the one used for compression";
+
+pub const PASSPHRASE_REQUIRED: &str = "File is encrypted; a passphrase is required";
+pub const WRONG_PASSPHRASE: &str = "Wrong passphrase, or the file was modified after encryption";
+pub const ALREADY_ENCRYPTED: &str = "File is already encrypted";
+pub const NOT_ENCRYPTED: &str = "File is not encrypted";
+pub const LZ4_ENCRYPTION: &str = "LZ4 frames have no header to carry encryption parameters";
+pub const ENCRYPTION_FAILED: &str = "Encryption failed";
+pub const KDF_INVALID: &str = "Invalid key derivation parameters";
+pub const KDF_TOO_COSTLY: &str = "Key derivation parameters are too costly";
+pub const KDF_FAILED: &str = "Key derivation failed";
+
+pub const IS_PATCH: &str = "File is a patch; apply it to its base file with the patch command";
+pub const NOT_A_PATCH: &str = "File is not a patch";
+pub const WRONG_BASE: &str = "Patch was made against a different base file";
+
+pub const INVALID_FORMAT: &str = "Invalid compressed file format";
+pub const INVALID_MAGIC: &str = "Invalid magic number";
+pub const INVALID_LZ4_MAGIC: &str = "Invalid LZ4 magic number";
+pub const NOT_FRAMED: &str = "File is not in the seekable framed format";
+pub const NOT_AN_ARCHIVE: &str = "Not an RSCMP archive";
+
+pub const UNSUPPORTED_VERSION: &str = "Unsupported version";
+pub const UNSUPPORTED_FLAGS: &str = "Unsupported header flags";
+pub const INVALID_ALGORITHM: &str = "Invalid algorithm identifier";
+pub const UNKNOWN_FILTER: &str = "Unknown filter";
+pub const UNKNOWN_FILTER_SPEC: &str = "Unknown filter. Use delta[:stride], bcj-x86, bcj-arm64 or transpose:width";
+pub const UNSUPPORTED_JSCMP_VERSION: &str = "Unsupported JSCMP version";
+pub const UNSUPPORTED_LZ4_DESCRIPTOR: &str = "Unsupported LZ4 frame descriptor";
+pub const UNSUPPORTED_LZ4_DICTIONARY: &str = "LZ4 frames with a dictionary are not supported";
+pub const UNSUPPORTED_ARCHIVE_VERSION: &str = "Unsupported archive version";
+
+pub const BLOCK_CHECKSUM: &str = "Block checksum mismatch";
+pub const BLOCK_NOT_IN_INDEX: &str = "Block does not match the seek index";
+pub const CHUNK_CHECKSUM: &str = "Chunk checksum mismatch";
+pub const ENTRY_SIZE_MISMATCH: &str = "Entry size does not match the archive index";
+pub const BWT_LENGTH_MISMATCH: &str = "BWT block length mismatch";
+pub const LZ4_HEADER_CHECKSUM: &str = "LZ4 frame header checksum mismatch";
+pub const LZ4_BLOCK_CHECKSUM: &str = "LZ4 block checksum mismatch";
+pub const LZ4_CONTENT_CHECKSUM: &str = "LZ4 content checksum mismatch";
+pub const LZ4_CONTENT_SIZE: &str = "LZ4 content size mismatch";
+
+pub const TRUNCATED_HEADER: &str = "Truncated header";
+pub const TRUNCATED_BLOCK: &str = "Truncated block";
+pub const TRUNCATED_FRAMED: &str = "Truncated framed stream";
+pub const MISSING_BLOCK_MARKER: &str = "Missing block marker";
+pub const SEEK_INDEX_MISSING: &str = "Seek index is missing";
+pub const TRUNCATED_DEDUP: &str = "Truncated deduplicated data";
+pub const TRUNCATED_ARCHIVE_INDEX: &str = "Truncated archive index";
+pub const ARCHIVE_INDEX_MISSING: &str = "Archive index is missing or truncated";
+pub const TRUNCATED_LZ: &str = "Truncated LZ data";
+pub const TRUNCATED_LITERAL_RUN: &str = "Truncated literal run";
+pub const TRUNCATED_LONG_RUN: &str = "Truncated long run";
+pub const TRUNCATED_REPEAT_RUN: &str = "Truncated repeat run";
+pub const TRUNCATED_BWT_HEADER: &str = "Truncated BWT block header";
+pub const TRUNCATED_HUFFMAN_TABLE: &str = "Truncated Huffman table";
+pub const TRUNCATED_BIT_STREAM: &str = "Truncated bit stream";
+pub const TRUNCATED_RC_HEADER: &str = "Truncated range coder header";
+pub const TRUNCATED_RC_STREAM: &str = "Truncated range coder stream";
+pub const TRUNCATED_LZ4_FRAME: &str = "Truncated LZ4 frame";
+pub const TRUNCATED_LZ4_SKIPPABLE: &str = "Truncated LZ4 skippable frame";
+pub const TRUNCATED_LZ4_BLOCK: &str = "Truncated LZ4 block";
+pub const TRUNCATED_LZ4_LENGTH: &str = "Truncated LZ4 length";
+pub const TRUNCATED_LZ4_LITERALS: &str = "Truncated LZ4 literals";
+pub const TRUNCATED_LZ4_OFFSET: &str = "Truncated LZ4 offset";
+pub const TRUNCATED_JSCMP_TOKEN: &str = "Truncated JSCMP LZ token";
+
+/// The code shared by the wasm and C APIs for `message`, or `None` if it
+/// isn't one of the messages above.
+pub fn code(message: &str) -> Option<&'static str> {
+    Some(match message {
+        CANCELLED => "CANCELLED",
+        DICTIONARY_UNSUPPORTED | DICTIONARY_REQUIRED | DICTIONARY_UNEXPECTED | DICTIONARY_MISMATCH => "DICTIONARY",
+        PASSPHRASE_REQUIRED | WRONG_PASSPHRASE | ALREADY_ENCRYPTED | NOT_ENCRYPTED | LZ4_ENCRYPTION | ENCRYPTION_FAILED
+        | KDF_INVALID | KDF_TOO_COSTLY | KDF_FAILED => "ENCRYPTION",
+        IS_PATCH | NOT_A_PATCH | WRONG_BASE => "PATCH",
+        INVALID_FORMAT | INVALID_MAGIC | INVALID_LZ4_MAGIC | NOT_FRAMED | NOT_AN_ARCHIVE => "INVALID_FORMAT",
+        UNSUPPORTED_VERSION | UNSUPPORTED_FLAGS | INVALID_ALGORITHM | UNKNOWN_FILTER | UNKNOWN_FILTER_SPEC
+        | UNSUPPORTED_JSCMP_VERSION | UNSUPPORTED_LZ4_DESCRIPTOR | UNSUPPORTED_LZ4_DICTIONARY
+        | UNSUPPORTED_ARCHIVE_VERSION => "UNSUPPORTED",
+        BLOCK_CHECKSUM | BLOCK_NOT_IN_INDEX | CHUNK_CHECKSUM | ENTRY_SIZE_MISMATCH | BWT_LENGTH_MISMATCH
+        | LZ4_HEADER_CHECKSUM | LZ4_BLOCK_CHECKSUM | LZ4_CONTENT_CHECKSUM | LZ4_CONTENT_SIZE => "CHECKSUM_MISMATCH",
+        TRUNCATED_HEADER | TRUNCATED_BLOCK | TRUNCATED_FRAMED | MISSING_BLOCK_MARKER | SEEK_INDEX_MISSING
+        | TRUNCATED_DEDUP | TRUNCATED_ARCHIVE_INDEX | ARCHIVE_INDEX_MISSING | TRUNCATED_LZ | TRUNCATED_LITERAL_RUN
+        | TRUNCATED_LONG_RUN | TRUNCATED_REPEAT_RUN | TRUNCATED_BWT_HEADER | TRUNCATED_HUFFMAN_TABLE
+        | TRUNCATED_BIT_STREAM | TRUNCATED_RC_HEADER | TRUNCATED_RC_STREAM | TRUNCATED_LZ4_FRAME
+        | TRUNCATED_LZ4_SKIPPABLE | TRUNCATED_LZ4_BLOCK | TRUNCATED_LZ4_LENGTH | TRUNCATED_LZ4_LITERALS
+        | TRUNCATED_LZ4_OFFSET | TRUNCATED_JSCMP_TOKEN => "TRUNCATED",
+        _ => return None,
+    })
+}
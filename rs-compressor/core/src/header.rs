@@ -0,0 +1,432 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::algos::filters::{Filter, MAX_FILTERS};
+use crate::algos::lz4;
+use crate::error;
+
+const MAGIC_NUMBER: &[u8] = b"RSCMP";
+const VERSION: u8 = 1;
+const BASE_SIZE: usize = 8;
+/// Bytes `header_size` needs to work out the size of any header.
+pub const PREFIX_SIZE: usize = BASE_SIZE + 14;
+
+// Bits of the flags byte (formerly reserved). Each set flag adds a field
+// after the base header, in flag order.
+const FLAG_DICTIONARY: u8 = 0x01;
+const FLAG_REFERENCE: u8 = 0x02;
+const FLAG_FRAMED: u8 = 0x04;
+const FLAG_METADATA: u8 = 0x08;
+const FLAG_ENCRYPTED: u8 = 0x10;
+// Marks the chunked payload layout; it adds no field.
+const FLAG_DEDUP: u8 = 0x20;
+const FLAG_FILTERS: u8 = 0x40;
+const KNOWN_FLAGS: u8 =
+    FLAG_DICTIONARY | FLAG_REFERENCE | FLAG_FRAMED | FLAG_METADATA | FLAG_ENCRYPTED | FLAG_DEDUP | FLAG_FILTERS;
+const ENCRYPTION_SIZE: usize = 4 + 4 + 1 + 16 + 12;
+// MAX_FILTERS slots of u8 filter ID and u8 parameter, unused ones zero. A
+// fixed size keeps `header_size` working from the prefix alone.
+const FILTERS_SIZE: usize = 2 * MAX_FILTERS;
+
+// Metadata is a u16 length followed by TLV entries: u8 tag, u16 length,
+// value. Readers skip tags they don't know.
+const TAG_FILENAME: u8 = 1;
+const TAG_MTIME: u8 = 2;
+const TAG_MODE: u8 = 3;
+const TAG_FIELD: u8 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    RLE,
+    LZ,
+    PackBits,
+    BWT,
+    RC,
+    LZ4,
+}
+
+impl Algorithm {
+    /// Numeric codec ID. LZ4 never appears in an RSCMP header, but archive
+    /// indexes record it as 6.
+    pub fn id(self) -> u8 {
+        match self {
+            Algorithm::RLE => 1,
+            Algorithm::LZ => 2,
+            Algorithm::PackBits => 3,
+            Algorithm::BWT => 4,
+            Algorithm::RC => 5,
+            Algorithm::LZ4 => 6,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Algorithm::RLE),
+            2 => Some(Algorithm::LZ),
+            3 => Some(Algorithm::PackBits),
+            4 => Some(Algorithm::BWT),
+            5 => Some(Algorithm::RC),
+            6 => Some(Algorithm::LZ4),
+            _ => None,
+        }
+    }
+}
+
+/// Optional facts about the original file, stored in the header.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    pub filename: Option<String>,
+    /// Modification time in seconds since the Unix epoch.
+    pub mtime: Option<u64>,
+    /// Unix permission bits.
+    pub mode: Option<u32>,
+    /// User-defined key/value pairs, in the order they were added.
+    pub fields: Vec<(String, String)>,
+}
+
+impl Metadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Metadata::default()
+    }
+
+    /// Checks that every entry fits the TLV length fields.
+    pub fn check(&self) -> Result<(), &'static str> {
+        let too_long = self.filename.as_ref().is_some_and(|name| name.len() > u16::MAX as usize)
+            || self.fields.iter().any(|(key, value)| key.len() > u8::MAX as usize || 1 + key.len() + value.len() > u16::MAX as usize)
+            || self.to_bytes().len() > u16::MAX as usize;
+        if too_long {
+            return Err("Header metadata is too large");
+        }
+        Ok(())
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut entry = |tag: u8, value: &[u8]| {
+            bytes.push(tag);
+            bytes.extend_from_slice(&(value.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(value);
+        };
+        if let Some(filename) = &self.filename {
+            entry(TAG_FILENAME, filename.as_bytes());
+        }
+        if let Some(mtime) = self.mtime {
+            entry(TAG_MTIME, &mtime.to_le_bytes());
+        }
+        if let Some(mode) = self.mode {
+            entry(TAG_MODE, &mode.to_le_bytes());
+        }
+        for (key, value) in &self.fields {
+            entry(TAG_FIELD, &[&[key.len() as u8], key.as_bytes(), value.as_bytes()].concat());
+        }
+        bytes
+    }
+
+    fn parse(mut data: &[u8]) -> Result<Self, &'static str> {
+        let text = |bytes: &[u8]| String::from_utf8(bytes.to_vec()).map_err(|_| "Invalid header metadata");
+        let mut metadata = Metadata::default();
+        while !data.is_empty() {
+            if data.len() < 3 {
+                return Err("Invalid header metadata");
+            }
+            let len = u16::from_le_bytes([data[1], data[2]]) as usize;
+            let value = data.get(3..3 + len).ok_or("Invalid header metadata")?;
+            match (data[0], len) {
+                (TAG_FILENAME, _) => metadata.filename = Some(text(value)?),
+                (TAG_MTIME, 8) => metadata.mtime = Some(u64::from_le_bytes(value.try_into().unwrap())),
+                (TAG_MODE, 4) => metadata.mode = Some(u32::from_le_bytes(value.try_into().unwrap())),
+                (TAG_FIELD, 1..) if (value[0] as usize) < len => {
+                    let key_end = 1 + value[0] as usize;
+                    metadata.fields.push((text(&value[1..key_end])?, text(&value[key_end..])?));
+                }
+                (TAG_FILENAME..=TAG_FIELD, _) => return Err("Invalid header metadata"),
+                _ => {}
+            }
+            data = &data[3 + len..];
+        }
+        Ok(metadata)
+    }
+}
+
+/// Key derivation parameters, salt and nonce of an encrypted payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Encryption {
+    /// Argon2id memory cost in KiB.
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub lanes: u8,
+    pub salt: [u8; 16],
+    pub nonce: [u8; 12],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    pub algorithm: Algorithm,
+    /// ID of the dictionary the payload was compressed with, if any.
+    pub dictionary_id: Option<u32>,
+    /// For patches, the xxHash32 of the file the patch applies to.
+    pub reference_checksum: Option<u32>,
+    /// Uncompressed block size when the payload uses the framed format.
+    pub block_size: Option<u32>,
+    pub metadata: Option<Metadata>,
+    /// Length of the metadata entries as stored, for headers read from a
+    /// file. `metadata` leaves out tags this version doesn't know, so the
+    /// payload offset can't be worked out from it; `to_bytes` drops them.
+    pub metadata_size: Option<usize>,
+    pub encryption: Option<Encryption>,
+    /// Whether the payload is a table of distinct chunks plus references.
+    pub deduplicated: bool,
+    /// Filters applied before the codec, in the order they ran.
+    pub filters: Vec<Filter>,
+}
+
+impl Header {
+    pub fn new(algorithm: Algorithm) -> Self {
+        Self {
+            algorithm,
+            dictionary_id: None,
+            reference_checksum: None,
+            block_size: None,
+            metadata: None,
+            metadata_size: None,
+            encryption: None,
+            deduplicated: false,
+            filters: Vec::new(),
+        }
+    }
+
+    /// Number of bytes before the compressed payload starts.
+    pub fn size(&self) -> usize {
+        if self.algorithm == Algorithm::LZ4 {
+            return 0;
+        }
+        BASE_SIZE
+            + if self.dictionary_id.is_some() { 4 } else { 0 }
+            + if self.reference_checksum.is_some() { 4 } else { 0 }
+            + if self.block_size.is_some() { 4 } else { 0 }
+            + self.metadata.as_ref().map_or(0, |metadata| 2 + self.metadata_size.unwrap_or_else(|| metadata.to_bytes().len()))
+            + if self.encryption.is_some() { ENCRYPTION_SIZE } else { 0 }
+            + if self.filters.is_empty() { 0 } else { FILTERS_SIZE }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // LZ4 output is written as a bare frame so standard LZ4 tools can read
+        // it; read_header recognises the frame magic instead.
+        if self.algorithm == Algorithm::LZ4 {
+            return Vec::new();
+        }
+
+        let mut flags = 0;
+        if self.dictionary_id.is_some() {
+            flags |= FLAG_DICTIONARY;
+        }
+        if self.reference_checksum.is_some() {
+            flags |= FLAG_REFERENCE;
+        }
+        if self.block_size.is_some() {
+            flags |= FLAG_FRAMED;
+        }
+        if self.metadata.is_some() {
+            flags |= FLAG_METADATA;
+        }
+        if self.encryption.is_some() {
+            flags |= FLAG_ENCRYPTED;
+        }
+        if self.deduplicated {
+            flags |= FLAG_DEDUP;
+        }
+        if !self.filters.is_empty() {
+            flags |= FLAG_FILTERS;
+        }
+
+        let mut header = Vec::with_capacity(self.size());
+        header.extend_from_slice(MAGIC_NUMBER);
+        header.push(VERSION);
+        header.push(self.algorithm.id());
+        header.push(flags);
+        if let Some(dictionary_id) = self.dictionary_id {
+            header.extend_from_slice(&dictionary_id.to_le_bytes());
+        }
+        if let Some(checksum) = self.reference_checksum {
+            header.extend_from_slice(&checksum.to_le_bytes());
+        }
+        if let Some(block_size) = self.block_size {
+            header.extend_from_slice(&block_size.to_le_bytes());
+        }
+        if let Some(metadata) = &self.metadata {
+            let entries = metadata.to_bytes();
+            header.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+            header.extend_from_slice(&entries);
+        }
+        if let Some(encryption) = &self.encryption {
+            header.extend_from_slice(&encryption.memory_kib.to_le_bytes());
+            header.extend_from_slice(&encryption.iterations.to_le_bytes());
+            header.push(encryption.lanes);
+            header.extend_from_slice(&encryption.salt);
+            header.extend_from_slice(&encryption.nonce);
+        }
+        if !self.filters.is_empty() {
+            let mut slots = [0; FILTERS_SIZE];
+            for (slot, filter) in slots.chunks_exact_mut(2).zip(&self.filters) {
+                slot.copy_from_slice(&filter.to_bytes());
+            }
+            header.extend_from_slice(&slots);
+        }
+        header
+    }
+}
+
+pub fn create_header(algorithm: Algorithm) -> Vec<u8> {
+    Header::new(algorithm).to_bytes()
+}
+
+/// Full size of the header that `prefix` starts with, given at least its
+/// first `PREFIX_SIZE` bytes, so streaming readers know how much to fetch.
+pub fn header_size(prefix: &[u8]) -> Result<usize, &'static str> {
+    if lz4::is_frame(prefix) {
+        return Ok(0);
+    }
+    let flags = *prefix.get(7).ok_or(error::INVALID_FORMAT)?;
+    let mut size = BASE_SIZE + 4 * (flags & (FLAG_DICTIONARY | FLAG_REFERENCE | FLAG_FRAMED)).count_ones() as usize;
+    if flags & FLAG_METADATA != 0 {
+        let len = prefix.get(size..size + 2).ok_or(error::TRUNCATED_HEADER)?;
+        size += 2 + u16::from_le_bytes([len[0], len[1]]) as usize;
+    }
+    if flags & FLAG_ENCRYPTED != 0 {
+        size += ENCRYPTION_SIZE;
+    }
+    if flags & FLAG_FILTERS != 0 {
+        size += FILTERS_SIZE;
+    }
+    Ok(size)
+}
+
+pub fn read_header(data: &[u8]) -> Result<Header, &'static str> {
+    if lz4::is_frame(data) {
+        return Ok(Header::new(Algorithm::LZ4));
+    }
+
+    if data.len() < BASE_SIZE {
+        return Err(error::INVALID_FORMAT);
+    }
+
+    if &data[0..5] != MAGIC_NUMBER {
+        return Err(error::INVALID_MAGIC);
+    }
+
+    if data[5] != VERSION {
+        return Err(error::UNSUPPORTED_VERSION);
+    }
+
+    let algorithm = match Algorithm::from_id(data[6]) {
+        Some(algorithm) if algorithm != Algorithm::LZ4 => algorithm,
+        _ => return Err(error::INVALID_ALGORITHM),
+    };
+
+    let flags = data[7];
+    if flags & !KNOWN_FLAGS != 0 {
+        return Err(error::UNSUPPORTED_FLAGS);
+    }
+
+    if flags & FLAG_FRAMED != 0 && flags & FLAG_DEDUP != 0 {
+        return Err(error::UNSUPPORTED_FLAGS);
+    }
+
+    let mut header = Header::new(algorithm);
+    header.deduplicated = flags & FLAG_DEDUP != 0;
+    let mut pos = BASE_SIZE;
+    let mut read_u32 = || -> Result<u32, &'static str> {
+        let bytes = data.get(pos..pos + 4).ok_or(error::TRUNCATED_HEADER)?;
+        pos += 4;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    if flags & FLAG_DICTIONARY != 0 {
+        header.dictionary_id = Some(read_u32()?);
+    }
+    if flags & FLAG_REFERENCE != 0 {
+        header.reference_checksum = Some(read_u32()?);
+    }
+    if flags & FLAG_FRAMED != 0 {
+        let block_size = read_u32()?;
+        if block_size == 0 {
+            return Err("Invalid block size");
+        }
+        header.block_size = Some(block_size);
+    }
+    if flags & FLAG_METADATA != 0 {
+        let len = data.get(pos..pos + 2).ok_or(error::TRUNCATED_HEADER)?;
+        let len = u16::from_le_bytes([len[0], len[1]]) as usize;
+        let entries = data.get(pos + 2..pos + 2 + len).ok_or(error::TRUNCATED_HEADER)?;
+        header.metadata = Some(Metadata::parse(entries)?);
+        header.metadata_size = Some(len);
+        pos += 2 + len;
+    }
+    if flags & FLAG_ENCRYPTED != 0 {
+        let field = data.get(pos..pos + ENCRYPTION_SIZE).ok_or(error::TRUNCATED_HEADER)?;
+        header.encryption = Some(Encryption {
+            memory_kib: u32::from_le_bytes(field[0..4].try_into().unwrap()),
+            iterations: u32::from_le_bytes(field[4..8].try_into().unwrap()),
+            lanes: field[8],
+            salt: field[9..25].try_into().unwrap(),
+            nonce: field[25..37].try_into().unwrap(),
+        });
+        pos += ENCRYPTION_SIZE;
+    }
+    if flags & FLAG_FILTERS != 0 {
+        let slots = data.get(pos..pos + FILTERS_SIZE).ok_or(error::TRUNCATED_HEADER)?;
+        for slot in slots.chunks_exact(2).take_while(|slot| slot[0] != 0) {
+            header.filters.push(Filter::from_bytes([slot[0], slot[1]])?);
+        }
+        if header.filters.is_empty() {
+            return Err("Invalid filter list");
+        }
+    }
+
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_roundtrip() {
+        let mut header = Header::new(Algorithm::BWT);
+        header.block_size = Some(4096);
+        header.metadata = Some(Metadata {
+            filename: Some("report.csv".to_string()),
+            mtime: Some(1_700_000_000),
+            mode: Some(0o640),
+            fields: vec![("source".to_string(), "nightly export".to_string())],
+        });
+        let bytes = header.to_bytes();
+        assert_eq!(bytes.len(), header.size());
+        assert_eq!(header_size(&bytes[..PREFIX_SIZE]).unwrap(), bytes.len());
+        let read = read_header(&bytes).unwrap();
+        assert_eq!(read.size(), bytes.len());
+        assert_eq!(Header { metadata_size: None, ..read }, header);
+        assert!(read_header(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_filters_roundtrip() {
+        let mut header = Header::new(Algorithm::LZ);
+        header.metadata = Some(Metadata { filename: Some("samples.bin".to_string()), ..Metadata::default() });
+        header.filters = vec![Filter::Delta(2), Filter::Transpose(4)];
+        let bytes = header.to_bytes();
+        assert_eq!(bytes.len(), header.size());
+        assert_eq!(header_size(&bytes[..PREFIX_SIZE]).unwrap(), bytes.len());
+        assert_eq!(Header { metadata_size: None, ..read_header(&bytes).unwrap() }, header);
+    }
+
+    #[test]
+    fn test_unknown_metadata_tags_are_skipped() {
+        let mut bytes = create_header(Algorithm::LZ);
+        bytes[7] = FLAG_METADATA;
+        bytes.extend_from_slice(&[4, 0, 0x7F, 1, 0, 0xAA]);
+        let header = read_header(&bytes).unwrap();
+        assert_eq!(header.metadata, Some(Metadata::default()));
+        // The skipped entry still counts towards where the payload starts.
+        assert_eq!(header.size(), bytes.len());
+    }
+}
@@ -0,0 +1,178 @@
+// The js-compressor's file format, read and written here so files move
+// between the two implementations:
+//
+//   "JSCMP", u8 version (1), u8 algorithm (1 RLE, 2 LZ), u8 reserved
+//   payload
+//
+// RLE payloads are (count, byte) pairs, the same as `algos::le`. LZ payloads
+// are 17-byte tokens: u64 offset, u64 length, u8 next byte, little-endian. A
+// token copies `length` bytes from `offset` back, then appends its next byte
+// unless that is 0. A zero byte can only come from a copy, and the first one
+// has nothing to copy, so LZ can't hold data with zero bytes at all.
+
+use alloc::vec::Vec;
+
+use crate::algos::file_type::Algorithm;
+use crate::algos::le;
+use crate::algos::match_finder::HashChain;
+use crate::error;
+use crate::report::Report;
+
+pub const MAGIC: &[u8; 5] = b"JSCMP";
+const VERSION: u8 = 1;
+const HEADER_SIZE: usize = 8;
+const TOKEN_SIZE: usize = 17;
+
+const WINDOW: usize = 65535;
+const MAX_CHAIN: usize = 64;
+const MIN_MATCH: usize = 3;
+/// js-compressor's own limit; its decoder takes any length.
+const MAX_MATCH: usize = 255;
+/// Far beyond anything either encoder writes, so a corrupt length fails
+/// instead of filling memory.
+const MAX_TOKEN_LENGTH: u64 = 1 << 24;
+
+pub fn is_jscmp(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Whether `algorithm` can write `input` as JSCMP.
+pub fn supports(input: &[u8], algorithm: Algorithm) -> bool {
+    match algorithm {
+        Algorithm::RLE => true,
+        Algorithm::LZ => !input.contains(&0),
+        _ => false,
+    }
+}
+
+/// Wraps `input` in a JSCMP file. Only RLE and LZ exist in that format.
+pub fn compress(input: &[u8], algorithm: Algorithm) -> Result<Vec<u8>, &'static str> {
+    let (id, payload) = match algorithm {
+        Algorithm::RLE => (1, le::compress(input)),
+        Algorithm::LZ => (2, encode_lz(input)?),
+        _ => return Err("JSCMP files can only hold RLE or LZ data"),
+    };
+    let mut output = MAGIC.to_vec();
+    output.extend_from_slice(&[VERSION, id, 0]);
+    output.extend_from_slice(&payload);
+    Ok(output)
+}
+
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, &'static str> {
+    decompress_with_report(data, &mut Report::none())
+}
+
+/// Like `decompress`, reporting payload positions to `report`.
+pub fn decompress_with_report(data: &[u8], report: &mut Report) -> Result<Vec<u8>, &'static str> {
+    if data.len() < HEADER_SIZE || !is_jscmp(data) {
+        return Err("Invalid JSCMP header");
+    }
+    if data[5] != VERSION {
+        return Err(error::UNSUPPORTED_JSCMP_VERSION);
+    }
+    let payload = &data[HEADER_SIZE..];
+    match data[6] {
+        1 => le::decompress_with_report(payload, report),
+        2 => decode_lz(payload, report),
+        _ => Err(error::INVALID_ALGORITHM),
+    }
+}
+
+fn encode_lz(input: &[u8]) -> Result<Vec<u8>, &'static str> {
+    if input.contains(&0) {
+        return Err("JSCMP LZ can't hold zero bytes; use RLE");
+    }
+    let mut chain = HashChain::new(input.len(), WINDOW, MAX_CHAIN, MIN_MATCH);
+    let mut output = Vec::new();
+    let mut pos = 0;
+    while pos < input.len() {
+        let (offset, length) = chain.find(input, pos, MAX_MATCH).unwrap_or((0, 0));
+        // A match running to the end of the input leaves no next byte.
+        let next = input.get(pos + length).copied().unwrap_or(0);
+        output.extend_from_slice(&(offset as u64).to_le_bytes());
+        output.extend_from_slice(&(length as u64).to_le_bytes());
+        output.push(next);
+
+        let end = (pos + length + 1).min(input.len());
+        for p in pos..end {
+            chain.insert(input, p);
+        }
+        pos = end;
+    }
+    Ok(output)
+}
+
+fn decode_lz(payload: &[u8], report: &mut Report) -> Result<Vec<u8>, &'static str> {
+    if !payload.len().is_multiple_of(TOKEN_SIZE) {
+        return Err(error::TRUNCATED_JSCMP_TOKEN);
+    }
+    let mut output = Vec::new();
+    for (i, token) in payload.chunks_exact(TOKEN_SIZE).enumerate() {
+        report.at(i * TOKEN_SIZE)?;
+        let offset = u64::from_le_bytes(token[0..8].try_into().unwrap());
+        let length = u64::from_le_bytes(token[8..16].try_into().unwrap());
+        if length > 0 {
+            if offset == 0 || offset > output.len() as u64 || length > MAX_TOKEN_LENGTH {
+                return Err("Invalid JSCMP LZ match");
+            }
+            let start = output.len() - offset as usize;
+            for i in 0..length as usize {
+                output.push(output[start + i]);
+            }
+        }
+        if token[16] != 0 {
+            output.push(token[16]);
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Token bytes as js-compressor's `compressBatch` writes them.
+    fn token(offset: u64, length: u64, next: u8) -> Vec<u8> {
+        [&offset.to_le_bytes()[..], &length.to_le_bytes(), &[next]].concat()
+    }
+
+    #[test]
+    fn test_decodes_js_compressor_output() {
+        let mut file = b"JSCMP\x01\x02\x00".to_vec();
+        for (offset, length, next) in [(0, 0, b'a'), (0, 0, b'b'), (2, 4, b'c'), (3, 2, 0)] {
+            file.extend(token(offset, length, next));
+        }
+        assert_eq!(decompress(&file).unwrap(), b"abababcab");
+
+        let rle = [b"JSCMP\x01\x01\x00".as_slice(), &[3, b'x', 1, b'y']].concat();
+        assert_eq!(decompress(&rle).unwrap(), b"xxxy");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let text = b"the JSCMP format, the JSCMP format, round and round ".repeat(40);
+        let binary: Vec<u8> = (0..5000u32).map(|i| (i % 7) as u8 + 1).collect();
+        for input in [&text[..], &binary, b""] {
+            for algorithm in [Algorithm::RLE, Algorithm::LZ] {
+                let compressed = compress(input, algorithm).unwrap();
+                assert_eq!(decompress(&compressed).unwrap(), input, "{:?}", algorithm);
+            }
+        }
+        let zeros = [b"zero".as_slice(), &[0]].concat();
+        assert!(!supports(&zeros, Algorithm::LZ) && compress(&zeros, Algorithm::LZ).is_err());
+        assert_eq!(decompress(&compress(&zeros, Algorithm::RLE).unwrap()).unwrap(), zeros);
+        assert!(compress(&text, Algorithm::RC).is_err());
+    }
+
+    #[test]
+    fn test_rejects_damage() {
+        let compressed = compress(b"abcabcabc", Algorithm::LZ).unwrap();
+        assert!(decompress(&compressed[..compressed.len() - 1]).is_err());
+        let mut bad = compressed.clone();
+        bad[5] = 9;
+        assert!(decompress(&bad).is_err());
+        let mut far = b"JSCMP\x01\x02\x00".to_vec();
+        far.extend(token(5, 1, 0));
+        assert!(decompress(&far).is_err());
+    }
+}
@@ -0,0 +1,14 @@
+// The codecs, the RSCMP and JSCMP formats and file type detection: pure byte
+// manipulation over `alloc`, with no I/O, so they build for `no_std` targets.
+// rs-compressor adds the framed, dedup, archive and encryption layers on top.
+#![cfg_attr(not(test), no_std)]
+#![allow(clippy::upper_case_acronyms)]
+
+extern crate alloc;
+
+pub mod algos;
+pub mod checksum;
+pub mod error;
+pub mod header;
+pub mod jscmp;
+pub mod report;
@@ -0,0 +1,66 @@
+// Progress hooks for the codecs. A codec's `*_with_report` variant calls
+// `Report::at` with how many bytes of its input it has consumed, and every
+// INTERVAL bytes that reaches the callback, which can stop the run by
+// returning an error. rs-compressor turns these into progress callbacks and
+// cancellation. `Report::none()` never stops a run, so the plain codec
+// functions unwrap their results with `unwrap_or_default`.
+
+use alloc::boxed::Box;
+
+/// Input bytes consumed between calls to the callback.
+pub const INTERVAL: usize = 64 * 1024;
+
+type Callback<'a> = Box<dyn FnMut(usize) -> Result<(), &'static str> + 'a>;
+
+pub struct Report<'a> {
+    callback: Option<Callback<'a>>,
+    next: usize,
+}
+
+impl<'a> Report<'a> {
+    pub fn none() -> Self {
+        Self { callback: None, next: usize::MAX }
+    }
+
+    /// Calls `callback` with the input position; an error it returns stops
+    /// the codec, which returns the error.
+    pub fn new(callback: impl FnMut(usize) -> Result<(), &'static str> + 'a) -> Self {
+        Self { callback: Some(Box::new(callback)), next: INTERVAL }
+    }
+
+    /// Notes that `pos` bytes of the input have been consumed.
+    #[inline]
+    pub fn at(&mut self, pos: usize) -> Result<(), &'static str> {
+        if pos < self.next {
+            return Ok(());
+        }
+        self.next = pos.saturating_add(INTERVAL);
+        match &mut self.callback {
+            Some(callback) => callback(pos),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_every_interval() {
+        let mut seen = alloc::vec::Vec::new();
+        let mut report = Report::new(|pos| {
+            seen.push(pos);
+            if pos >= 3 * INTERVAL { Err("stop") } else { Ok(()) }
+        });
+        for pos in (0..=2 * INTERVAL + 10).step_by(4096) {
+            report.at(pos).unwrap();
+        }
+        assert_eq!(report.at(3 * INTERVAL), Err("stop"));
+        drop(report);
+        assert_eq!(seen, [INTERVAL, 2 * INTERVAL, 3 * INTERVAL]);
+
+        let mut report = Report::none();
+        assert!(report.at(usize::MAX - 1).is_ok());
+    }
+}
@@ -0,0 +1,161 @@
+#ifndef RS_COMPRESSOR_H
+#define RS_COMPRESSOR_H
+
+/* Generated by cbindgen from src/ffi.rs; edit that file, not this one. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define RSC_ALGORITHM_AUTO 0
+
+#define RSC_ALGORITHM_RLE 1
+
+#define RSC_ALGORITHM_LZ 2
+
+#define RSC_ALGORITHM_PACKBITS 3
+
+#define RSC_ALGORITHM_BWT 4
+
+#define RSC_ALGORITHM_RC 5
+
+#define RSC_ALGORITHM_LZ4 6
+
+// Result of every call. The values are part of the ABI and never change;
+// new ones are only ever added.
+typedef enum RscStatus {
+  RSC_STATUS_OK = 0,
+  // A null pointer, unknown algorithm or option the codec can't take.
+  RSC_STATUS_INVALID_ARGUMENT = 1,
+  // The output buffer is too small; `*output_len` holds the size needed.
+  RSC_STATUS_BUFFER_TOO_SMALL = 2,
+  // The input is not an RSCMP, LZ4 or JSCMP file.
+  RSC_STATUS_INVALID_FORMAT = 3,
+  // The file uses a codec, filter or feature this build can't decode.
+  RSC_STATUS_UNSUPPORTED = 4,
+  RSC_STATUS_CORRUPT_DATA = 5,
+  RSC_STATUS_CHECKSUM_MISMATCH = 6,
+  RSC_STATUS_TRUNCATED = 7,
+  // The file needs a dictionary, is encrypted or is a patch; those take
+  // the CLI or the Rust API.
+  RSC_STATUS_NEEDS_OPTIONS = 8,
+  RSC_STATUS_INTERNAL = 9,
+} RscStatus;
+
+// Streaming decompression context.
+typedef struct RscDecoder RscDecoder;
+
+// Streaming compression context; writes the seekable framed format.
+typedef struct RscEncoder RscEncoder;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Describes `status` in a static, NUL-terminated English string.
+const char *rsc_status_message(enum RscStatus status);
+
+// Largest output `rsc_compress` can produce for `input_len` bytes with any
+// algorithm.
+size_t rsc_compress_bound(size_t input_len);
+
+// Compresses `input` with `algorithm` (an `RSC_ALGORITHM_` value) into
+// `output`, which holds `output_capacity` bytes, and stores the size written
+// in `*output_len`. A capacity of `rsc_compress_bound(input_len)` is always
+// enough.
+//
+// # Safety
+// `input` must point to `input_len` readable bytes (or be null if that is
+// 0), `output` to `output_capacity` writable bytes and `output_len` to a
+// `size_t`.
+enum RscStatus rsc_compress(uint32_t algorithm,
+                            const uint8_t *input,
+                            size_t input_len,
+                            uint8_t *output,
+                            size_t output_capacity,
+                            size_t *output_len);
+
+// Decompresses an RSCMP, LZ4 or JSCMP file. On success `*output` points to
+// `*output_len` bytes to release with `rsc_free`.
+//
+// # Safety
+// `input` must point to `input_len` readable bytes (or be null if that is
+// 0); `output` and `output_len` must be valid for writes.
+enum RscStatus rsc_decompress(const uint8_t *input,
+                              size_t input_len,
+                              uint8_t **output,
+                              size_t *output_len);
+
+// Releases a buffer the library returned. Null is ignored.
+//
+// # Safety
+// `data` and `len` must be a pointer and length returned together by this
+// library, not yet freed.
+void rsc_free(uint8_t *data, size_t len);
+
+// Starts a compression stream. `algorithm` may be `RSC_ALGORITHM_AUTO` but
+// not LZ4; a `block_size` of 0 uses the default. Returns null on an invalid
+// argument.
+struct RscEncoder *rsc_encoder_new(uint32_t algorithm, uint32_t block_size);
+
+// Feeds `input` to the stream. `*output` receives the compressed bytes for
+// every block completed, possibly none; release them with `rsc_free`.
+//
+// # Safety
+// `encoder` must come from `rsc_encoder_new` and not be finished or freed;
+// the buffers are as for `rsc_decompress`.
+enum RscStatus rsc_encoder_push(struct RscEncoder *encoder,
+                                const uint8_t *input,
+                                size_t input_len,
+                                uint8_t **output,
+                                size_t *output_len);
+
+// Writes the end of the stream to `*output` and frees `encoder`, whatever
+// the result.
+//
+// # Safety
+// As for `rsc_encoder_push`; `encoder` is invalid afterwards.
+enum RscStatus rsc_encoder_finish(struct RscEncoder *encoder, uint8_t **output, size_t *output_len);
+
+// Abandons a stream without finishing it. Null is ignored.
+//
+// # Safety
+// `encoder` must come from `rsc_encoder_new` and not be finished or freed.
+void rsc_encoder_free(struct RscEncoder *encoder);
+
+// Starts a decompression stream. Framed files decode block by block as
+// they arrive; any other file is returned whole by `rsc_decoder_finish`.
+struct RscDecoder *rsc_decoder_new(void);
+
+// Feeds `input` to the stream. `*output` receives the contents of every
+// block completed, possibly none; release them with `rsc_free`.
+//
+// # Safety
+// `decoder` must come from `rsc_decoder_new` and not be finished or freed;
+// the buffers are as for `rsc_decompress`.
+enum RscStatus rsc_decoder_push(struct RscDecoder *decoder,
+                                const uint8_t *input,
+                                size_t input_len,
+                                uint8_t **output,
+                                size_t *output_len);
+
+// Decodes whatever remains and frees `decoder`, whatever the result. A
+// framed stream that stopped before its index is `RSC_STATUS_TRUNCATED`.
+//
+// # Safety
+// As for `rsc_decoder_push`; `decoder` is invalid afterwards.
+enum RscStatus rsc_decoder_finish(struct RscDecoder *decoder, uint8_t **output, size_t *output_len);
+
+// Abandons a stream without finishing it. Null is ignored.
+//
+// # Safety
+// `decoder` must come from `rsc_decoder_new` and not be finished or freed.
+void rsc_decoder_free(struct RscDecoder *decoder);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RS_COMPRESSOR_H */
@@ -0,0 +1,103 @@
+"""Tests for the Python bindings. Build them into the active virtualenv with
+`maturin develop`, then run `python -m pytest python/tests` (or
+`python -m unittest discover python/tests`) from rs-compressor/."""
+
+import io
+import unittest
+
+import rs_compressor
+
+TEXT = b"the quick brown fox jumps over the lazy dog\n" * 200
+BINARY = bytes((i * 37 + i // 5) % 256 for i in range(20000))
+
+
+class CompressTest(unittest.TestCase):
+    def test_roundtrip_every_algorithm(self):
+        for algorithm in ["auto", "rle", "lz", "packbits", "bwt", "rc", "lz4"]:
+            for data in [TEXT, BINARY, b""]:
+                packed = rs_compressor.compress(data, algorithm=algorithm)
+                self.assertIsInstance(packed, bytes)
+                self.assertEqual(rs_compressor.decompress(packed), data, algorithm)
+
+    def test_levels(self):
+        sizes = {}
+        for level in range(1, 10):
+            packed = rs_compressor.compress(TEXT, level=level)
+            self.assertEqual(rs_compressor.decompress(packed), TEXT)
+            sizes[level] = len(packed)
+        self.assertLess(sizes[9], sizes[1])
+        with self.assertRaises(ValueError):
+            rs_compressor.compress(TEXT, level=10)
+        with self.assertRaises(ValueError):
+            rs_compressor.compress(TEXT, algorithm="lz", level=5)
+
+    def test_accepts_bytes_like_input(self):
+        packed = rs_compressor.compress(bytearray(TEXT), "lz")
+        self.assertEqual(rs_compressor.decompress(memoryview(packed)), TEXT)
+
+    def test_errors(self):
+        with self.assertRaises(ValueError):
+            rs_compressor.compress(TEXT, algorithm="zip")
+        with self.assertRaises(rs_compressor.CompressionError):
+            rs_compressor.decompress(b"not a compressed file")
+        packed = rs_compressor.compress(TEXT, algorithm="rc")
+        with self.assertRaises(rs_compressor.CompressionError):
+            rs_compressor.decompress(packed[: len(packed) // 2])
+
+
+class StreamingTest(unittest.TestCase):
+    def test_writer_and_reader_roundtrip(self):
+        raw = io.BytesIO()
+        with rs_compressor.CompressedWriter(raw, algorithm="lz", block_size=4096) as writer:
+            for start in range(0, len(TEXT), 1000):
+                self.assertEqual(writer.write(TEXT[start : start + 1000]), len(TEXT[start : start + 1000]))
+        self.assertTrue(writer.closed)
+        self.assertFalse(raw.closed)
+        self.assertEqual(rs_compressor.decompress(raw.getvalue()), TEXT)
+
+        raw.seek(0)
+        with rs_compressor.CompressedReader(raw) as reader:
+            pieces = []
+            while True:
+                piece = reader.read(777)
+                if not piece:
+                    break
+                self.assertLessEqual(len(piece), 777)
+                pieces.append(piece)
+        self.assertEqual(b"".join(pieces), TEXT)
+
+    def test_reader_reads_whole_files(self):
+        packed = rs_compressor.compress(BINARY, algorithm="bwt")
+        reader = rs_compressor.CompressedReader(io.BytesIO(packed))
+        self.assertEqual(reader.read(), BINARY)
+        self.assertEqual(reader.read(), b"")
+        reader.close()
+        with self.assertRaises(ValueError):
+            reader.read()
+
+    def test_reader_reads_jscmp(self):
+        # A js-compressor RLE file: header, then (count, byte) pairs.
+        jscmp = b"JSCMP\x01\x01\x00\x03a\x02b"
+        with rs_compressor.CompressedReader(io.BytesIO(jscmp)) as reader:
+            self.assertEqual(reader.read(), b"aaabb")
+
+    def test_truncated_stream(self):
+        raw = io.BytesIO()
+        with rs_compressor.CompressedWriter(raw, block_size=1024) as writer:
+            writer.write(TEXT)
+        reader = rs_compressor.CompressedReader(io.BytesIO(raw.getvalue()[: len(raw.getvalue()) // 2]))
+        with self.assertRaises(rs_compressor.CompressionError):
+            reader.read()
+
+    def test_writer_rejects_lz4_and_closed_writes(self):
+        with self.assertRaises(rs_compressor.CompressionError):
+            rs_compressor.CompressedWriter(io.BytesIO(), algorithm="lz4")
+        writer = rs_compressor.CompressedWriter(io.BytesIO())
+        writer.close()
+        writer.close()
+        with self.assertRaises(ValueError):
+            writer.write(b"late")
+
+
+if __name__ == "__main__":
+    unittest.main()
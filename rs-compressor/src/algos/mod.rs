@@ -1,3 +0,0 @@
-pub mod le;
-pub mod lz;
-pub mod file_type;
@@ -0,0 +1,530 @@
+// Multi-file archives (.rsa). Every file is stored as a complete RSCMP
+// payload with its own codec, followed by a central index so entries can be
+// listed without touching their data:
+//
+//   "RSARC" version
+//   entry payloads and chunks, back to back
+//   index: u32 entry count, then per entry
+//     u16 path length, path (UTF-8, '/' separated)
+//     u8 kind (0 file, 1 directory, 2 deduplicated file),
+//     u8 codec ID (0 for directories)
+//     u32 mode, u64 mtime (seconds since the epoch), u64 size
+//     u64 payload offset, u64 payload length
+//   u32 chunk count, then per chunk
+//     32-byte BLAKE2b-256 hash, u32 raw length, u64 offset, u64 length
+//   u64 index offset, "RSIDX"
+//
+// All integers are little-endian. Adding files writes their payloads where
+// the old index was and appends a new index, so nothing already stored is
+// recompressed. Replacing an entry moves the payloads after the old one down
+// over it, so repeated adds of the same path don't grow the archive.
+//
+// Deduplicated files are cut into content-defined chunks (see `dedup`) that
+// are shared by every file in the archive: each distinct chunk is stored
+// once as an RSCMP payload, and the file's own payload is just the list of
+// its u32 chunk numbers. Version 1 archives have no chunk table.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::algos::file_type::Algorithm as FileAlgorithm;
+use crate::codec;
+use crate::dedup::{self, ChunkHash, DedupStats};
+use crate::error;
+use crate::header::Algorithm;
+
+const MAGIC: &[u8] = b"RSARC";
+const INDEX_MAGIC: &[u8] = b"RSIDX";
+const VERSION: u8 = 2;
+const TRAILER_SIZE: usize = 8 + INDEX_MAGIC.len();
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntryKind {
+    File,
+    Directory,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub path: String,
+    pub kind: EntryKind,
+    /// Codec of the stored payload; `None` for directories.
+    pub algorithm: Option<Algorithm>,
+    pub mode: u32,
+    pub mtime: u64,
+    pub size: u64,
+    /// Stored as references into the archive's shared chunks.
+    pub deduplicated: bool,
+    offset: u64,
+    length: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Chunk {
+    hash: ChunkHash,
+    raw_len: u32,
+    offset: u64,
+    length: u64,
+}
+
+impl Entry {
+    /// Bytes stored for this entry alone. For deduplicated files that is just
+    /// the chunk list, since the chunks are shared.
+    pub fn compressed_size(&self) -> u64 {
+        self.length
+    }
+}
+
+pub struct Archive {
+    // Everything before the index: the magic and the entry payloads.
+    data: Vec<u8>,
+    entries: Vec<Entry>,
+    chunks: Vec<Chunk>,
+    chunk_numbers: HashMap<ChunkHash, u32>,
+}
+
+impl Default for Archive {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Archive paths are relative and '/' separated; anything that could escape
+/// the extraction directory is rejected.
+fn check_path(path: &str) -> Result<(), &'static str> {
+    if path.is_empty() || path.starts_with('/') || path.contains('\\') {
+        return Err("Invalid path in archive");
+    }
+    if path.split('/').any(|part| part.is_empty() || part == "." || part == "..") {
+        return Err("Invalid path in archive");
+    }
+    Ok(())
+}
+
+fn take<'a>(data: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8], &'static str> {
+    let bytes = data.get(*pos..*pos + len).ok_or(error::TRUNCATED_ARCHIVE_INDEX)?;
+    *pos += len;
+    Ok(bytes)
+}
+
+fn take_u32(data: &[u8], pos: &mut usize) -> Result<u32, &'static str> {
+    let bytes = take(data, pos, 4)?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn take_u64(data: &[u8], pos: &mut usize) -> Result<u64, &'static str> {
+    let bytes = take(data, pos, 8)?;
+    Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+impl Archive {
+    pub fn new() -> Self {
+        let mut data = MAGIC.to_vec();
+        data.push(VERSION);
+        Self { data, entries: Vec::new(), chunks: Vec::new(), chunk_numbers: HashMap::new() }
+    }
+
+    pub fn open(mut bytes: Vec<u8>) -> Result<Self, &'static str> {
+        let start = MAGIC.len() + 1;
+        if bytes.len() < start + TRAILER_SIZE || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(error::NOT_AN_ARCHIVE);
+        }
+        let version = bytes[MAGIC.len()];
+        if version != 1 && version != VERSION {
+            return Err(error::UNSUPPORTED_ARCHIVE_VERSION);
+        }
+        let trailer = bytes.len() - TRAILER_SIZE;
+        if &bytes[trailer + 8..] != INDEX_MAGIC {
+            return Err(error::ARCHIVE_INDEX_MISSING);
+        }
+        let index_offset = u64::from_le_bytes(bytes[trailer..trailer + 8].try_into().unwrap()) as usize;
+        if index_offset < start || index_offset > trailer {
+            return Err("Invalid archive index offset");
+        }
+
+        let index = &bytes[index_offset..trailer];
+        let mut pos = 0;
+        let count = take_u32(index, &mut pos)?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let path_len = u16::from_le_bytes(take(index, &mut pos, 2)?.try_into().unwrap()) as usize;
+            let path = String::from_utf8(take(index, &mut pos, path_len)?.to_vec())
+                .map_err(|_| "Invalid path in archive")?;
+            check_path(&path)?;
+            let kind_and_codec = take(index, &mut pos, 2)?;
+            let (kind, deduplicated) = match kind_and_codec[0] {
+                0 => (EntryKind::File, false),
+                1 => (EntryKind::Directory, false),
+                2 if version > 1 => (EntryKind::File, true),
+                _ => return Err("Invalid archive entry kind"),
+            };
+            let algorithm = match (kind, kind_and_codec[1]) {
+                (EntryKind::Directory, _) => None,
+                (EntryKind::File, id) => Some(Algorithm::from_id(id).ok_or(error::INVALID_ALGORITHM)?),
+            };
+            let mode = take_u32(index, &mut pos)?;
+            let mtime = take_u64(index, &mut pos)?;
+            let size = take_u64(index, &mut pos)?;
+            let offset = take_u64(index, &mut pos)?;
+            let length = take_u64(index, &mut pos)?;
+            if offset.checked_add(length).is_none_or(|end| end > index_offset as u64) {
+                return Err("Archive entry points outside the archive");
+            }
+            entries.push(Entry { path, kind, algorithm, mode, mtime, size, deduplicated, offset, length });
+        }
+
+        let mut chunks = Vec::new();
+        if version > 1 {
+            let count = take_u32(index, &mut pos)?;
+            for _ in 0..count {
+                let hash = take(index, &mut pos, 32)?.try_into().unwrap();
+                let raw_len = take_u32(index, &mut pos)?;
+                let offset = take_u64(index, &mut pos)?;
+                let length = take_u64(index, &mut pos)?;
+                if offset.checked_add(length).is_none_or(|end| end > index_offset as u64) {
+                    return Err("Archive chunk points outside the archive");
+                }
+                chunks.push(Chunk { hash, raw_len, offset, length });
+            }
+        }
+        let chunk_numbers = chunks.iter().enumerate().map(|(i, chunk)| (chunk.hash, i as u32)).collect();
+
+        // Anything written back out uses the current layout.
+        bytes.truncate(index_offset);
+        bytes[MAGIC.len()] = VERSION;
+        Ok(Self { data: bytes, entries, chunks, chunk_numbers })
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Compresses `contents` into the archive. `None` picks the codec the
+    /// same way `Auto` does. An existing entry with the same path is replaced
+    /// and its payload dropped.
+    pub fn add_file(
+        &mut self,
+        path: &str,
+        contents: &[u8],
+        mode: u32,
+        mtime: u64,
+        algorithm: Option<FileAlgorithm>,
+    ) -> Result<(), &'static str> {
+        check_path(path)?;
+        let algorithm = algorithm.unwrap_or_else(|| codec::auto_select(contents, None));
+        let payload = codec::compress(contents, algorithm, None)?;
+        let replaced = self.push(Entry {
+            path: path.to_string(),
+            kind: EntryKind::File,
+            algorithm: Some(codec::header_algorithm(algorithm)),
+            mode,
+            mtime,
+            size: contents.len() as u64,
+            deduplicated: false,
+            offset: self.data.len() as u64,
+            length: payload.len() as u64,
+        });
+        self.data.extend_from_slice(&payload);
+        if replaced {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Like `add_file`, but cuts `contents` into content-defined chunks and
+    /// only stores the ones no file in the archive has yet.
+    pub fn add_file_deduplicated(
+        &mut self,
+        path: &str,
+        contents: &[u8],
+        mode: u32,
+        mtime: u64,
+        algorithm: Option<FileAlgorithm>,
+    ) -> Result<(), &'static str> {
+        check_path(path)?;
+        let algorithm = algorithm.unwrap_or_else(|| codec::auto_select(contents, None));
+        let mut numbers = Vec::new();
+        for chunk in dedup::chunks(contents) {
+            let hash = dedup::chunk_hash(chunk);
+            let number = match self.chunk_numbers.get(&hash) {
+                Some(&number) => number,
+                None => {
+                    let payload = codec::compress(chunk, algorithm, None)?;
+                    let number = self.chunks.len() as u32;
+                    self.chunks.push(Chunk {
+                        hash,
+                        raw_len: chunk.len() as u32,
+                        offset: self.data.len() as u64,
+                        length: payload.len() as u64,
+                    });
+                    self.chunk_numbers.insert(hash, number);
+                    self.data.extend_from_slice(&payload);
+                    number
+                }
+            };
+            numbers.extend_from_slice(&number.to_le_bytes());
+        }
+
+        let replaced = self.push(Entry {
+            path: path.to_string(),
+            kind: EntryKind::File,
+            algorithm: Some(codec::header_algorithm(algorithm)),
+            mode,
+            mtime,
+            size: contents.len() as u64,
+            deduplicated: true,
+            offset: self.data.len() as u64,
+            length: numbers.len() as u64,
+        });
+        self.data.extend_from_slice(&numbers);
+        if replaced {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    pub fn add_directory(&mut self, path: &str, mode: u32, mtime: u64) -> Result<(), &'static str> {
+        check_path(path)?;
+        let replaced = self.push(Entry {
+            path: path.to_string(),
+            kind: EntryKind::Directory,
+            algorithm: None,
+            mode,
+            mtime,
+            size: 0,
+            deduplicated: false,
+            offset: self.data.len() as u64,
+            length: 0,
+        });
+        if replaced {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Returns whether an entry with the same path was replaced.
+    fn push(&mut self, entry: Entry) -> bool {
+        let count = self.entries.len();
+        self.entries.retain(|existing| existing.path != entry.path);
+        let replaced = self.entries.len() < count;
+        self.entries.push(entry);
+        replaced
+    }
+
+    /// Rewrites `data` with only the payloads and chunks the entries still
+    /// refer to, in entry order. Nothing is recompressed; chunks are
+    /// renumbered in order of first use.
+    fn compact(&mut self) -> Result<(), &'static str> {
+        let mut data = self.data[..MAGIC.len() + 1].to_vec();
+        let mut entries = self.entries.clone();
+        let mut chunks: Vec<Chunk> = Vec::new();
+        let mut renumbered: HashMap<usize, u32> = HashMap::new();
+        for entry in &mut entries {
+            let payload = &self.data[entry.offset as usize..(entry.offset + entry.length) as usize];
+            let payload = match entry.deduplicated {
+                true => {
+                    let mut numbers = Vec::with_capacity(payload.len());
+                    for number in Self::chunk_numbers(payload)? {
+                        let new_number = match renumbered.get(&number) {
+                            Some(&new_number) => new_number,
+                            None => {
+                                let chunk = self.chunks.get(number).ok_or("Invalid chunk reference in archive")?;
+                                let new_number = chunks.len() as u32;
+                                chunks.push(Chunk { offset: data.len() as u64, ..chunk.clone() });
+                                data.extend_from_slice(&self.data[chunk.offset as usize..(chunk.offset + chunk.length) as usize]);
+                                renumbered.insert(number, new_number);
+                                new_number
+                            }
+                        };
+                        numbers.extend_from_slice(&new_number.to_le_bytes());
+                    }
+                    numbers
+                }
+                false => payload.to_vec(),
+            };
+            entry.offset = data.len() as u64;
+            data.extend_from_slice(&payload);
+        }
+
+        self.chunk_numbers = chunks.iter().enumerate().map(|(i, chunk)| (chunk.hash, i as u32)).collect();
+        self.data = data;
+        self.entries = entries;
+        self.chunks = chunks;
+        Ok(())
+    }
+
+    /// Decompresses a file entry's contents.
+    pub fn read(&self, entry: &Entry) -> Result<Vec<u8>, &'static str> {
+        if entry.kind == EntryKind::Directory {
+            return Err("Entry is a directory");
+        }
+        let payload = &self.data[entry.offset as usize..(entry.offset + entry.length) as usize];
+        let contents = match entry.deduplicated {
+            true => self.read_chunks(payload)?,
+            false => codec::decompress(payload, None)?,
+        };
+        if contents.len() as u64 != entry.size {
+            return Err(error::ENTRY_SIZE_MISMATCH);
+        }
+        Ok(contents)
+    }
+
+    fn chunk_numbers(payload: &[u8]) -> Result<impl Iterator<Item = usize> + '_, &'static str> {
+        if !payload.len().is_multiple_of(4) {
+            return Err("Invalid chunk list in archive");
+        }
+        Ok(payload.chunks_exact(4).map(|number| u32::from_le_bytes(number.try_into().unwrap()) as usize))
+    }
+
+    fn read_chunks(&self, payload: &[u8]) -> Result<Vec<u8>, &'static str> {
+        let mut contents = Vec::new();
+        for number in Self::chunk_numbers(payload)? {
+            let chunk = self.chunks.get(number).ok_or("Invalid chunk reference in archive")?;
+            let data = codec::decompress(&self.data[chunk.offset as usize..(chunk.offset + chunk.length) as usize], None)?;
+            if data.len() != chunk.raw_len as usize || dedup::chunk_hash(&data) != chunk.hash {
+                return Err(error::CHUNK_CHECKSUM);
+            }
+            contents.extend_from_slice(&data);
+        }
+        Ok(contents)
+    }
+
+    /// How much sharing chunks saves across the deduplicated files.
+    pub fn dedup_stats(&self) -> DedupStats {
+        let mut stats = DedupStats::default();
+        let mut seen = HashSet::new();
+        for entry in self.entries.iter().filter(|entry| entry.deduplicated) {
+            stats.bytes += entry.size;
+            let payload = &self.data[entry.offset as usize..(entry.offset + entry.length) as usize];
+            for number in Self::chunk_numbers(payload).into_iter().flatten() {
+                stats.chunks += 1;
+                if let Some(chunk) = self.chunks.get(number).filter(|_| seen.insert(number)) {
+                    stats.unique_chunks += 1;
+                    stats.unique_bytes += chunk.raw_len as u64;
+                }
+            }
+        }
+        stats
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.data.clone();
+        let index_offset = bytes.len() as u64;
+        bytes.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for entry in &self.entries {
+            bytes.extend_from_slice(&(entry.path.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(entry.path.as_bytes());
+            bytes.push(match (entry.kind, entry.deduplicated) {
+                (EntryKind::File, false) => 0,
+                (EntryKind::Directory, _) => 1,
+                (EntryKind::File, true) => 2,
+            });
+            bytes.push(entry.algorithm.map_or(0, Algorithm::id));
+            bytes.extend_from_slice(&entry.mode.to_le_bytes());
+            bytes.extend_from_slice(&entry.mtime.to_le_bytes());
+            bytes.extend_from_slice(&entry.size.to_le_bytes());
+            bytes.extend_from_slice(&entry.offset.to_le_bytes());
+            bytes.extend_from_slice(&entry.length.to_le_bytes());
+        }
+        bytes.extend_from_slice(&(self.chunks.len() as u32).to_le_bytes());
+        for chunk in &self.chunks {
+            bytes.extend_from_slice(&chunk.hash);
+            bytes.extend_from_slice(&chunk.raw_len.to_le_bytes());
+            bytes.extend_from_slice(&chunk.offset.to_le_bytes());
+            bytes.extend_from_slice(&chunk.length.to_le_bytes());
+        }
+        bytes.extend_from_slice(&index_offset.to_le_bytes());
+        bytes.extend_from_slice(INDEX_MAGIC);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_roundtrip_and_add() {
+        let text = b"hello hello hello archive".repeat(20);
+        let mut archive = Archive::new();
+        archive.add_directory("docs", 0o755, 1_700_000_000).unwrap();
+        archive.add_file("docs/readme.txt", &text, 0o644, 1_700_000_001, None).unwrap();
+        archive.add_file("empty.bin", b"", 0o600, 0, Some(FileAlgorithm::LZ4)).unwrap();
+
+        let mut reopened = Archive::open(archive.to_bytes()).unwrap();
+        assert_eq!(reopened.entries(), archive.entries());
+        assert_eq!(reopened.read(&reopened.entries()[1]).unwrap(), text);
+        assert_eq!(reopened.entries()[2].algorithm, Some(Algorithm::LZ4));
+
+        // Adding replaces an entry with the same path and keeps the others.
+        reopened.add_file("docs/readme.txt", b"v2", 0o644, 1_800_000_000, Some(FileAlgorithm::RLE)).unwrap();
+        let updated = Archive::open(reopened.to_bytes()).unwrap();
+        let paths: Vec<&str> = updated.entries().iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["docs", "empty.bin", "docs/readme.txt"]);
+        assert_eq!(updated.read(&updated.entries()[2]).unwrap(), b"v2");
+    }
+
+    #[test]
+    fn test_replacing_entries_drops_old_payloads() {
+        let noise: Vec<u8> = (0..20_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8).collect();
+        let mut archive = Archive::new();
+        archive.add_file("keep.bin", &noise[..5000], 0o644, 0, None).unwrap();
+        archive.add_file("data.bin", &noise, 0o644, 0, None).unwrap();
+        let size = archive.to_bytes().len();
+        for round in 0..5u8 {
+            let mut updated = noise.clone();
+            updated[0] = round;
+            archive = Archive::open(archive.to_bytes()).unwrap();
+            archive.add_file("data.bin", &updated, 0o644, 0, None).unwrap();
+        }
+        assert!(archive.to_bytes().len() < size + 100);
+        assert_eq!(archive.read(&archive.entries()[0]).unwrap(), &noise[..5000]);
+        assert_eq!(archive.read(&archive.entries()[1]).unwrap()[0], 4);
+
+        // Chunks only the replaced file used go too; shared ones stay.
+        let shared: Vec<u8> = (0..30_000u32).flat_map(|i| format!("row {} of the nightly dump\n", i).into_bytes()).collect();
+        let mut archive = Archive::new();
+        archive.add_file_deduplicated("monday.sql", &shared, 0o644, 0, None).unwrap();
+        archive.add_file_deduplicated("tuesday.sql", &noise, 0o644, 0, None).unwrap();
+        archive.add_file_deduplicated("tuesday.sql", &shared[..20_000], 0o644, 0, None).unwrap();
+        let alone = {
+            let mut archive = Archive::new();
+            archive.add_file_deduplicated("monday.sql", &shared, 0o644, 0, None).unwrap();
+            archive.to_bytes().len()
+        };
+        let reopened = Archive::open(archive.to_bytes()).unwrap();
+        assert!(reopened.to_bytes().len() < alone + alone / 4);
+        assert_eq!(reopened.read(&reopened.entries()[0]).unwrap(), shared);
+        assert_eq!(reopened.read(&reopened.entries()[1]).unwrap(), &shared[..20_000]);
+    }
+
+    #[test]
+    fn test_rejects_unsafe_paths_and_truncation() {
+        let mut archive = Archive::new();
+        for path in ["", "/etc/passwd", "../up", "a//b", "a/./b", "a\\b"] {
+            assert!(archive.add_file(path, b"x", 0o644, 0, None).is_err(), "{:?}", path);
+        }
+        archive.add_file("ok.txt", b"x", 0o644, 0, None).unwrap();
+        let bytes = archive.to_bytes();
+        assert!(Archive::open(bytes[..bytes.len() - 1].to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_deduplicated_files_share_chunks() {
+        let shared: Vec<u8> = (0..30_000u32).flat_map(|i| format!("row {} of the nightly dump\n", i).into_bytes()).collect();
+        let mut archive = Archive::new();
+        archive.add_file_deduplicated("monday.sql", &shared, 0o644, 0, None).unwrap();
+        let stored = archive.to_bytes().len();
+        let tuesday = [shared.as_slice(), b"one more row\n"].concat();
+        archive.add_file_deduplicated("tuesday.sql", &tuesday, 0o644, 0, None).unwrap();
+        archive.add_file("plain.txt", b"not chunked", 0o644, 0, None).unwrap();
+
+        // The second copy adds little more than its chunk list and last chunk.
+        let reopened = Archive::open(archive.to_bytes()).unwrap();
+        assert!(reopened.to_bytes().len() < stored + stored / 4);
+        assert_eq!(reopened.entries(), archive.entries());
+        assert_eq!(reopened.read(&reopened.entries()[0]).unwrap(), shared);
+        assert_eq!(reopened.read(&reopened.entries()[1]).unwrap(), tuesday);
+        assert_eq!(reopened.read(&reopened.entries()[2]).unwrap(), b"not chunked");
+        assert!(reopened.dedup_stats().ratio() > 1.8);
+    }
+}
@@ -0,0 +1,473 @@
+// Whole-file compress and decompress, header included, shared by the CLI and
+// the wasm bindings.
+
+use crate::algos::dictionary::dictionary_id;
+use crate::algos::file_type::{detect_file_type, explain_selection, select_algorithm, Algorithm, SelectionReport};
+use crate::algos::filters::{self, Filter};
+use crate::algos::range_coder::{self, Mode};
+use crate::algos::{bwt, le, lz, lz4, packbits};
+use crate::checksum::xxh32;
+use crate::error;
+use crate::{dedup, framed, jscmp};
+use crate::header::{read_header, Algorithm as HeaderAlgorithm, Header, Metadata};
+use crate::progress::Progress;
+use crate::report::Report;
+
+pub fn header_algorithm(algorithm: Algorithm) -> HeaderAlgorithm {
+    match algorithm {
+        Algorithm::RLE => HeaderAlgorithm::RLE,
+        Algorithm::LZ => HeaderAlgorithm::LZ,
+        Algorithm::PackBits => HeaderAlgorithm::PackBits,
+        Algorithm::BWT => HeaderAlgorithm::BWT,
+        Algorithm::RC => HeaderAlgorithm::RC,
+        Algorithm::LZ4 => HeaderAlgorithm::LZ4,
+    }
+}
+
+/// Only the LZ codecs with a history window can make use of a dictionary.
+pub fn supports_dictionary(algorithm: Algorithm) -> bool {
+    matches!(algorithm, Algorithm::LZ | Algorithm::RC)
+}
+
+/// The algorithm `Auto` picks for `input`. With a dictionary the choice is
+/// limited to LZ, the codec dictionaries are trained for.
+pub fn auto_select(input: &[u8], dictionary: Option<&[u8]>) -> Algorithm {
+    match dictionary {
+        Some(_) => Algorithm::LZ,
+        None => select_algorithm(detect_file_type(input), input),
+    }
+}
+
+/// `explain_selection` for a run that compresses `input` with `selected`
+/// and `filters`, which the report names as the decision. Codecs that take a
+/// dictionary are measured with `dictionary`, as the run would use it.
+pub fn explain(input: &[u8], dictionary: Option<&[u8]>, selected: Algorithm, filters: &[Filter]) -> SelectionReport {
+    let mut report = explain_selection(input);
+    if let Some(dictionary) = dictionary {
+        for (algorithm, size) in report.candidates.iter_mut().filter(|(algorithm, _)| supports_dictionary(*algorithm)) {
+            *size = compress_payload(input, *algorithm, &[], Some(dictionary)).map_or(*size, |payload| payload.len());
+        }
+    }
+    report.selected = selected;
+    report.filters = filters.to_vec();
+    report
+}
+
+/// Runs `filters` over `input`, then the codec. Framed blocks and dedup
+/// chunks are each filtered on their own.
+pub fn compress_payload(
+    input: &[u8],
+    algorithm: Algorithm,
+    filters: &[Filter],
+    dictionary: Option<&[u8]>,
+) -> Result<Vec<u8>, &'static str> {
+    compress_payload_with_report(input, algorithm, filters, dictionary, &mut Report::none())
+}
+
+/// Like `compress_payload`, with the codec reporting its position to
+/// `report`.
+pub fn compress_payload_with_report(
+    input: &[u8],
+    algorithm: Algorithm,
+    filters: &[Filter],
+    dictionary: Option<&[u8]>,
+    report: &mut Report,
+) -> Result<Vec<u8>, &'static str> {
+    if dictionary.is_some() && !supports_dictionary(algorithm) {
+        return Err(error::DICTIONARY_UNSUPPORTED);
+    }
+
+    let filtered;
+    let input = match filters {
+        [] => input,
+        _ => {
+            filtered = filters::encode_chain(filters, input);
+            &filtered
+        }
+    };
+    match algorithm {
+        Algorithm::RLE => le::compress_with_report(input, report),
+        Algorithm::LZ => lz::compress_with_report(input, dictionary.unwrap_or_default(), report),
+        Algorithm::PackBits => packbits::compress_with_report(input, report),
+        Algorithm::BWT => bwt::compress_with_report(input, report),
+        Algorithm::RC => range_coder::compress_with_report(input, Mode::LZ, dictionary.unwrap_or_default(), report),
+        Algorithm::LZ4 => lz4::compress_with_report(input, report),
+    }
+}
+
+/// Compresses `input` and prepends the header describing it.
+pub fn compress(input: &[u8], algorithm: Algorithm, dictionary: Option<&[u8]>) -> Result<Vec<u8>, &'static str> {
+    compress_with_metadata(input, algorithm, dictionary, None)
+}
+
+/// Like `compress`, also storing `metadata` in the header. LZ4 frames have no
+/// RSCMP header, so they can't carry metadata.
+pub fn compress_with_metadata(
+    input: &[u8],
+    algorithm: Algorithm,
+    dictionary: Option<&[u8]>,
+    metadata: Option<&Metadata>,
+) -> Result<Vec<u8>, &'static str> {
+    compress_with_filters(input, algorithm, &[], dictionary, metadata)
+}
+
+/// Like `compress_with_metadata`, running `filters` before the codec and
+/// recording them in the header.
+pub fn compress_with_filters(
+    input: &[u8],
+    algorithm: Algorithm,
+    filters: &[Filter],
+    dictionary: Option<&[u8]>,
+    metadata: Option<&Metadata>,
+) -> Result<Vec<u8>, &'static str> {
+    compress_with_progress(input, algorithm, filters, dictionary, metadata, &mut Progress::none())
+}
+
+/// Like `compress_with_filters`, reporting to `progress` as the codec works
+/// through the input and stopping early if it is cancelled.
+pub fn compress_with_progress(
+    input: &[u8],
+    algorithm: Algorithm,
+    filters: &[Filter],
+    dictionary: Option<&[u8]>,
+    metadata: Option<&Metadata>,
+    progress: &mut Progress,
+) -> Result<Vec<u8>, &'static str> {
+    let header = build_header(algorithm, filters, dictionary, metadata)?;
+    progress.start(input.len() as u64)?;
+    let payload = compress_payload_with_report(input, algorithm, filters, dictionary, &mut progress.report())?;
+    progress.finish();
+    Ok([header.to_bytes(), payload].concat())
+}
+
+pub(crate) fn build_header(
+    algorithm: Algorithm,
+    filters: &[Filter],
+    dictionary: Option<&[u8]>,
+    metadata: Option<&Metadata>,
+) -> Result<Header, &'static str> {
+    if let Some(metadata) = metadata {
+        if algorithm == Algorithm::LZ4 {
+            return Err("LZ4 frames cannot carry header metadata");
+        }
+        metadata.check()?;
+    }
+    if !filters.is_empty() && algorithm == Algorithm::LZ4 {
+        return Err("LZ4 frames have no header to record filters in");
+    }
+    filters::check_chain(filters)?;
+    let mut header = Header::new(header_algorithm(algorithm));
+    header.dictionary_id = dictionary.map(dictionary_id);
+    header.metadata = metadata.cloned();
+    header.filters = filters.to_vec();
+    Ok(header)
+}
+
+/// Reverses `compress_payload`: the codec, then `filters` in reverse.
+pub fn decompress_payload(
+    data: &[u8],
+    algorithm: HeaderAlgorithm,
+    filters: &[Filter],
+    dictionary: Option<&[u8]>,
+) -> Result<Vec<u8>, &'static str> {
+    decompress_payload_with_report(data, algorithm, filters, dictionary, &mut Report::none())
+}
+
+/// Like `decompress_payload`, with the codec reporting its position in
+/// `data` to `report`.
+pub fn decompress_payload_with_report(
+    data: &[u8],
+    algorithm: HeaderAlgorithm,
+    filters: &[Filter],
+    dictionary: Option<&[u8]>,
+    report: &mut Report,
+) -> Result<Vec<u8>, &'static str> {
+    let output = decode_payload(data, algorithm, dictionary, report)?;
+    Ok(filters::decode_chain(filters, output))
+}
+
+fn decode_payload(
+    data: &[u8],
+    algorithm: HeaderAlgorithm,
+    dictionary: Option<&[u8]>,
+    report: &mut Report,
+) -> Result<Vec<u8>, &'static str> {
+    match algorithm {
+        HeaderAlgorithm::RLE => le::decompress_with_report(data, report),
+        HeaderAlgorithm::LZ => lz::decompress_with_report(data, dictionary.unwrap_or_default(), report),
+        HeaderAlgorithm::PackBits => packbits::decompress_with_report(data, report),
+        HeaderAlgorithm::BWT => bwt::decompress_with_report(data, report),
+        HeaderAlgorithm::RC => range_coder::decompress_with_report(data, dictionary.unwrap_or_default(), report),
+        HeaderAlgorithm::LZ4 => lz4::decompress_with_report(data, report),
+    }
+}
+
+/// Checks that the caller supplied exactly the dictionary the header asks for.
+pub fn check_dictionary(header: &Header, dictionary: Option<&[u8]>) -> Result<(), &'static str> {
+    match (header.dictionary_id, dictionary) {
+        (None, None) => Ok(()),
+        (Some(_), None) => Err(error::DICTIONARY_REQUIRED),
+        (None, Some(_)) => Err(error::DICTIONARY_UNEXPECTED),
+        (Some(id), Some(dictionary)) if id != dictionary_id(dictionary) => {
+            Err(error::DICTIONARY_MISMATCH)
+        }
+        (Some(_), Some(_)) => Ok(()),
+    }
+}
+
+/// Encrypted files have to go through `crypto::decrypt` first.
+pub fn check_encryption(header: &Header) -> Result<(), &'static str> {
+    match header.encryption {
+        Some(_) => Err(error::PASSPHRASE_REQUIRED),
+        None => Ok(()),
+    }
+}
+
+/// Reads the header and decompresses the payload it describes.
+pub fn decompress(input: &[u8], dictionary: Option<&[u8]>) -> Result<Vec<u8>, &'static str> {
+    decompress_with_repairs(input, dictionary).map(|(output, _)| output)
+}
+
+/// Like `decompress`, also returning the numbers of framed blocks that were
+/// damaged and rebuilt from parity.
+pub fn decompress_with_repairs(input: &[u8], dictionary: Option<&[u8]>) -> Result<(Vec<u8>, Vec<usize>), &'static str> {
+    decompress_with_progress(input, dictionary, &mut Progress::none())
+}
+
+/// Like `decompress_with_repairs`, reporting to `progress` as the payload is
+/// decoded and stopping early if it is cancelled. js-compressor's JSCMP
+/// files are decoded too.
+pub fn decompress_with_progress(
+    input: &[u8],
+    dictionary: Option<&[u8]>,
+    progress: &mut Progress,
+) -> Result<(Vec<u8>, Vec<usize>), &'static str> {
+    if jscmp::is_jscmp(input) {
+        if dictionary.is_some() {
+            return Err(error::DICTIONARY_UNEXPECTED);
+        }
+        progress.start(input.len() as u64)?;
+        let output = jscmp::decompress_with_report(input, &mut progress.report())?;
+        progress.finish();
+        return Ok((output, Vec::new()));
+    }
+    let header = read_header(input)?;
+    if header.reference_checksum.is_some() {
+        return Err(error::IS_PATCH);
+    }
+    check_encryption(&header)?;
+    check_dictionary(&header, dictionary)?;
+    let payload = &input[header.size()..];
+    match header.block_size {
+        Some(_) => framed::decompress_with_repairs(payload, header.algorithm, &header.filters, dictionary, progress),
+        None if header.deduplicated => {
+            Ok((dedup::decompress(payload, header.algorithm, &header.filters, dictionary, progress)?, Vec::new()))
+        }
+        None => {
+            progress.start(payload.len() as u64)?;
+            let output =
+                decompress_payload_with_report(payload, header.algorithm, &header.filters, dictionary, &mut progress.report())?;
+            progress.finish();
+            Ok((output, Vec::new()))
+        }
+    }
+}
+
+/// Where decoding `input` went wrong, as near as it can be pinned down: 0 if
+/// the header is bad, the first damaged block of a framed file, or else the
+/// start of the payload.
+pub fn error_offset(input: &[u8], dictionary: Option<&[u8]>) -> usize {
+    let Ok(header) = read_header(input) else {
+        return 0;
+    };
+    let payload = &input[header.size()..];
+    match header.block_size {
+        Some(_) => framed::first_damaged(payload, header.algorithm, &header.filters, dictionary)
+            .map_or(header.size(), |pos| header.size() + pos),
+        None => header.size(),
+    }
+}
+
+/// Decompresses `length` bytes starting at `start`. Framed files only decode
+/// the blocks the range touches; other files are decoded whole and sliced.
+pub fn decompress_range(
+    input: &[u8],
+    start: u64,
+    length: usize,
+    dictionary: Option<&[u8]>,
+) -> Result<Vec<u8>, &'static str> {
+    let header = read_header(input)?;
+    if header.block_size.is_some() {
+        check_encryption(&header)?;
+        check_dictionary(&header, dictionary)?;
+        return framed::decompress_range(input, &header, dictionary, start, length);
+    }
+
+    let data = decompress(input, dictionary)?;
+    let start = usize::try_from(start).unwrap_or(usize::MAX).min(data.len());
+    let end = start.saturating_add(length).min(data.len());
+    Ok(data[start..end].to_vec())
+}
+
+/// Encodes `new` as a patch against `old`: range coded LZ with `old` as the
+/// history window, so unchanged regions become long matches into it.
+pub fn diff(old: &[u8], new: &[u8]) -> Vec<u8> {
+    let mut header = Header::new(HeaderAlgorithm::RC);
+    header.reference_checksum = Some(xxh32(old, 0));
+    let payload = range_coder::compress_with_dictionary(new, Mode::LZ, old);
+    [header.to_bytes(), payload].concat()
+}
+
+/// Rebuilds the new file from `old` and a patch made by `diff`.
+pub fn patch(old: &[u8], patch: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let header = read_header(patch)?;
+    match header.reference_checksum {
+        None => Err(error::NOT_A_PATCH),
+        Some(checksum) if checksum != xxh32(old, 0) => Err(error::WRONG_BASE),
+        Some(_) => decompress_payload(&patch[header.size()..], header.algorithm, &header.filters, Some(old)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dictionary_is_recorded_and_checked() {
+        let dictionary = b"{\"status\":\"active\",\"region\":\"eu-west-1\"}".to_vec();
+        let other = b"something else entirely".to_vec();
+        let input = b"{\"id\":1,\"status\":\"active\",\"region\":\"eu-west-1\"}";
+
+        for algorithm in [Algorithm::LZ, Algorithm::RC] {
+            let compressed = compress(input, algorithm, Some(&dictionary)).unwrap();
+            assert_eq!(read_header(&compressed).unwrap().dictionary_id, Some(dictionary_id(&dictionary)));
+            assert_eq!(decompress(&compressed, Some(&dictionary)).unwrap(), input.to_vec());
+            assert!(decompress(&compressed, Some(&other)).is_err());
+            assert!(decompress(&compressed, None).is_err());
+        }
+    }
+
+    #[test]
+    fn test_explain_reports_the_runs_decision() {
+        let dictionary = b"{\"status\": \"ok\", \"items\": [], \"count\": 0}".repeat(4);
+        let input = b"{\"status\": \"ok\", \"items\": [1], \"count\": 1}";
+        let selected = auto_select(input, Some(&dictionary));
+        let report = explain(input, Some(&dictionary), selected, &[]);
+        assert_eq!(report.selected, Algorithm::LZ);
+        let lz = report.candidates.iter().find(|(algorithm, _)| *algorithm == Algorithm::LZ).unwrap().1;
+        assert_eq!(lz, compress_payload(input, Algorithm::LZ, &[], Some(&dictionary)).unwrap().len());
+        assert!(lz < lz::compress(input).len());
+
+        let chain = [Filter::Delta(1)];
+        let report = explain(input, None, Algorithm::RC, &chain);
+        assert_eq!((report.selected, report.filters), (Algorithm::RC, chain.to_vec()));
+    }
+
+    #[test]
+    fn test_dictionary_rejected_for_other_codecs() {
+        assert!(compress(b"data", Algorithm::BWT, Some(b"dict")).is_err());
+        let plain = compress(b"data", Algorithm::PackBits, None).unwrap();
+        assert_eq!(decompress(&plain, None).unwrap(), b"data".to_vec());
+    }
+
+    #[test]
+    fn test_patch_roundtrip_and_wrong_base() {
+        let old: Vec<u8> = (0..50_000u32).flat_map(|i| format!("line {} of the build\n", i).into_bytes()).collect();
+        let mut new = old.clone();
+        new.splice(20_000..20_010, b"patched!!!".iter().copied());
+        new.extend_from_slice(b"appended trailer\n");
+
+        let delta = diff(&old, &new);
+        assert!(delta.len() < 1024);
+        assert_eq!(patch(&old, &delta).unwrap(), new);
+        assert!(patch(&new, &delta).is_err());
+        assert!(decompress(&delta, None).is_err());
+    }
+
+    #[test]
+    fn test_metadata_is_stored() {
+        let metadata = Metadata { filename: Some("notes.txt".to_string()), mode: Some(0o600), ..Metadata::default() };
+        let compressed = compress_with_metadata(b"some notes", Algorithm::LZ, None, Some(&metadata)).unwrap();
+        assert_eq!(read_header(&compressed).unwrap().metadata, Some(metadata.clone()));
+        assert_eq!(decompress(&compressed, None).unwrap(), b"some notes".to_vec());
+        assert!(compress_with_metadata(b"x", Algorithm::LZ4, None, Some(&metadata)).is_err());
+    }
+
+    #[test]
+    fn test_unknown_metadata_tags_still_decode() {
+        // A newer writer's tag 0x7F ahead of the filename: the payload starts
+        // after both, although only the filename is understood.
+        let metadata = Metadata { filename: Some("notes.txt".to_string()), ..Metadata::default() };
+        let compressed = compress_with_metadata(b"some notes, some notes", Algorithm::LZ, None, Some(&metadata)).unwrap();
+        // With no other flags set, the metadata length follows the 8-byte
+        // base header.
+        let len_at = 8;
+        let len = u16::from_le_bytes([compressed[len_at], compressed[len_at + 1]]);
+        let unknown = [0x7F, 3, 0, 1, 2, 3];
+        let mut file = compressed[..len_at].to_vec();
+        file.extend_from_slice(&(len + unknown.len() as u16).to_le_bytes());
+        file.extend_from_slice(&unknown);
+        file.extend_from_slice(&compressed[len_at + 2..]);
+
+        assert_eq!(read_header(&file).unwrap().metadata, Some(metadata));
+        assert_eq!(decompress(&file, None).unwrap(), b"some notes, some notes".to_vec());
+    }
+
+    #[test]
+    fn test_filters_are_recorded_and_reversed() {
+        // Little-endian u32 counter samples: delta plus transpose turns them
+        // into long runs.
+        let samples: Vec<u8> = (0..20_000u32).flat_map(|i| (1_000_000 + i * 3).to_le_bytes()).collect();
+        let chain = [Filter::Delta(4), Filter::Transpose(4)];
+        let filtered = compress_with_filters(&samples, Algorithm::LZ, &chain, None, None).unwrap();
+        assert!(filtered.len() * 4 < compress(&samples, Algorithm::LZ, None).unwrap().len());
+        assert_eq!(read_header(&filtered).unwrap().filters, chain);
+        assert_eq!(decompress(&filtered, None).unwrap(), samples);
+
+        let options = framed::FrameOptions { block_size: 10_000, parity: 1 };
+        let framed = framed::compress(&samples, Algorithm::RC, &chain, None, None, &options, &mut Progress::none()).unwrap();
+        assert_eq!(decompress(&framed, None).unwrap(), samples);
+        assert_eq!(decompress_range(&framed, 9_990, 20, None).unwrap(), samples[9_990..10_010]);
+        let (deduplicated, _) = dedup::compress(&samples, Algorithm::LZ, &chain, None, None, &mut Progress::none()).unwrap();
+        assert_eq!(decompress(&deduplicated, None).unwrap(), samples);
+
+        assert!(compress_with_filters(&samples, Algorithm::LZ4, &chain, None, None).is_err());
+    }
+
+    #[test]
+    fn test_plain_files_report_progress_from_the_codec() {
+        use crate::progress::{CancellationToken, CANCELLED};
+
+        let input: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8 ^ (i / 4096) as u8).collect();
+        for algorithm in [Algorithm::RLE, Algorithm::LZ, Algorithm::PackBits, Algorithm::BWT, Algorithm::RC, Algorithm::LZ4] {
+            let mut reports = Vec::new();
+            let mut progress = Progress::new(|done, total| reports.push((done, total)));
+            let compressed = compress_with_progress(&input, algorithm, &[], None, None, &mut progress).unwrap();
+            drop(progress);
+            assert!(reports.len() > 2, "{:?}", algorithm);
+            assert!(reports.windows(2).all(|pair| pair[0].0 < pair[1].0));
+            assert_eq!(reports.last(), Some(&(input.len() as u64, input.len() as u64)));
+            assert_eq!(compressed, compress(&input, algorithm, None).unwrap());
+
+            let mut reports = Vec::new();
+            let mut progress = Progress::new(|done, total| reports.push((done, total)));
+            let (output, _) = decompress_with_progress(&compressed, None, &mut progress).unwrap();
+            drop(progress);
+            assert_eq!(output, input);
+            assert!(reports.windows(2).all(|pair| pair[0].0 < pair[1].0));
+            assert_eq!(reports.last().map(|report| report.0), Some((compressed.len() - Header::new(header_algorithm(algorithm)).size()) as u64));
+        }
+
+        let compressed = compress(&input, Algorithm::RLE, None).unwrap();
+        let token = CancellationToken::new();
+        let mut calls = 0;
+        let mut progress = Progress::new(|_, _| {
+            calls += 1;
+            if calls == 3 {
+                token.cancel();
+            }
+        })
+        .with_cancellation(token.clone());
+        assert_eq!(decompress_with_progress(&compressed, None, &mut progress), Err(CANCELLED));
+    }
+}
@@ -0,0 +1,107 @@
+// Passphrase-based encryption of compressed files. The key is derived with
+// Argon2id from the passphrase and a random salt, and everything after the
+// header is sealed with ChaCha20-Poly1305. The header itself, with the salt,
+// nonce and KDF parameters, stays readable and is authenticated as
+// associated data, so tampering with metadata is caught too.
+
+use argon2::{Algorithm as Argon2Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+use crate::error;
+use crate::header::{read_header, Algorithm, Encryption, Header};
+
+// OWASP's recommended Argon2id settings: 19 MiB, two passes, one lane.
+const MEMORY_KIB: u32 = 19 * 1024;
+const ITERATIONS: u32 = 2;
+const LANES: u8 = 1;
+// Headers are untrusted input; cap what a file can make the KDF spend.
+const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_ITERATIONS: u32 = 64;
+
+fn derive_key(passphrase: &str, encryption: &Encryption) -> Result<Key, &'static str> {
+    if encryption.memory_kib > MAX_MEMORY_KIB || encryption.iterations > MAX_ITERATIONS {
+        return Err(error::KDF_TOO_COSTLY);
+    }
+    let params = Params::new(encryption.memory_kib, encryption.iterations, encryption.lanes as u32, Some(32))
+        .map_err(|_| error::KDF_INVALID)?;
+    let mut key = Key::default();
+    Argon2::new(Argon2Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &encryption.salt, &mut key)
+        .map_err(|_| error::KDF_FAILED)?;
+    Ok(key)
+}
+
+/// Encrypts a compressed file (header included) with `passphrase`.
+pub fn encrypt(file: &[u8], passphrase: &str) -> Result<Vec<u8>, &'static str> {
+    encrypt_with_cost(file, passphrase, MEMORY_KIB, ITERATIONS)
+}
+
+pub(crate) fn encrypt_with_cost(file: &[u8], passphrase: &str, memory_kib: u32, iterations: u32) -> Result<Vec<u8>, &'static str> {
+    let (mut header, body) = split(file)?;
+    if header.encryption.is_some() {
+        return Err(error::ALREADY_ENCRYPTED);
+    }
+
+    let mut salt = [0; 16];
+    let mut nonce = [0; 12];
+    getrandom::getrandom(&mut salt).map_err(|_| "No secure random source available")?;
+    getrandom::getrandom(&mut nonce).map_err(|_| "No secure random source available")?;
+    let encryption = Encryption { memory_kib, iterations, lanes: LANES, salt, nonce };
+    let key = derive_key(passphrase, &encryption)?;
+    header.encryption = Some(encryption);
+
+    let header_bytes = header.to_bytes();
+    let ciphertext = ChaCha20Poly1305::new(&key)
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: body, aad: &header_bytes })
+        .map_err(|_| error::ENCRYPTION_FAILED)?;
+    Ok([header_bytes, ciphertext].concat())
+}
+
+/// Reverses `encrypt`, returning the compressed file as it was before.
+pub fn decrypt(file: &[u8], passphrase: &str) -> Result<Vec<u8>, &'static str> {
+    let (mut header, body) = split(file)?;
+    let encryption = header.encryption.take().ok_or(error::NOT_ENCRYPTED)?;
+    let key = derive_key(passphrase, &encryption)?;
+
+    let plaintext = ChaCha20Poly1305::new(&key)
+        .decrypt(Nonce::from_slice(&encryption.nonce), Payload { msg: body, aad: &file[..file.len() - body.len()] })
+        .map_err(|_| error::WRONG_PASSPHRASE)?;
+    Ok([header.to_bytes(), plaintext].concat())
+}
+
+pub fn is_encrypted(file: &[u8]) -> bool {
+    read_header(file).is_ok_and(|header| header.encryption.is_some())
+}
+
+fn split(file: &[u8]) -> Result<(Header, &[u8]), &'static str> {
+    let header = read_header(file)?;
+    if header.algorithm == Algorithm::LZ4 {
+        return Err(error::LZ4_ENCRYPTION);
+    }
+    let size = header.size();
+    Ok((header, &file[size..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algos::file_type::Algorithm as FileAlgorithm;
+    use crate::codec;
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let compressed = codec::compress(b"customer export, customer export", FileAlgorithm::LZ, None).unwrap();
+        // Cheap KDF settings keep the test fast; the format records them.
+        let encrypted = encrypt_with_cost(&compressed, "correct horse", 64, 1).unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(codec::decompress(&encrypted, None).is_err());
+        assert_eq!(decrypt(&encrypted, "correct horse").unwrap(), compressed);
+        assert!(decrypt(&encrypted, "wrong horse").is_err());
+
+        let mut tampered = encrypted.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(decrypt(&tampered, "correct horse").is_err());
+    }
+}
@@ -0,0 +1,294 @@
+// Content-defined chunking and deduplication. FastCDC cuts the input where a
+// Gear rolling hash of the last 64 bytes matches a mask, so boundaries depend
+// on content rather than position: an insertion only moves the cuts next to
+// it, and a region repeated anywhere in the input (or in another file) yields
+// the same chunks. Each distinct chunk, keyed by its BLAKE2b-256 hash, is
+// compressed once and repeats refer back to it:
+//
+//   RSCMP header with the dedup flag
+//   u32 chunk count, per distinct chunk: 32-byte hash, u32 raw length,
+//       u32 payload length, payload
+//   u32 reference count, per chunk of the input: u32 index of its chunk
+//
+// All integers are little-endian. Payloads use the codec named in the header.
+
+use std::collections::HashMap;
+
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Digest};
+
+use crate::algos::file_type::Algorithm;
+use crate::algos::filters::Filter;
+use crate::codec;
+use crate::error;
+use crate::header::{Algorithm as HeaderAlgorithm, Metadata};
+use crate::progress::Progress;
+
+pub const MIN_CHUNK: usize = 4 * 1024;
+pub const AVG_CHUNK: usize = 16 * 1024;
+pub const MAX_CHUNK: usize = 64 * 1024;
+
+// Normalised chunking: a mask with more bits before the average size and
+// fewer after it pulls chunk sizes towards the average. The Gear hash shifts
+// left, so only its high bits depend on a full 64-byte window.
+const MASK_SMALL: u64 = u64::MAX << (64 - 16);
+const MASK_LARGE: u64 = u64::MAX << (64 - 12);
+
+const fn gear_table() -> [u64; 256] {
+    // splitmix64, so the table is fixed without spelling out 256 constants.
+    let mut table = [0; 256];
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+const GEAR: [u64; 256] = gear_table();
+
+pub type ChunkHash = [u8; 32];
+
+pub fn chunk_hash(chunk: &[u8]) -> ChunkHash {
+    Blake2b::<U32>::digest(chunk).into()
+}
+
+/// Length of the chunk at the start of `data`.
+fn cut(data: &[u8]) -> usize {
+    if data.len() <= MIN_CHUNK {
+        return data.len();
+    }
+    let end = data.len().min(MAX_CHUNK);
+    let normal = AVG_CHUNK.min(end);
+    let mut hash: u64 = 0;
+    for (i, &byte) in data.iter().enumerate().take(end).skip(MIN_CHUNK) {
+        hash = (hash << 1).wrapping_add(GEAR[byte as usize]);
+        let mask = if i < normal { MASK_SMALL } else { MASK_LARGE };
+        if hash & mask == 0 {
+            return i + 1;
+        }
+    }
+    end
+}
+
+/// Splits `input` into content-defined chunks.
+pub fn chunks(input: &[u8]) -> Vec<&[u8]> {
+    let mut chunks = Vec::new();
+    let mut rest = input;
+    while !rest.is_empty() {
+        let (chunk, tail) = rest.split_at(cut(rest));
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks
+}
+
+/// How much deduplication saved.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DedupStats {
+    pub chunks: usize,
+    pub unique_chunks: usize,
+    /// Bytes before deduplication.
+    pub bytes: u64,
+    /// Bytes left after storing each distinct chunk once.
+    pub unique_bytes: u64,
+}
+
+impl DedupStats {
+    /// Input size over deduplicated size; 1.0 when nothing repeated.
+    pub fn ratio(&self) -> f64 {
+        if self.unique_bytes == 0 {
+            return 1.0;
+        }
+        self.bytes as f64 / self.unique_bytes as f64
+    }
+}
+
+pub fn compress(
+    input: &[u8],
+    algorithm: Algorithm,
+    filters: &[Filter],
+    dictionary: Option<&[u8]>,
+    metadata: Option<&Metadata>,
+    progress: &mut Progress,
+) -> Result<(Vec<u8>, DedupStats), &'static str> {
+    if algorithm == Algorithm::LZ4 {
+        return Err("LZ4 frames have no header to mark deduplicated output; pick another codec");
+    }
+    let mut header = codec::build_header(algorithm, filters, dictionary, metadata)?;
+    header.deduplicated = true;
+
+    let mut stats = DedupStats { bytes: input.len() as u64, ..DedupStats::default() };
+    let mut known: HashMap<ChunkHash, u32> = HashMap::new();
+    let mut stored = Vec::new();
+    let mut references = Vec::new();
+    progress.start(input.len() as u64)?;
+    for chunk in chunks(input) {
+        let hash = chunk_hash(chunk);
+        let index = match known.get(&hash) {
+            Some(&index) => index,
+            None => {
+                let payload = codec::compress_payload(chunk, algorithm, filters, dictionary)?;
+                stored.extend_from_slice(&hash);
+                stored.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+                stored.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+                stored.extend_from_slice(&payload);
+                stats.unique_bytes += chunk.len() as u64;
+                let index = known.len() as u32;
+                known.insert(hash, index);
+                index
+            }
+        };
+        references.extend_from_slice(&index.to_le_bytes());
+        progress.advance(chunk.len() as u64)?;
+    }
+    stats.chunks = references.len() / 4;
+    stats.unique_chunks = known.len();
+
+    let mut output = header.to_bytes();
+    output.extend_from_slice(&(stats.unique_chunks as u32).to_le_bytes());
+    output.extend_from_slice(&stored);
+    output.extend_from_slice(&(stats.chunks as u32).to_le_bytes());
+    output.extend_from_slice(&references);
+    progress.finish();
+    Ok((output, stats))
+}
+
+fn take<'a>(data: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8], &'static str> {
+    let bytes = data.get(*pos..).and_then(|rest| rest.get(..len)).ok_or(error::TRUNCATED_DEDUP)?;
+    *pos += len;
+    Ok(bytes)
+}
+
+fn take_u32(data: &[u8], pos: &mut usize) -> Result<u32, &'static str> {
+    Ok(u32::from_le_bytes(take(data, pos, 4)?.try_into().unwrap()))
+}
+
+/// Payloads of the distinct chunks in `data` (which starts after the header),
+/// without decoding them.
+pub fn chunk_payloads(data: &[u8]) -> Result<Vec<&[u8]>, &'static str> {
+    let mut pos = 0;
+    let count = take_u32(data, &mut pos)?;
+    (0..count)
+        .map(|_| {
+            take(data, &mut pos, 32 + 4)?;
+            let payload_len = take_u32(data, &mut pos)? as usize;
+            take(data, &mut pos, payload_len)
+        })
+        .collect()
+}
+
+/// Decodes every chunk once and reassembles the input. `data` starts after
+/// the header.
+pub fn decompress(
+    data: &[u8],
+    algorithm: HeaderAlgorithm,
+    filters: &[Filter],
+    dictionary: Option<&[u8]>,
+    progress: &mut Progress,
+) -> Result<Vec<u8>, &'static str> {
+    let mut pos = 0;
+    progress.start(data.len() as u64)?;
+    let count = take_u32(data, &mut pos)?;
+    let mut chunks = Vec::new();
+    for _ in 0..count {
+        let start = pos;
+        let hash = take(data, &mut pos, 32)?;
+        let raw_len = take_u32(data, &mut pos)? as usize;
+        let payload_len = take_u32(data, &mut pos)? as usize;
+        let chunk = codec::decompress_payload(take(data, &mut pos, payload_len)?, algorithm, filters, dictionary)?;
+        if chunk.len() != raw_len || chunk_hash(&chunk) != hash {
+            return Err(error::CHUNK_CHECKSUM);
+        }
+        chunks.push(chunk);
+        progress.advance((pos - start) as u64)?;
+    }
+
+    let references = take_u32(data, &mut pos)? as usize;
+    let mut output = Vec::new();
+    for _ in 0..references {
+        let index = take_u32(data, &mut pos)? as usize;
+        output.extend_from_slice(chunks.get(index).ok_or("Invalid chunk reference")?);
+    }
+    progress.finish();
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::read_header;
+
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (state >> 33) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_chunk_sizes_stay_in_bounds() {
+        let input = noise(1 << 20, 1);
+        let chunks = chunks(&input);
+        assert_eq!(chunks.concat(), input);
+        for chunk in &chunks[..chunks.len() - 1] {
+            assert!((MIN_CHUNK..=MAX_CHUNK).contains(&chunk.len()), "{}", chunk.len());
+        }
+        let average = input.len() / chunks.len();
+        assert!(average > AVG_CHUNK / 2 && average < AVG_CHUNK * 2, "{}", average);
+    }
+
+    #[test]
+    fn test_boundaries_resync_after_an_insertion() {
+        let original = noise(256 * 1024, 2);
+        let mut edited = original.clone();
+        edited.splice(1000..1000, b"inserted".iter().copied());
+
+        let before: Vec<ChunkHash> = chunks(&original).into_iter().map(chunk_hash).collect();
+        let after: Vec<ChunkHash> = chunks(&edited).into_iter().map(chunk_hash).collect();
+        let shared = after.iter().filter(|hash| before.contains(hash)).count();
+        assert!(shared >= before.len() - 2);
+    }
+
+    #[test]
+    fn test_dedup_roundtrip() {
+        let block = noise(200_000, 3);
+        let input = [block.clone(), b"between".to_vec(), block.clone(), block].concat();
+        let (compressed, stats) = compress(&input, Algorithm::PackBits, &[], None, None, &mut Progress::none()).unwrap();
+        assert!(stats.ratio() > 2.0, "{:?}", stats);
+        assert!(compressed.len() < input.len() * 2 / 3);
+
+        let header = read_header(&compressed).unwrap();
+        assert!(header.deduplicated);
+        assert_eq!(decompress(&compressed[header.size()..], header.algorithm, &[], None, &mut Progress::none()).unwrap(), input);
+        assert_eq!(codec::decompress(&compressed, None).unwrap(), input);
+
+        let (empty, stats) = compress(b"", Algorithm::RC, &[], None, None, &mut Progress::none()).unwrap();
+        assert_eq!(stats.ratio(), 1.0);
+        assert_eq!(codec::decompress(&empty, None).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_lengths_past_the_end_are_errors() {
+        let mut pos = 2;
+        assert!(take(b"abcd", &mut pos, usize::MAX).is_err());
+        assert!(take(b"abcd", &mut pos, 2).is_ok());
+        assert_eq!(pos, 4);
+
+        let mut data = 1u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&[0; 32]);
+        data.extend_from_slice(&4u32.to_le_bytes());
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        data.extend_from_slice(b"abcd");
+        assert!(chunk_payloads(&data).is_err());
+        assert!(decompress(&data, HeaderAlgorithm::LZ, &[], None, &mut Progress::none()).is_err());
+    }
+}
@@ -0,0 +1,439 @@
+// C ABI for the codecs, for C and C++ programs linking the staticlib or the
+// shared library. include/rs_compressor.h is generated from this file by
+// cbindgen when the crate builds.
+//
+// Every function returns an `RscStatus`. One-shot compression writes into a
+// buffer the caller sized with `rsc_compress_bound`; everything else hands
+// back a buffer the library allocated, which the caller releases with
+// `rsc_free`. Panics are caught at the boundary and reported as
+// `RSC_STATUS_INTERNAL`, never unwound into C.
+
+use std::ffi::{c_char, CStr};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use crate::algos::file_type::{select_filters, Algorithm};
+use crate::codec;
+use crate::framed::{StreamDecoder, StreamEncoder, DEFAULT_BLOCK_SIZE};
+use crate::header;
+
+pub const RSC_ALGORITHM_AUTO: u32 = 0;
+pub const RSC_ALGORITHM_RLE: u32 = 1;
+pub const RSC_ALGORITHM_LZ: u32 = 2;
+pub const RSC_ALGORITHM_PACKBITS: u32 = 3;
+pub const RSC_ALGORITHM_BWT: u32 = 4;
+pub const RSC_ALGORITHM_RC: u32 = 5;
+pub const RSC_ALGORITHM_LZ4: u32 = 6;
+
+/// Result of every call. The values are part of the ABI and never change;
+/// new ones are only ever added.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RscStatus {
+    Ok = 0,
+    /// A null pointer, unknown algorithm or option the codec can't take.
+    InvalidArgument = 1,
+    /// The output buffer is too small; `*output_len` holds the size needed.
+    BufferTooSmall = 2,
+    /// The input is not an RSCMP, LZ4 or JSCMP file.
+    InvalidFormat = 3,
+    /// The file uses a codec, filter or feature this build can't decode.
+    Unsupported = 4,
+    CorruptData = 5,
+    ChecksumMismatch = 6,
+    Truncated = 7,
+    /// The file needs a dictionary, is encrypted or is a patch; those take
+    /// the CLI or the Rust API.
+    NeedsOptions = 8,
+    Internal = 9,
+}
+
+/// Describes `status` in a static, NUL-terminated English string.
+#[no_mangle]
+pub extern "C" fn rsc_status_message(status: RscStatus) -> *const c_char {
+    let message: &'static CStr = match status {
+        RscStatus::Ok => c"OK",
+        RscStatus::InvalidArgument => c"Invalid argument",
+        RscStatus::BufferTooSmall => c"Output buffer too small",
+        RscStatus::InvalidFormat => c"Not a compressed file",
+        RscStatus::Unsupported => c"Unsupported codec or feature",
+        RscStatus::CorruptData => c"Corrupt compressed data",
+        RscStatus::ChecksumMismatch => c"Checksum mismatch",
+        RscStatus::Truncated => c"Truncated compressed data",
+        RscStatus::NeedsOptions => c"File needs a dictionary, passphrase or base file",
+        RscStatus::Internal => c"Internal error",
+    };
+    message.as_ptr()
+}
+
+fn status(message: &str, fallback: RscStatus) -> RscStatus {
+    match crate::error_code(message) {
+        Some("DICTIONARY" | "ENCRYPTION" | "PATCH") => RscStatus::NeedsOptions,
+        Some("INVALID_FORMAT") => RscStatus::InvalidFormat,
+        Some("UNSUPPORTED") => RscStatus::Unsupported,
+        Some("CHECKSUM_MISMATCH") => RscStatus::ChecksumMismatch,
+        Some("TRUNCATED") => RscStatus::Truncated,
+        _ => fallback,
+    }
+}
+
+/// Runs `body`, turning a panic into `Internal`.
+fn guard(body: impl FnOnce() -> RscStatus) -> RscStatus {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(RscStatus::Internal)
+}
+
+/// `None` for Auto, or an error for an unknown ID.
+fn algorithm(id: u32) -> Result<Option<Algorithm>, RscStatus> {
+    Ok(Some(match id {
+        RSC_ALGORITHM_AUTO => return Ok(None),
+        RSC_ALGORITHM_RLE => Algorithm::RLE,
+        RSC_ALGORITHM_LZ => Algorithm::LZ,
+        RSC_ALGORITHM_PACKBITS => Algorithm::PackBits,
+        RSC_ALGORITHM_BWT => Algorithm::BWT,
+        RSC_ALGORITHM_RC => Algorithm::RC,
+        RSC_ALGORITHM_LZ4 => Algorithm::LZ4,
+        _ => return Err(RscStatus::InvalidArgument),
+    }))
+}
+
+/// The input slice, which may be null when empty.
+unsafe fn input<'a>(data: *const u8, len: usize) -> Result<&'a [u8], RscStatus> {
+    match (data.is_null(), len) {
+        (true, 0) => Ok(&[]),
+        (true, _) => Err(RscStatus::InvalidArgument),
+        (false, _) => Ok(slice::from_raw_parts(data, len)),
+    }
+}
+
+/// Hands `data` to the caller, who releases it with `rsc_free`.
+unsafe fn give(data: Vec<u8>, output: *mut *mut u8, output_len: *mut usize) {
+    let data = Box::into_raw(data.into_boxed_slice());
+    *output_len = data.len();
+    *output = data as *mut u8;
+}
+
+/// Largest output `rsc_compress` can produce for `input_len` bytes with any
+/// algorithm.
+#[no_mangle]
+pub extern "C" fn rsc_compress_bound(input_len: usize) -> usize {
+    // LZ and RLE spend two bytes on a byte that doesn't compress, the worst
+    // of any codec. The rest covers the header and per-block tables.
+    input_len.saturating_mul(2).saturating_add(header::PREFIX_SIZE + 1024)
+}
+
+/// Compresses `input` with `algorithm` (an `RSC_ALGORITHM_` value) into
+/// `output`, which holds `output_capacity` bytes, and stores the size written
+/// in `*output_len`. A capacity of `rsc_compress_bound(input_len)` is always
+/// enough.
+///
+/// # Safety
+/// `input` must point to `input_len` readable bytes (or be null if that is
+/// 0), `output` to `output_capacity` writable bytes and `output_len` to a
+/// `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn rsc_compress(
+    algorithm: u32,
+    input: *const u8,
+    input_len: usize,
+    output: *mut u8,
+    output_capacity: usize,
+    output_len: *mut usize,
+) -> RscStatus {
+    guard(|| {
+        if output.is_null() || output_len.is_null() {
+            return RscStatus::InvalidArgument;
+        }
+        let input = match self::input(input, input_len) {
+            Ok(input) => input,
+            Err(status) => return status,
+        };
+        let (algorithm, filters) = match self::algorithm(algorithm) {
+            Ok(Some(algorithm)) => (algorithm, Vec::new()),
+            Ok(None) => (codec::auto_select(input, None), select_filters(input)),
+            Err(status) => return status,
+        };
+        let compressed = match codec::compress_with_filters(input, algorithm, &filters, None, None) {
+            Ok(compressed) => compressed,
+            Err(message) => return status(message, RscStatus::InvalidArgument),
+        };
+        *output_len = compressed.len();
+        if compressed.len() > output_capacity {
+            return RscStatus::BufferTooSmall;
+        }
+        ptr::copy_nonoverlapping(compressed.as_ptr(), output, compressed.len());
+        RscStatus::Ok
+    })
+}
+
+/// Decompresses an RSCMP, LZ4 or JSCMP file. On success `*output` points to
+/// `*output_len` bytes to release with `rsc_free`.
+///
+/// # Safety
+/// `input` must point to `input_len` readable bytes (or be null if that is
+/// 0); `output` and `output_len` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rsc_decompress(
+    input: *const u8,
+    input_len: usize,
+    output: *mut *mut u8,
+    output_len: *mut usize,
+) -> RscStatus {
+    guard(|| {
+        if output.is_null() || output_len.is_null() {
+            return RscStatus::InvalidArgument;
+        }
+        let input = match self::input(input, input_len) {
+            Ok(input) => input,
+            Err(status) => return status,
+        };
+        match codec::decompress(input, None) {
+            Ok(data) => {
+                give(data, output, output_len);
+                RscStatus::Ok
+            }
+            Err(message) => status(message, RscStatus::CorruptData),
+        }
+    })
+}
+
+/// Releases a buffer the library returned. Null is ignored.
+///
+/// # Safety
+/// `data` and `len` must be a pointer and length returned together by this
+/// library, not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn rsc_free(data: *mut u8, len: usize) {
+    if !data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+    }
+}
+
+/// Streaming compression context; writes the seekable framed format.
+pub struct RscEncoder(StreamEncoder);
+
+/// Streaming decompression context.
+pub struct RscDecoder(StreamDecoder);
+
+/// Starts a compression stream. `algorithm` may be `RSC_ALGORITHM_AUTO` but
+/// not LZ4; a `block_size` of 0 uses the default. Returns null on an invalid
+/// argument.
+#[no_mangle]
+pub extern "C" fn rsc_encoder_new(algorithm: u32, block_size: u32) -> *mut RscEncoder {
+    let block_size = if block_size == 0 { DEFAULT_BLOCK_SIZE } else { block_size };
+    match self::algorithm(algorithm).ok().and_then(|algorithm| StreamEncoder::new(algorithm, block_size).ok()) {
+        Some(encoder) => Box::into_raw(Box::new(RscEncoder(encoder))),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Feeds `input` to the stream. `*output` receives the compressed bytes for
+/// every block completed, possibly none; release them with `rsc_free`.
+///
+/// # Safety
+/// `encoder` must come from `rsc_encoder_new` and not be finished or freed;
+/// the buffers are as for `rsc_decompress`.
+#[no_mangle]
+pub unsafe extern "C" fn rsc_encoder_push(
+    encoder: *mut RscEncoder,
+    input: *const u8,
+    input_len: usize,
+    output: *mut *mut u8,
+    output_len: *mut usize,
+) -> RscStatus {
+    guard(|| {
+        if encoder.is_null() || output.is_null() || output_len.is_null() {
+            return RscStatus::InvalidArgument;
+        }
+        let input = match self::input(input, input_len) {
+            Ok(input) => input,
+            Err(status) => return status,
+        };
+        match (*encoder).0.push(input) {
+            Ok(data) => {
+                give(data, output, output_len);
+                RscStatus::Ok
+            }
+            Err(message) => status(message, RscStatus::InvalidArgument),
+        }
+    })
+}
+
+/// Writes the end of the stream to `*output` and frees `encoder`, whatever
+/// the result.
+///
+/// # Safety
+/// As for `rsc_encoder_push`; `encoder` is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rsc_encoder_finish(
+    encoder: *mut RscEncoder,
+    output: *mut *mut u8,
+    output_len: *mut usize,
+) -> RscStatus {
+    guard(|| {
+        if encoder.is_null() {
+            return RscStatus::InvalidArgument;
+        }
+        let encoder = Box::from_raw(encoder);
+        if output.is_null() || output_len.is_null() {
+            return RscStatus::InvalidArgument;
+        }
+        match encoder.0.finish() {
+            Ok(data) => {
+                give(data, output, output_len);
+                RscStatus::Ok
+            }
+            Err(message) => status(message, RscStatus::InvalidArgument),
+        }
+    })
+}
+
+/// Abandons a stream without finishing it. Null is ignored.
+///
+/// # Safety
+/// `encoder` must come from `rsc_encoder_new` and not be finished or freed.
+#[no_mangle]
+pub unsafe extern "C" fn rsc_encoder_free(encoder: *mut RscEncoder) {
+    if !encoder.is_null() {
+        drop(Box::from_raw(encoder));
+    }
+}
+
+/// Starts a decompression stream. Framed files decode block by block as
+/// they arrive; any other file is returned whole by `rsc_decoder_finish`.
+#[no_mangle]
+pub extern "C" fn rsc_decoder_new() -> *mut RscDecoder {
+    Box::into_raw(Box::new(RscDecoder(StreamDecoder::new(None))))
+}
+
+/// Feeds `input` to the stream. `*output` receives the contents of every
+/// block completed, possibly none; release them with `rsc_free`.
+///
+/// # Safety
+/// `decoder` must come from `rsc_decoder_new` and not be finished or freed;
+/// the buffers are as for `rsc_decompress`.
+#[no_mangle]
+pub unsafe extern "C" fn rsc_decoder_push(
+    decoder: *mut RscDecoder,
+    input: *const u8,
+    input_len: usize,
+    output: *mut *mut u8,
+    output_len: *mut usize,
+) -> RscStatus {
+    guard(|| {
+        if decoder.is_null() || output.is_null() || output_len.is_null() {
+            return RscStatus::InvalidArgument;
+        }
+        let input = match self::input(input, input_len) {
+            Ok(input) => input,
+            Err(status) => return status,
+        };
+        match (*decoder).0.push(input) {
+            Ok(data) => {
+                give(data, output, output_len);
+                RscStatus::Ok
+            }
+            Err(message) => status(message, RscStatus::CorruptData),
+        }
+    })
+}
+
+/// Decodes whatever remains and frees `decoder`, whatever the result. A
+/// framed stream that stopped before its index is `RSC_STATUS_TRUNCATED`.
+///
+/// # Safety
+/// As for `rsc_decoder_push`; `decoder` is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rsc_decoder_finish(
+    decoder: *mut RscDecoder,
+    output: *mut *mut u8,
+    output_len: *mut usize,
+) -> RscStatus {
+    guard(|| {
+        if decoder.is_null() {
+            return RscStatus::InvalidArgument;
+        }
+        let decoder = Box::from_raw(decoder);
+        if output.is_null() || output_len.is_null() {
+            return RscStatus::InvalidArgument;
+        }
+        match decoder.0.finish() {
+            Ok(data) => {
+                give(data, output, output_len);
+                RscStatus::Ok
+            }
+            Err(message) => status(message, RscStatus::CorruptData),
+        }
+    })
+}
+
+/// Abandons a stream without finishing it. Null is ignored.
+///
+/// # Safety
+/// `decoder` must come from `rsc_decoder_new` and not be finished or freed.
+#[no_mangle]
+pub unsafe extern "C" fn rsc_decoder_free(decoder: *mut RscDecoder) {
+    if !decoder.is_null() {
+        drop(Box::from_raw(decoder));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bound_holds_for_every_codec() {
+        let mut state = 7u32;
+        let noise: Vec<u8> = (0..300_000)
+            .map(|_| {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect();
+        let alternating: Vec<u8> = (0..70_000).map(|i| if i % 2 == 0 { 0 } else { 255 }).collect();
+        for input in [&noise[..], &alternating, b"x", b""] {
+            let bound = rsc_compress_bound(input.len());
+            let mut output = vec![0; bound];
+            for algorithm in RSC_ALGORITHM_AUTO..=RSC_ALGORITHM_LZ4 {
+                let mut written = 0;
+                let status = unsafe {
+                    rsc_compress(algorithm, input.as_ptr(), input.len(), output.as_mut_ptr(), bound, &mut written)
+                };
+                assert_eq!(status, RscStatus::Ok, "algorithm {} on {} bytes", algorithm, input.len());
+
+                let (mut data, mut len) = (ptr::null_mut(), 0);
+                assert_eq!(unsafe { rsc_decompress(output.as_ptr(), written, &mut data, &mut len) }, RscStatus::Ok);
+                assert_eq!(unsafe { slice::from_raw_parts(data, len) }, input);
+                unsafe { rsc_free(data, len) };
+            }
+        }
+    }
+
+    #[test]
+    fn test_statuses_come_from_error_codes() {
+        let input = b"status from a real failure, status from a real failure".repeat(20);
+        let rc = codec::compress(&input, Algorithm::RC, None).unwrap();
+        let mut bad_algorithm = rc.clone();
+        bad_algorithm[6] = 99;
+        let mut bad_checksum = codec::compress(&input, Algorithm::LZ4, None).unwrap();
+        *bad_checksum.last_mut().unwrap() ^= 0xFF;
+        let with_dictionary = codec::compress(&input, Algorithm::LZ, Some(b"dictionary")).unwrap();
+        let mut corrupt = codec::compress(&input, Algorithm::LZ, None).unwrap();
+        let end = corrupt.len();
+        corrupt[end - 2..].fill(0xFF);
+
+        let cases: [(&[u8], RscStatus); 6] = [
+            (b"definitely not compressed", RscStatus::InvalidFormat),
+            (&bad_algorithm, RscStatus::Unsupported),
+            (&rc[..header::read_header(&rc).unwrap().size() + 4], RscStatus::Truncated),
+            (&bad_checksum, RscStatus::ChecksumMismatch),
+            (&with_dictionary, RscStatus::NeedsOptions),
+            (&corrupt, RscStatus::CorruptData),
+        ];
+        for (file, expected) in cases {
+            let (mut data, mut len) = (ptr::null_mut(), 0);
+            assert_eq!(unsafe { rsc_decompress(file.as_ptr(), file.len(), &mut data, &mut len) }, expected);
+        }
+    }
+}
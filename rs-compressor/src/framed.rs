@@ -0,0 +1,1115 @@
+// Block-framed format. The input is cut into fixed-size blocks that are
+// compressed independently with the codec named in the header, and a
+// trailing index of block offsets lets readers jump straight to the block
+// holding any byte instead of decoding from the start:
+//
+//   RSCMP header with the framed flag and the block size
+//   blocks: "RSBK", u32 payload length, u32 raw length, u64 raw offset,
+//           u32 xxHash32 of raw, payload
+//   index:  "RSIX", u32 block count, per block u64 offset and u32 raw length
+//   u64 index offset, "RSIX"
+//
+// All integers are little-endian and offsets count from the start of the
+// file, header included. The markers and per-block checksums and offsets
+// also let `recover` salvage intact blocks from a damaged file.
+//
+// With parity enabled, every group of up to GROUP_SIZE blocks is preceded by
+// a Reed–Solomon parity record, so up to `parity` damaged blocks per group
+// can be rebuilt:
+//
+//   "RSPR", u16 block count, u16 parity count, u32 row length,
+//   per block u32 encoded length and u32 xxHash32 of the encoded block,
+//   u32 xxHash32 of the record so far,
+//   per parity row: row bytes, u32 xxHash32 of the row
+
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::ops::Range;
+
+use crate::algos::file_type::{select_filters, Algorithm};
+use crate::algos::filters::Filter;
+use crate::checksum::xxh32;
+use crate::codec;
+use crate::error;
+use crate::header::{self, read_header, Algorithm as HeaderAlgorithm, Header, Metadata};
+use crate::jscmp;
+use crate::progress::Progress;
+use crate::reed_solomon;
+
+pub const DEFAULT_BLOCK_SIZE: u32 = 1 << 20;
+/// Blocks covered by one parity record.
+pub const GROUP_SIZE: usize = 16;
+/// Most parity blocks a group can have: a full group and its parity rows
+/// together must fit in `reed_solomon::MAX_SHARDS`.
+pub const MAX_PARITY: u8 = (reed_solomon::MAX_SHARDS - GROUP_SIZE) as u8;
+const BLOCK_MAGIC: &[u8] = b"RSBK";
+const INDEX_MAGIC: &[u8] = b"RSIX";
+const PARITY_MAGIC: &[u8] = b"RSPR";
+const BLOCK_HEADER_SIZE: usize = 24;
+const INDEX_ENTRY_SIZE: usize = 12;
+const TRAILER_SIZE: usize = 12;
+
+#[derive(Debug, Clone, Copy)]
+pub struct FrameOptions {
+    pub block_size: u32,
+    /// Parity blocks per group of `GROUP_SIZE` blocks; 0 writes none.
+    pub parity: u8,
+}
+
+impl Default for FrameOptions {
+    fn default() -> Self {
+        Self { block_size: DEFAULT_BLOCK_SIZE, parity: 0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Block {
+    offset: u64,
+    raw_start: u64,
+    raw_len: u32,
+}
+
+fn u32_at(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap())
+}
+
+fn u64_at(data: &[u8], pos: usize) -> u64 {
+    u64::from_le_bytes(data[pos..pos + 8].try_into().unwrap())
+}
+
+pub fn compress(
+    input: &[u8],
+    algorithm: Algorithm,
+    filters: &[Filter],
+    dictionary: Option<&[u8]>,
+    metadata: Option<&Metadata>,
+    options: &FrameOptions,
+    progress: &mut Progress,
+) -> Result<Vec<u8>, &'static str> {
+    if algorithm == Algorithm::LZ4 {
+        return Err("LZ4 output is already framed; pick another codec for seekable files");
+    }
+    if options.block_size == 0 {
+        return Err("Invalid block size");
+    }
+    if GROUP_SIZE + options.parity as usize > reed_solomon::MAX_SHARDS {
+        return Err("Too many parity blocks per group");
+    }
+
+    let mut header = codec::build_header(algorithm, filters, dictionary, metadata)?;
+    header.block_size = Some(options.block_size);
+    let mut output = header.to_bytes();
+    progress.start(input.len() as u64)?;
+
+    let block_size = options.block_size as usize;
+    let mut index = Vec::new();
+    let mut raw_offset = 0;
+    for group in input.chunks(block_size.saturating_mul(GROUP_SIZE)) {
+        let mut blocks = Vec::new();
+        for chunk in group.chunks(block_size) {
+            blocks.push(encode_block(chunk, raw_offset, algorithm, filters, dictionary)?);
+            raw_offset += chunk.len() as u64;
+            progress.advance(chunk.len() as u64)?;
+        }
+        if options.parity > 0 {
+            write_parity(&mut output, &blocks, options.parity as usize);
+        }
+        for (chunk, block) in group.chunks(block_size).zip(blocks) {
+            index.push((output.len() as u64, chunk.len() as u32));
+            output.extend_from_slice(&block);
+        }
+    }
+
+    let index_offset = output.len() as u64;
+    output.extend_from_slice(INDEX_MAGIC);
+    output.extend_from_slice(&(index.len() as u32).to_le_bytes());
+    for (offset, raw_len) in index {
+        output.extend_from_slice(&offset.to_le_bytes());
+        output.extend_from_slice(&raw_len.to_le_bytes());
+    }
+    output.extend_from_slice(&index_offset.to_le_bytes());
+    output.extend_from_slice(INDEX_MAGIC);
+    progress.finish();
+    Ok(output)
+}
+
+fn encode_block(
+    chunk: &[u8],
+    raw_offset: u64,
+    algorithm: Algorithm,
+    filters: &[Filter],
+    dictionary: Option<&[u8]>,
+) -> Result<Vec<u8>, &'static str> {
+    let payload = codec::compress_payload(chunk, algorithm, filters, dictionary)?;
+    let mut block = Vec::with_capacity(BLOCK_HEADER_SIZE + payload.len());
+    block.extend_from_slice(BLOCK_MAGIC);
+    block.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    block.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+    block.extend_from_slice(&raw_offset.to_le_bytes());
+    block.extend_from_slice(&xxh32(chunk, 0).to_le_bytes());
+    block.extend_from_slice(&payload);
+    Ok(block)
+}
+
+fn write_parity(output: &mut Vec<u8>, blocks: &[Vec<u8>], parity: usize) {
+    let row_len = blocks.iter().map(Vec::len).max().unwrap_or(0);
+    let start = output.len();
+    output.extend_from_slice(PARITY_MAGIC);
+    output.extend_from_slice(&(blocks.len() as u16).to_le_bytes());
+    output.extend_from_slice(&(parity as u16).to_le_bytes());
+    output.extend_from_slice(&(row_len as u32).to_le_bytes());
+    for block in blocks {
+        output.extend_from_slice(&(block.len() as u32).to_le_bytes());
+        output.extend_from_slice(&xxh32(block, 0).to_le_bytes());
+    }
+    let checksum = xxh32(&output[start..], 0);
+    output.extend_from_slice(&checksum.to_le_bytes());
+    for row in reed_solomon::encode(blocks, parity, row_len) {
+        output.extend_from_slice(&row);
+        output.extend_from_slice(&xxh32(&row, 0).to_le_bytes());
+    }
+}
+
+struct ParityRecord<'a> {
+    /// Encoded length and checksum of each block in the group.
+    blocks: Vec<(usize, u32)>,
+    /// Parity rows, `None` where the row checksum failed.
+    rows: Vec<Option<&'a [u8]>>,
+    row_len: usize,
+}
+
+/// Parses the parity record at the start of `data`, returning it and its
+/// length, or `None` if its fixed part is damaged or describes more shards
+/// than Reed–Solomon allows. Rows cut off by the end of `data` count as
+/// damaged, and the length stops at the end of `data`.
+fn read_parity(data: &[u8]) -> Option<(ParityRecord<'_>, usize)> {
+    let count = u16::from_le_bytes(data.get(4..6)?.try_into().unwrap()) as usize;
+    let parity = u16::from_le_bytes(data.get(6..8)?.try_into().unwrap()) as usize;
+    let row_len = u32_at(data.get(..12)?, 8) as usize;
+    if count + parity > reed_solomon::MAX_SHARDS {
+        return None;
+    }
+    let fixed = 12 + count * 8;
+    if u32_at(data.get(..fixed + 4)?, fixed) != xxh32(&data[..fixed], 0) {
+        return None;
+    }
+
+    let blocks = (0..count).map(|i| (u32_at(data, 12 + i * 8) as usize, u32_at(data, 16 + i * 8))).collect();
+    let mut pos = fixed + 4;
+    let mut rows = Vec::with_capacity(parity);
+    let row_size = row_len.saturating_add(4);
+    for _ in 0..parity {
+        let row = data.get(pos..).and_then(|rest| rest.get(..row_size));
+        rows.push(row.filter(|row| u32_at(row, row_len) == xxh32(&row[..row_len], 0)).map(|row| &row[..row_len]));
+        pos = pos.saturating_add(row_size).min(data.len());
+    }
+    Some((ParityRecord { blocks, rows, row_len }, pos))
+}
+
+/// Cuts the group a parity record describes out of `data`, rebuilding any
+/// blocks whose checksum fails. Returns the blocks and the indexes of those
+/// that were rebuilt.
+fn repair_group(data: &[u8], record: &ParityRecord) -> Result<(Vec<Vec<u8>>, Vec<usize>), &'static str> {
+    let mut shards = Vec::with_capacity(record.blocks.len());
+    let mut damaged = Vec::new();
+    let mut offset = 0;
+    for (i, &(len, checksum)) in record.blocks.iter().enumerate() {
+        let shard = data.get(offset..).and_then(|rest| rest.get(..len)).filter(|shard| xxh32(shard, 0) == checksum);
+        if shard.is_none() {
+            damaged.push(i);
+        }
+        shards.push(shard.map(<[u8]>::to_vec));
+        offset = offset.saturating_add(len);
+    }
+
+    if !damaged.is_empty() {
+        let rows: Vec<Option<Vec<u8>>> = record.rows.iter().map(|row| row.map(<[u8]>::to_vec)).collect();
+        reed_solomon::reconstruct(&mut shards, &rows, record.row_len)?;
+    }
+    let blocks = shards
+        .into_iter()
+        .zip(&record.blocks)
+        .map(|(shard, &(len, _))| {
+            let mut block = shard.unwrap();
+            block.truncate(len);
+            block
+        })
+        .collect();
+    Ok((blocks, damaged))
+}
+
+/// Decodes the block at the start of `data`, returning its contents and
+/// encoded length.
+fn decode_block(
+    data: &[u8],
+    algorithm: HeaderAlgorithm,
+    filters: &[Filter],
+    dictionary: Option<&[u8]>,
+) -> Result<(Vec<u8>, usize), &'static str> {
+    if data.len() < BLOCK_HEADER_SIZE || &data[..4] != BLOCK_MAGIC {
+        return Err(error::MISSING_BLOCK_MARKER);
+    }
+    let payload_len = u32_at(data, 4) as usize;
+    let raw_len = u32_at(data, 8) as usize;
+    let checksum = u32_at(data, 20);
+    let payload = data[BLOCK_HEADER_SIZE..].get(..payload_len).ok_or(error::TRUNCATED_BLOCK)?;
+    let raw = codec::decompress_payload(payload, algorithm, filters, dictionary)?;
+    if raw.len() != raw_len || xxh32(&raw, 0) != checksum {
+        return Err(error::BLOCK_CHECKSUM);
+    }
+    Ok((raw, BLOCK_HEADER_SIZE + payload_len))
+}
+
+/// Decodes every block in order. `data` starts after the header.
+pub fn decompress(
+    data: &[u8],
+    algorithm: HeaderAlgorithm,
+    filters: &[Filter],
+    dictionary: Option<&[u8]>,
+) -> Result<Vec<u8>, &'static str> {
+    decompress_with_repairs(data, algorithm, filters, dictionary, &mut Progress::none()).map(|(output, _)| output)
+}
+
+/// Like `decompress`, also returning the numbers of the blocks that had to
+/// be rebuilt from parity.
+pub fn decompress_with_repairs(
+    data: &[u8],
+    algorithm: HeaderAlgorithm,
+    filters: &[Filter],
+    dictionary: Option<&[u8]>,
+    progress: &mut Progress,
+) -> Result<(Vec<u8>, Vec<usize>), &'static str> {
+    let mut output = Vec::new();
+    let mut repaired = Vec::new();
+    let mut block = 0;
+    let mut pos = 0;
+    progress.start(data.len() as u64)?;
+    loop {
+        let rest = data.get(pos..).unwrap_or_default();
+        if rest.starts_with(INDEX_MAGIC) {
+            break;
+        }
+        if !rest.starts_with(PARITY_MAGIC) {
+            let (raw, used) = decode_block(rest, algorithm, filters, dictionary)?;
+            output.extend_from_slice(&raw);
+            pos += used;
+            block += 1;
+            progress.advance(used as u64)?;
+            continue;
+        }
+
+        let Some((record, used)) = read_parity(rest) else {
+            // Without the record the group can't be repaired, but its blocks
+            // may still be intact: carry on from the next block marker.
+            let skip = rest[4..].windows(4).position(|window| window == BLOCK_MAGIC).ok_or("Damaged parity record")?;
+            pos += 4 + skip;
+            continue;
+        };
+        let (blocks, damaged) = repair_group(rest.get(used..).unwrap_or_default(), &record)?;
+        repaired.extend(damaged.iter().map(|i| block + i));
+        progress.advance(used as u64)?;
+        for (encoded, &(len, _)) in blocks.iter().zip(&record.blocks) {
+            let (raw, _) = decode_block(encoded, algorithm, filters, dictionary)?;
+            output.extend_from_slice(&raw);
+            progress.advance(len as u64)?;
+        }
+        block += record.blocks.len();
+        pos += used + record.blocks.iter().map(|&(len, _)| len).sum::<usize>();
+    }
+    progress.finish();
+    Ok((output, repaired))
+}
+
+fn read_trailer(trailer: &[u8]) -> Result<u64, &'static str> {
+    if trailer.len() != TRAILER_SIZE || &trailer[8..] != INDEX_MAGIC {
+        return Err(error::SEEK_INDEX_MISSING);
+    }
+    Ok(u64_at(trailer, 0))
+}
+
+/// Payloads of the blocks in `data` (which starts after the header), in
+/// order, without decoding them. Stops at the index or at anything that isn't
+/// a block or parity record.
+pub fn block_payloads(data: &[u8]) -> Vec<&[u8]> {
+    let mut payloads = Vec::new();
+    let mut pos = 0;
+    loop {
+        let rest = data.get(pos..).unwrap_or_default();
+        if rest.starts_with(PARITY_MAGIC) {
+            let Some((_, used)) = read_parity(rest) else { break };
+            pos += used;
+            continue;
+        }
+        if !rest.starts_with(BLOCK_MAGIC) || rest.len() < BLOCK_HEADER_SIZE {
+            break;
+        }
+        let len = u32_at(rest, 4) as usize;
+        let Some(payload) = rest[BLOCK_HEADER_SIZE..].get(..len) else { break };
+        payloads.push(payload);
+        pos += BLOCK_HEADER_SIZE + len;
+    }
+    payloads
+}
+
+/// Offset in `data` (which starts after the header) of the first block that
+/// doesn't decode, or of a parity record that can't be read, for error
+/// reports. `None` if every block decodes.
+pub fn first_damaged(
+    data: &[u8],
+    algorithm: HeaderAlgorithm,
+    filters: &[Filter],
+    dictionary: Option<&[u8]>,
+) -> Option<usize> {
+    let mut pos = 0;
+    loop {
+        let rest = data.get(pos..).unwrap_or_default();
+        if rest.starts_with(INDEX_MAGIC) {
+            return None;
+        }
+        if rest.starts_with(PARITY_MAGIC) {
+            let Some((_, used)) = read_parity(rest) else { return Some(pos) };
+            pos += used;
+            continue;
+        }
+        match decode_block(rest, algorithm, filters, dictionary) {
+            Ok((_, used)) => pos += used,
+            Err(_) => return Some(pos),
+        }
+    }
+}
+
+/// Parses the index that starts at `index_offset`; `index` runs up to the
+/// trailer.
+fn parse_index(index: &[u8], index_offset: u64) -> Result<Vec<Block>, &'static str> {
+    if index.len() < 8 || &index[..4] != INDEX_MAGIC {
+        return Err(error::SEEK_INDEX_MISSING);
+    }
+    let count = u32_at(index, 4) as usize;
+    if count.checked_mul(INDEX_ENTRY_SIZE).and_then(|size| size.checked_add(8)) != Some(index.len()) {
+        return Err("Seek index is corrupted");
+    }
+
+    let mut blocks = Vec::with_capacity(count);
+    let mut raw_start = 0;
+    for entry in index[8..].chunks_exact(INDEX_ENTRY_SIZE) {
+        let offset = u64_at(entry, 0);
+        let raw_len = u32_at(entry, 8);
+        if offset.checked_add(BLOCK_HEADER_SIZE as u64).is_none_or(|end| end > index_offset) {
+            return Err("Seek index is corrupted");
+        }
+        blocks.push(Block { offset, raw_start, raw_len });
+        raw_start += raw_len as u64;
+    }
+    Ok(blocks)
+}
+
+/// Reads the seek index of a whole framed file held in memory.
+fn read_index(input: &[u8]) -> Result<Vec<Block>, &'static str> {
+    let trailer_start = input.len().checked_sub(TRAILER_SIZE).ok_or(error::SEEK_INDEX_MISSING)?;
+    let index_offset = read_trailer(&input[trailer_start..])?;
+    let index = input
+        .get(index_offset as usize..trailer_start)
+        .ok_or("Seek index is corrupted")?;
+    parse_index(index, index_offset)
+}
+
+/// What `recover` salvaged from a damaged framed file.
+#[derive(Debug)]
+pub struct Recovery {
+    /// Contents of every intact or repaired block, in order, with the lost
+    /// ranges left out.
+    pub data: Vec<u8>,
+    /// Byte ranges of the original file that could not be recovered.
+    pub lost: Vec<Range<u64>>,
+    /// Original size, if the seek index survived to tell it. Without it,
+    /// data after the last recovered block may be missing unnoticed.
+    pub total: Option<u64>,
+    /// Blocks rebuilt from parity.
+    pub repaired: usize,
+}
+
+fn find_marker(data: &[u8], from: usize) -> Option<usize> {
+    data.get(from..)?
+        .windows(4)
+        .position(|window| window == BLOCK_MAGIC || window == PARITY_MAGIC)
+        .map(|pos| from + pos)
+}
+
+/// Salvages every block that still decodes and passes its checksum from a
+/// damaged framed file, resynchronising at the next marker after damage.
+/// `input` is the whole file, header included.
+pub fn recover(input: &[u8], header: &Header, dictionary: Option<&[u8]>) -> Result<Recovery, &'static str> {
+    let block_size = header.block_size.ok_or("File is not block framed, so there is nothing to resynchronise at")? as u64;
+    let total = read_index(input).ok().map(|blocks| blocks.last().map_or(0, |b| b.raw_start + b.raw_len as u64));
+
+    let mut found: Vec<(u64, Vec<u8>)> = Vec::new();
+    let mut repaired = 0;
+    let mut pos = header.size();
+    while let Some(next) = find_marker(input, pos) {
+        let rest = &input[next..];
+        pos = next + 4;
+
+        if rest.starts_with(PARITY_MAGIC) {
+            let Some((record, used)) = read_parity(rest) else {
+                continue;
+            };
+            // If the group can't be repaired as a whole, carry on past the
+            // parity rows and take its intact blocks one by one.
+            pos = next + used;
+            let Ok((blocks, damaged)) = repair_group(rest.get(used..).unwrap_or_default(), &record) else {
+                continue;
+            };
+            let decoded: Result<Vec<_>, _> = blocks
+                .iter()
+                .map(|block| decode_block(block, header.algorithm, &header.filters, dictionary).map(|(raw, _)| (u64_at(block, 12), raw)))
+                .collect();
+            if let Ok(decoded) = decoded {
+                repaired += damaged.len();
+                found.extend(decoded);
+                pos += record.blocks.iter().map(|&(len, _)| len).sum::<usize>();
+            }
+            continue;
+        }
+
+        if let Ok((raw, used)) = decode_block(rest, header.algorithm, &header.filters, dictionary) {
+            let raw_offset = u64_at(rest, 12);
+            if raw_offset.is_multiple_of(block_size) {
+                found.push((raw_offset, raw));
+                pos = next + used;
+            }
+        }
+    }
+
+    found.sort_by_key(|&(offset, _)| offset);
+    let mut data = Vec::new();
+    let mut lost = Vec::new();
+    let mut cursor = 0;
+    for (offset, raw) in found {
+        if offset < cursor {
+            continue;
+        }
+        if offset > cursor {
+            lost.push(cursor..offset);
+        }
+        cursor = offset + raw.len() as u64;
+        data.extend_from_slice(&raw);
+    }
+    if let Some(total) = total.filter(|&total| total > cursor) {
+        lost.push(cursor..total);
+    }
+    Ok(Recovery { data, lost, total, repaired })
+}
+
+/// Decodes `length` bytes starting at `start`, touching only the blocks the
+/// range overlaps. `input` is the whole file, header included.
+pub fn decompress_range(
+    input: &[u8],
+    header: &Header,
+    dictionary: Option<&[u8]>,
+    start: u64,
+    length: usize,
+) -> Result<Vec<u8>, &'static str> {
+    let blocks = read_index(input)?;
+    let end = start.saturating_add(length as u64);
+    let mut output = Vec::new();
+    for block in blocks.iter().filter(|b| b.raw_start < end && b.raw_start + b.raw_len as u64 > start) {
+        let (raw, _) = decode_block(&input[block.offset as usize..], header.algorithm, &header.filters, dictionary)?;
+        if raw.len() != block.raw_len as usize {
+            return Err(error::BLOCK_NOT_IN_INDEX);
+        }
+        let from = start.saturating_sub(block.raw_start) as usize;
+        let to = ((end - block.raw_start) as usize).min(raw.len());
+        output.extend_from_slice(&raw[from..to]);
+    }
+    Ok(output)
+}
+
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Random access to the decompressed contents of a framed file. Only the
+/// block under the current position is decoded, and the last one is cached.
+pub struct SeekableReader<R> {
+    inner: R,
+    algorithm: HeaderAlgorithm,
+    filters: Vec<Filter>,
+    dictionary: Option<Vec<u8>>,
+    blocks: Vec<Block>,
+    len: u64,
+    position: u64,
+    cached: Option<(usize, Vec<u8>)>,
+}
+
+impl<R: Read + Seek> SeekableReader<R> {
+    pub fn new(inner: R) -> io::Result<Self> {
+        Self::with_dictionary(inner, None)
+    }
+
+    pub fn with_dictionary(mut inner: R, dictionary: Option<Vec<u8>>) -> io::Result<Self> {
+        let mut start = Vec::new();
+        inner.seek(SeekFrom::Start(0))?;
+        (&mut inner).take(header::PREFIX_SIZE as u64).read_to_end(&mut start)?;
+        let size = header::header_size(&start).map_err(invalid)?;
+        if size > start.len() {
+            (&mut inner).take((size - start.len()) as u64).read_to_end(&mut start)?;
+        }
+        let header = read_header(&start).map_err(invalid)?;
+        if header.block_size.is_none() {
+            return Err(invalid(error::NOT_FRAMED));
+        }
+        codec::check_encryption(&header).map_err(invalid)?;
+        codec::check_dictionary(&header, dictionary.as_deref()).map_err(invalid)?;
+
+        let end = inner.seek(SeekFrom::End(0))?;
+        if end < (header.size() + TRAILER_SIZE) as u64 {
+            return Err(invalid(error::SEEK_INDEX_MISSING));
+        }
+        let mut trailer = [0; TRAILER_SIZE];
+        inner.seek(SeekFrom::Start(end - TRAILER_SIZE as u64))?;
+        inner.read_exact(&mut trailer)?;
+        let index_offset = read_trailer(&trailer).map_err(invalid)?;
+        if index_offset > end - TRAILER_SIZE as u64 {
+            return Err(invalid("Seek index is corrupted"));
+        }
+        let mut index = vec![0; (end - TRAILER_SIZE as u64 - index_offset) as usize];
+        inner.seek(SeekFrom::Start(index_offset))?;
+        inner.read_exact(&mut index)?;
+        let blocks = parse_index(&index, index_offset).map_err(invalid)?;
+
+        let len = blocks.last().map_or(0, |b| b.raw_start + b.raw_len as u64);
+        Ok(Self {
+            inner,
+            algorithm: header.algorithm,
+            filters: header.filters,
+            dictionary,
+            blocks,
+            len,
+            position: 0,
+            cached: None,
+        })
+    }
+
+    /// Total decompressed size.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn load(&mut self, block: usize) -> io::Result<&[u8]> {
+        if self.cached.as_ref().is_none_or(|(cached, _)| *cached != block) {
+            let Block { offset, raw_len, .. } = self.blocks[block];
+            let mut encoded = vec![0; BLOCK_HEADER_SIZE];
+            self.inner.seek(SeekFrom::Start(offset))?;
+            self.inner.read_exact(&mut encoded)?;
+            let payload_len = u32_at(&encoded, 4) as u64;
+            (&mut self.inner).take(payload_len).read_to_end(&mut encoded)?;
+            let (raw, _) = decode_block(&encoded, self.algorithm, &self.filters, self.dictionary.as_deref()).map_err(invalid)?;
+            if raw.len() != raw_len as usize {
+                return Err(invalid(error::BLOCK_NOT_IN_INDEX));
+            }
+            self.cached = Some((block, raw));
+        }
+        Ok(&self.cached.as_ref().unwrap().1)
+    }
+}
+
+impl<R: Read + Seek> Read for SeekableReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.position >= self.len {
+            return Ok(0);
+        }
+        let position = self.position;
+        let block = self.blocks.partition_point(|b| b.raw_start + b.raw_len as u64 <= position);
+        let offset = (position - self.blocks[block].raw_start) as usize;
+        let data = self.load(block)?;
+        let count = buf.len().min(data.len() - offset);
+        buf[..count].copy_from_slice(&data[offset..offset + count]);
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl<R: Read + Seek> Seek for SeekableReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        };
+        self.position = target.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Seek before the start of the data"))?;
+        Ok(self.position)
+    }
+}
+
+impl<'a> SeekableReader<Cursor<&'a [u8]>> {
+    pub fn from_bytes(data: &'a [u8]) -> io::Result<Self> {
+        Self::new(Cursor::new(data))
+    }
+}
+
+/// Writes the framed format a piece at a time, so the input never has to be
+/// in memory whole. The output is what `compress` produces without parity.
+pub struct StreamEncoder {
+    /// `None` until the first block picks a codec for `Auto`.
+    algorithm: Option<Algorithm>,
+    filters: Vec<Filter>,
+    block_size: usize,
+    pending: Vec<u8>,
+    written: u64,
+    raw_offset: u64,
+    index: Vec<(u64, u32)>,
+}
+
+impl StreamEncoder {
+    /// `None` picks the codec and filters from the first block, the way
+    /// `Auto` does for a whole file.
+    pub fn new(algorithm: Option<Algorithm>, block_size: u32) -> Result<Self, &'static str> {
+        if algorithm == Some(Algorithm::LZ4) {
+            return Err("LZ4 output is already framed; pick another codec for streaming");
+        }
+        if block_size == 0 {
+            return Err("Invalid block size");
+        }
+        Ok(Self {
+            algorithm,
+            filters: Vec::new(),
+            block_size: block_size as usize,
+            pending: Vec::new(),
+            written: 0,
+            raw_offset: 0,
+            index: Vec::new(),
+        })
+    }
+
+    /// Buffers `input` and returns the output for every block it completed,
+    /// possibly nothing.
+    pub fn push(&mut self, input: &[u8]) -> Result<Vec<u8>, &'static str> {
+        self.pending.extend_from_slice(input);
+        let pending = std::mem::take(&mut self.pending);
+        let mut output = Vec::new();
+        let mut blocks = pending.chunks_exact(self.block_size);
+        for block in &mut blocks {
+            self.write_block(block, &mut output)?;
+        }
+        self.pending = blocks.remainder().to_vec();
+        Ok(output)
+    }
+
+    /// Writes the last partial block and the seek index.
+    pub fn finish(mut self) -> Result<Vec<u8>, &'static str> {
+        let pending = std::mem::take(&mut self.pending);
+        let mut output = Vec::new();
+        if !pending.is_empty() || self.written == 0 {
+            self.write_block(&pending, &mut output)?;
+        }
+
+        let index_offset = self.written;
+        output.extend_from_slice(INDEX_MAGIC);
+        output.extend_from_slice(&(self.index.len() as u32).to_le_bytes());
+        for (offset, raw_len) in &self.index {
+            output.extend_from_slice(&offset.to_le_bytes());
+            output.extend_from_slice(&raw_len.to_le_bytes());
+        }
+        output.extend_from_slice(&index_offset.to_le_bytes());
+        output.extend_from_slice(INDEX_MAGIC);
+        Ok(output)
+    }
+
+    /// Appends `chunk` as a block, preceded by the header for the first one.
+    /// An empty chunk writes only the header.
+    fn write_block(&mut self, chunk: &[u8], output: &mut Vec<u8>) -> Result<(), &'static str> {
+        let start = output.len();
+        let algorithm = match self.algorithm {
+            Some(algorithm) if self.written > 0 => algorithm,
+            _ => {
+                let algorithm = self.algorithm.unwrap_or_else(|| {
+                    self.filters = select_filters(chunk);
+                    codec::auto_select(chunk, None)
+                });
+                self.algorithm = Some(algorithm);
+                let mut header = codec::build_header(algorithm, &self.filters, None, None)?;
+                header.block_size = Some(self.block_size as u32);
+                output.extend_from_slice(&header.to_bytes());
+                algorithm
+            }
+        };
+        if !chunk.is_empty() {
+            let block = encode_block(chunk, self.raw_offset, algorithm, &self.filters, None)?;
+            self.index.push((self.written + (output.len() - start) as u64, chunk.len() as u32));
+            output.extend_from_slice(&block);
+            self.raw_offset += chunk.len() as u64;
+        }
+        self.written += (output.len() - start) as u64;
+        Ok(())
+    }
+}
+
+/// Decodes a compressed file fed to it a piece at a time. Framed files come
+/// out block by block as each one arrives; anything else, JSCMP files
+/// included, can only be decoded whole, so it is buffered until `finish`.
+/// Parity records are skipped: a
+/// damaged block is an error here, as there's no going back to repair it.
+pub struct StreamDecoder {
+    dictionary: Option<Vec<u8>>,
+    buffer: Vec<u8>,
+    header: Option<Header>,
+    /// Set for a JSCMP file, which has no RSCMP header.
+    jscmp: bool,
+    /// Set once the seek index is reached; what follows is ignored.
+    done: bool,
+}
+
+impl StreamDecoder {
+    pub fn new(dictionary: Option<Vec<u8>>) -> Self {
+        Self { dictionary, buffer: Vec::new(), header: None, jscmp: false, done: false }
+    }
+
+    /// Buffers `data` and returns the contents of every block it completed.
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<u8>, &'static str> {
+        if self.done {
+            return Ok(Vec::new());
+        }
+        self.buffer.extend_from_slice(data);
+        if self.header.is_none() && !self.jscmp && !self.read_header(false)? {
+            return Ok(Vec::new());
+        }
+        self.decode_blocks()
+    }
+
+    pub fn finish(mut self) -> Result<Vec<u8>, &'static str> {
+        if self.header.is_none() && !self.jscmp {
+            self.read_header(true)?;
+        }
+        if self.jscmp || self.header.as_ref().is_some_and(|header| header.block_size.is_none()) {
+            return codec::decompress(&self.buffer, self.dictionary.as_deref());
+        }
+        let output = self.decode_blocks()?;
+        if !self.done {
+            return Err(error::TRUNCATED_FRAMED);
+        }
+        Ok(output)
+    }
+
+    /// Parses the header once enough of it has arrived. Framed headers are
+    /// consumed; other files keep theirs for `codec::decompress`.
+    fn read_header(&mut self, at_end: bool) -> Result<bool, &'static str> {
+        if self.buffer.len() < header::PREFIX_SIZE && !at_end {
+            return Ok(false);
+        }
+        if jscmp::is_jscmp(&self.buffer) {
+            self.jscmp = true;
+            return Ok(true);
+        }
+        let size = header::header_size(&self.buffer)?;
+        if self.buffer.len() < size {
+            return if at_end { Err(error::TRUNCATED_HEADER) } else { Ok(false) };
+        }
+        let header = read_header(&self.buffer[..size])?;
+        codec::check_encryption(&header)?;
+        codec::check_dictionary(&header, self.dictionary.as_deref())?;
+        if header.reference_checksum.is_some() {
+            return Err(error::IS_PATCH);
+        }
+        if header.block_size.is_some() {
+            self.buffer.drain(..size);
+        }
+        self.header = Some(header);
+        Ok(true)
+    }
+
+    fn decode_blocks(&mut self) -> Result<Vec<u8>, &'static str> {
+        let Some(header) = self.header.as_ref().filter(|header| header.block_size.is_some()) else {
+            return Ok(Vec::new());
+        };
+        let mut output = Vec::new();
+        let mut pos = 0;
+        while let Some(rest) = self.buffer.get(pos..).filter(|rest| rest.len() >= 4) {
+            if rest.starts_with(INDEX_MAGIC) {
+                self.done = true;
+                break;
+            }
+            // Lengths come from the stream, so a corrupt one mustn't wrap a
+            // 32-bit usize.
+            let needed = if rest.starts_with(PARITY_MAGIC) {
+                let Some(fixed) = rest.get(..12) else { break };
+                let count = u16::from_le_bytes([fixed[4], fixed[5]]) as usize;
+                let parity = u16::from_le_bytes([fixed[6], fixed[7]]) as usize;
+                (u32_at(fixed, 8) as usize)
+                    .checked_add(4)
+                    .and_then(|row_size| row_size.checked_mul(parity))
+                    .and_then(|rows| rows.checked_add(12 + count * 8 + 4))
+            } else {
+                let Some(fixed) = rest.get(..BLOCK_HEADER_SIZE) else { break };
+                BLOCK_HEADER_SIZE.checked_add(u32_at(fixed, 4) as usize)
+            }
+            .ok_or("Block length out of range")?;
+            if rest.len() < needed {
+                break;
+            }
+            if !rest.starts_with(PARITY_MAGIC) {
+                let (raw, _) = decode_block(rest, header.algorithm, &header.filters, self.dictionary.as_deref())?;
+                output.extend_from_slice(&raw);
+            }
+            pos += needed;
+        }
+        match self.done {
+            true => self.buffer.clear(),
+            false => drop(self.buffer.drain(..pos)),
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::{CancellationToken, CANCELLED};
+
+    fn options(block_size: u32, parity: u8) -> FrameOptions {
+        FrameOptions { block_size, parity }
+    }
+
+    fn sample() -> Vec<u8> {
+        (0..5_000u32).flat_map(|i| format!("{} GET /index.html 200\n", i).into_bytes()).collect()
+    }
+
+    #[test]
+    fn test_framed_roundtrip() {
+        let input = sample();
+        for algorithm in [Algorithm::LZ, Algorithm::BWT, Algorithm::PackBits] {
+            let framed = compress(&input, algorithm, &[], None, None, &options(16 * 1024, 0), &mut Progress::none()).unwrap();
+            assert_eq!(codec::decompress(&framed, None).unwrap(), input);
+        }
+        let empty = compress(b"", Algorithm::LZ, &[], None, None, &options(1024, 2), &mut Progress::none()).unwrap();
+        assert!(codec::decompress(&empty, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_seekable_reader() {
+        let input = sample();
+        let framed = compress(&input, Algorithm::RC, &[], None, None, &options(10_000, 0), &mut Progress::none()).unwrap();
+        let mut reader = SeekableReader::from_bytes(&framed).unwrap();
+        assert_eq!(reader.len(), input.len() as u64);
+
+        // A range that straddles a block boundary.
+        let mut buf = vec![0; 5000];
+        reader.seek(SeekFrom::Start(37_500)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, input[37_500..42_500]);
+
+        reader.seek(SeekFrom::End(-10)).unwrap();
+        let mut tail = Vec::new();
+        reader.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, input[input.len() - 10..]);
+
+        assert!(reader.seek(SeekFrom::Current(-(input.len() as i64) - 1)).is_err());
+        let header = read_header(&framed).unwrap();
+        assert_eq!(decompress_range(&framed, &header, None, 37_500, 5000).unwrap(), input[37_500..42_500]);
+    }
+
+    #[test]
+    fn test_detects_corruption() {
+        let input = sample();
+        let mut framed = compress(&input, Algorithm::LZ, &[], None, None, &options(64 * 1024, 0), &mut Progress::none()).unwrap();
+        assert!(compress(&input, Algorithm::LZ4, &[], None, None, &options(1024, 0), &mut Progress::none()).is_err());
+
+        let header = read_header(&framed).unwrap();
+        let body = header.size();
+        assert_eq!(first_damaged(&framed[body..], header.algorithm, &[], None), None);
+        let second = SeekableReader::from_bytes(&framed).unwrap().blocks[1].offset as usize;
+        framed[second + 30] ^= 1;
+        assert_eq!(first_damaged(&framed[body..], header.algorithm, &[], None), Some(second - body));
+        assert_eq!(codec::error_offset(&framed, None), second);
+
+        let last = framed.len() - 1;
+        framed[last] ^= 1;
+        assert!(SeekableReader::from_bytes(&framed).is_err());
+
+        // An index entry pointing near u64::MAX is an error, not an overflow.
+        let mut crafted = compress(&input, Algorithm::LZ, &[], None, None, &options(64 * 1024, 0), &mut Progress::none()).unwrap();
+        let index_offset = u64_at(&crafted, crafted.len() - TRAILER_SIZE) as usize;
+        crafted[index_offset + 8..index_offset + 16].copy_from_slice(&(u64::MAX - 7).to_le_bytes());
+        assert!(SeekableReader::from_bytes(&crafted).is_err());
+        assert!(decompress_range(&crafted, &header, None, 0, 10).is_err());
+    }
+
+    #[test]
+    fn test_parity_repairs_damaged_blocks() {
+        let input = sample();
+        let framed = compress(&input, Algorithm::LZ, &[], None, None, &options(4096, 2), &mut Progress::none()).unwrap();
+        let header = read_header(&framed).unwrap();
+        let body = header.size();
+
+        // Two damaged blocks in the first group are rebuilt; the block
+        // numbers come back so callers can report them.
+        let mut damaged = framed.clone();
+        let block = SeekableReader::from_bytes(&framed).unwrap().blocks;
+        damaged[block[1].offset as usize + 20] ^= 0xFF;
+        damaged[block[3].offset as usize..block[3].offset as usize + 8].fill(0);
+        let (output, repaired) = decompress_with_repairs(&damaged[body..], header.algorithm, &[], None, &mut Progress::none()).unwrap();
+        assert_eq!(output, input);
+        assert_eq!(repaired, [1, 3]);
+
+        // A third one in the same group is more than two parity rows can fix.
+        damaged[block[5].offset as usize + 30] ^= 0xFF;
+        assert!(decompress_with_repairs(&damaged[body..], header.algorithm, &[], None, &mut Progress::none()).is_err());
+    }
+
+    #[test]
+    fn test_oversized_parity_is_an_error() {
+        let input = &sample()[..20_000];
+        let too_many = options(4096, MAX_PARITY + 1);
+        assert!(compress(input, Algorithm::PackBits, &[], None, None, &too_many, &mut Progress::none()).is_err());
+        let most = compress(input, Algorithm::PackBits, &[], None, None, &options(4096, MAX_PARITY), &mut Progress::none()).unwrap();
+        assert_eq!(codec::decompress(&most, None).unwrap(), input);
+
+        // A record claiming 300 parity rows for its two blocks, checksummed
+        // so it looks intact, is rejected rather than fed to the decoder.
+        let mut crafted = compress(input, Algorithm::LZ, &[], None, None, &options(16 * 1024, 2), &mut Progress::none()).unwrap();
+        let header = read_header(&crafted).unwrap();
+        let body = header.size();
+        crafted[body + 6..body + 8].copy_from_slice(&300u16.to_le_bytes());
+        let fixed = body + 12 + 2 * 8;
+        let checksum = xxh32(&crafted[body..fixed], 0);
+        crafted[fixed..fixed + 4].copy_from_slice(&checksum.to_le_bytes());
+        assert!(read_parity(&crafted[body..]).is_none());
+        let first = SeekableReader::from_bytes(&crafted).unwrap().blocks[0].offset as usize;
+        crafted[first + 30] ^= 0xFF;
+        assert!(decompress_with_repairs(&crafted[body..], header.algorithm, &[], None, &mut Progress::none()).is_err());
+        recover(&crafted, &header, None).unwrap();
+    }
+
+    #[test]
+    fn test_truncated_parity_group_is_an_error() {
+        let input = sample();
+        let framed = compress(&input, Algorithm::LZ, &[], None, None, &options(4096, 2), &mut Progress::none()).unwrap();
+        let header = read_header(&framed).unwrap();
+        let body = header.size();
+        let (record, used) = read_parity(&framed[body..]).unwrap();
+        assert_eq!(record.rows.len(), 2);
+
+        // Cut inside the fixed part, each row, and just after the rows.
+        let fixed = used - 2 * (record.row_len + 4);
+        for cut in [fixed - 3, fixed + 10, fixed + record.row_len + 10, used - 1, used, used + 20] {
+            let truncated = &framed[..body + cut];
+            if let Some((_, used)) = read_parity(&truncated[body..]) {
+                assert!(used <= cut);
+            }
+            assert!(decompress_with_repairs(&truncated[body..], header.algorithm, &[], None, &mut Progress::none()).is_err(), "{}", cut);
+            assert!(first_damaged(&truncated[body..], header.algorithm, &[], None).is_some(), "{}", cut);
+            assert!(recover(truncated, &header, None).unwrap().data.is_empty(), "{}", cut);
+        }
+    }
+
+    #[test]
+    fn test_recover_reports_lost_ranges() {
+        let input = sample();
+        let framed = compress(&input, Algorithm::LZ, &[], None, None, &options(4096, 0), &mut Progress::none()).unwrap();
+        let header = read_header(&framed).unwrap();
+        let blocks = SeekableReader::from_bytes(&framed).unwrap().blocks;
+
+        // Wipe the middle of block 2, including the next block's marker, and
+        // cut the file off partway through the last block.
+        let mut damaged = framed.clone();
+        let start = blocks[2].offset as usize + 100;
+        damaged[start..blocks[3].offset as usize + 2].fill(0x55);
+        damaged.truncate(blocks[blocks.len() - 1].offset as usize + 50);
+
+        // With the index gone the truncated tail can't be noticed, only the
+        // gap between intact blocks.
+        let recovery = recover(&damaged, &header, None).unwrap();
+        let last = blocks[blocks.len() - 1].raw_start;
+        assert_eq!(recovery.lost, vec![8192..16384]);
+        assert_eq!(recovery.total, None);
+        assert_eq!(recovery.data[..8192], input[..8192]);
+        assert_eq!(recovery.data[8192..], input[16384..last as usize]);
+    }
+
+    #[test]
+    fn test_recover_with_intact_index_reports_everything_lost() {
+        let input = sample();
+        let framed = compress(&input, Algorithm::RC, &[], None, None, &options(4096, 0), &mut Progress::none()).unwrap();
+        let header = read_header(&framed).unwrap();
+        let blocks = SeekableReader::from_bytes(&framed).unwrap().blocks;
+
+        let mut damaged = framed.clone();
+        let last = blocks.len() - 1;
+        damaged[blocks[last].offset as usize + 30] ^= 0x01;
+        let recovery = recover(&damaged, &header, None).unwrap();
+        assert_eq!(recovery.total, Some(input.len() as u64));
+        assert_eq!(recovery.lost, vec![blocks[last].raw_start..input.len() as u64]);
+        assert_eq!(recovery.data, input[..blocks[last].raw_start as usize]);
+    }
+
+    #[test]
+    fn test_streaming_matches_whole_file() {
+        let input = sample();
+        let whole = compress(&input, Algorithm::LZ, &[], None, None, &options(10_000, 0), &mut Progress::none()).unwrap();
+
+        let mut encoder = StreamEncoder::new(Some(Algorithm::LZ), 10_000).unwrap();
+        let mut streamed = Vec::new();
+        for piece in input.chunks(3_333) {
+            streamed.extend(encoder.push(piece).unwrap());
+        }
+        streamed.extend(encoder.finish().unwrap());
+        assert_eq!(streamed, whole);
+
+        // Blocks come out as soon as they are complete, parity or not.
+        let with_parity = compress(&input, Algorithm::LZ, &[], None, None, &options(10_000, 1), &mut Progress::none()).unwrap();
+        let mut decoder = StreamDecoder::new(None);
+        let mut output = Vec::new();
+        for piece in with_parity.chunks(4_000) {
+            output.extend(decoder.push(piece).unwrap());
+        }
+        assert!(output.len() >= input.len() - 20_000);
+        output.extend(decoder.finish().unwrap());
+        assert_eq!(output, input);
+
+        let plain = codec::compress(&input, Algorithm::RC, None).unwrap();
+        let mut decoder = StreamDecoder::new(None);
+        assert!(decoder.push(&plain).unwrap().is_empty());
+        assert_eq!(decoder.finish().unwrap(), input);
+
+        let jscmp_file = jscmp::compress(&input, Algorithm::LZ).unwrap();
+        let mut decoder = StreamDecoder::new(None);
+        for piece in jscmp_file.chunks(3) {
+            assert!(decoder.push(piece).unwrap().is_empty());
+        }
+        assert_eq!(decoder.finish().unwrap(), input);
+        let mut decoder = StreamDecoder::new(None);
+        decoder.push(&jscmp_file[..4]).unwrap();
+        assert!(decoder.finish().is_err());
+
+        let mut decoder = StreamDecoder::new(None);
+        decoder.push(&whole[..whole.len() / 2]).unwrap();
+        assert!(decoder.finish().is_err());
+    }
+
+    #[test]
+    fn test_reports_progress_per_block() {
+        let input = sample();
+        let mut reports = Vec::new();
+        let mut progress = Progress::new(|done, total| reports.push((done, total)));
+        let framed = compress(&input, Algorithm::LZ, &[], None, None, &options(10_000, 1), &mut progress).unwrap();
+        drop(progress);
+        assert_eq!(reports.len(), input.len().div_ceil(10_000) + 1);
+        assert!(reports.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(reports.last(), Some(&(input.len() as u64, input.len() as u64)));
+
+        let token = CancellationToken::new();
+        let mut calls = 0;
+        let mut progress = Progress::new(|_, _| {
+            calls += 1;
+            if calls == 3 {
+                token.cancel();
+            }
+        })
+        .with_cancellation(token.clone());
+        let header = read_header(&framed).unwrap();
+        let result = decompress_with_repairs(&framed[header.size()..], header.algorithm, &[], None, &mut progress);
+        assert_eq!(result, Err(CANCELLED));
+    }
+}
@@ -0,0 +1,146 @@
+// Progress reporting and cancellation for long compress and decompress runs.
+// Framed files report each block and deduplicated ones each chunk. A plain
+// file is coded in one piece, so the codec itself reports its position
+// every `report::INTERVAL` bytes through `Progress::report`.
+//
+// Counts are of the input being processed: raw bytes when compressing,
+// compressed payload bytes (header excluded) when decompressing.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::error;
+use crate::report::Report;
+
+/// The error a cancelled run returns.
+pub const CANCELLED: &str = error::CANCELLED;
+
+/// Shared flag that stops a run at its next progress report. Clones refer to
+/// the same flag, so one can be handed to another thread or a signal handler.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Where a run reports to. `Progress::none()` reports nowhere and never
+/// cancels.
+#[derive(Default)]
+pub struct Progress<'a> {
+    callback: Option<Box<dyn FnMut(u64, u64) + 'a>>,
+    token: Option<CancellationToken>,
+    done: u64,
+    total: u64,
+}
+
+impl<'a> Progress<'a> {
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Calls `callback` with bytes processed and the total.
+    pub fn new(callback: impl FnMut(u64, u64) + 'a) -> Self {
+        Self { callback: Some(Box::new(callback)), ..Self::default() }
+    }
+
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Begins a run over `total` bytes.
+    pub fn start(&mut self, total: u64) -> Result<(), &'static str> {
+        self.total = total;
+        self.done = 0;
+        self.notify()
+    }
+
+    /// Records `bytes` more as processed. Fails if the run was cancelled.
+    pub fn advance(&mut self, bytes: u64) -> Result<(), &'static str> {
+        self.done = (self.done + bytes).min(self.total);
+        self.notify()
+    }
+
+    /// Records that the first `done` bytes are processed. Fails if the run
+    /// was cancelled.
+    pub fn advance_to(&mut self, done: u64) -> Result<(), &'static str> {
+        self.done = done.min(self.total);
+        self.notify()
+    }
+
+    /// A codec `Report` that advances this run to the codec's position, for
+    /// input coded in one piece.
+    pub fn report(&mut self) -> Report<'_> {
+        Report::new(move |pos| self.advance_to(pos as u64))
+    }
+
+    /// Reports the run as complete, covering trailing bytes such as a seek
+    /// index that no unit of work accounted for.
+    pub fn finish(&mut self) {
+        if self.done < self.total {
+            self.done = self.total;
+            if let Some(callback) = &mut self.callback {
+                callback(self.done, self.total);
+            }
+        }
+    }
+
+    fn notify(&mut self) -> Result<(), &'static str> {
+        if let Some(callback) = &mut self.callback {
+            callback(self.done, self.total);
+        }
+        match &self.token {
+            Some(token) if token.is_cancelled() => Err(CANCELLED),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_and_cancels() {
+        let mut reports = Vec::new();
+        let token = CancellationToken::new();
+        let mut progress = Progress::new(|done, total| reports.push((done, total))).with_cancellation(token.clone());
+        progress.start(100).unwrap();
+        progress.advance(60).unwrap();
+        token.cancel();
+        assert_eq!(progress.advance(60), Err(CANCELLED));
+        progress.finish();
+        drop(progress);
+        assert_eq!(reports, [(0, 100), (60, 100), (100, 100)]);
+
+        let mut reports = Vec::new();
+        let mut progress = Progress::new(|done, total| reports.push((done, total)));
+        progress.start(1 << 20).unwrap();
+        let mut report = progress.report();
+        for pos in 0..1 << 20 {
+            report.at(pos).unwrap();
+        }
+        drop(report);
+        progress.finish();
+        drop(progress);
+        assert_eq!(reports.len(), 17);
+        assert_eq!(reports[1], (64 * 1024, 1 << 20));
+        assert_eq!(reports[16], (1 << 20, 1 << 20));
+
+        let mut progress = Progress::none();
+        progress.start(10).unwrap();
+        progress.advance(10).unwrap();
+        progress.finish();
+    }
+}
@@ -0,0 +1,254 @@
+// Python bindings, built with `maturin develop` (the `python` feature):
+//
+//   import rs_compressor
+//   packed = rs_compressor.compress(data, algorithm="lz")
+//   rs_compressor.decompress(packed) == data
+//
+//   with open("big.log.rs", "wb") as raw, rs_compressor.CompressedWriter(raw) as out:
+//       out.write(chunk)
+//   with open("big.log.rs", "rb") as raw, rs_compressor.CompressedReader(raw) as inp:
+//       inp.read(65536)
+//
+// Errors are raised as `rs_compressor.CompressionError`, a `ValueError`.
+// Inputs may be any bytes-like object (`bytes`, `bytearray`, `memoryview`).
+
+use pyo3::buffer::PyBuffer;
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::algos::file_type::{select_filters, Algorithm};
+use crate::codec;
+use crate::framed::{StreamDecoder, StreamEncoder, DEFAULT_BLOCK_SIZE};
+
+create_exception!(rs_compressor, CompressionError, PyValueError);
+
+/// Bytes read from the underlying file per refill.
+const READ_SIZE: usize = 64 * 1024;
+
+fn error(message: &str) -> PyErr {
+    CompressionError::new_err(message.to_string())
+}
+
+/// `None` for "auto".
+fn parse_algorithm(name: &str) -> PyResult<Option<Algorithm>> {
+    Ok(Some(match name.to_ascii_lowercase().as_str() {
+        "auto" => return Ok(None),
+        "rle" => Algorithm::RLE,
+        "lz" => Algorithm::LZ,
+        "packbits" => Algorithm::PackBits,
+        "bwt" => Algorithm::BWT,
+        "rc" => Algorithm::RC,
+        "lz4" => Algorithm::LZ4,
+        _ => return Err(PyValueError::new_err(format!(
+            "Unknown algorithm {:?}; use auto, rle, lz, packbits, bwt, rc or lz4",
+            name
+        ))),
+    }))
+}
+
+/// The codec for "auto" at `level`: 1-3 favour speed (LZ4), 4-6 let Auto
+/// choose from the data, 7-8 use BWT and 9 the range coder.
+fn level_algorithm(level: u8, input: &[u8]) -> PyResult<Algorithm> {
+    Ok(match level {
+        1..=3 => Algorithm::LZ4,
+        4..=6 => codec::auto_select(input, None),
+        7..=8 => Algorithm::BWT,
+        9 => Algorithm::RC,
+        _ => return Err(PyValueError::new_err("level must be between 1 and 9")),
+    })
+}
+
+/// Compresses `data` into an RSCMP file (an LZ4 frame for "lz4").
+/// `level` only applies to "auto", trading speed for size from 1 to 9.
+#[pyfunction]
+#[pyo3(signature = (data, algorithm="auto", level=None))]
+fn compress<'py>(py: Python<'py>, data: PyBuffer<u8>, algorithm: &str, level: Option<u8>) -> PyResult<Bound<'py, PyBytes>> {
+    let data = data.to_vec(py)?;
+    let (algorithm, filters) = match (parse_algorithm(algorithm)?, level) {
+        (Some(_), Some(_)) => return Err(PyValueError::new_err("level only applies to algorithm=\"auto\"")),
+        (Some(algorithm), None) => (algorithm, Vec::new()),
+        (None, level) => {
+            let algorithm = match level {
+                Some(level) => level_algorithm(level, &data)?,
+                None => codec::auto_select(&data, None),
+            };
+            let filters = if algorithm == Algorithm::LZ4 { Vec::new() } else { select_filters(&data) };
+            (algorithm, filters)
+        }
+    };
+    let compressed = py
+        .detach(|| codec::compress_with_filters(&data, algorithm, &filters, None, None))
+        .map_err(error)?;
+    Ok(PyBytes::new(py, &compressed))
+}
+
+/// Decompresses an RSCMP, LZ4 or JSCMP file.
+#[pyfunction]
+fn decompress<'py>(py: Python<'py>, data: PyBuffer<u8>) -> PyResult<Bound<'py, PyBytes>> {
+    let data = data.to_vec(py)?;
+    let decompressed = py.detach(|| codec::decompress(&data, None)).map_err(error)?;
+    Ok(PyBytes::new(py, &decompressed))
+}
+
+/// Write-only file object that compresses into `file`, a binary file opened
+/// for writing, in the seekable framed format. `close()` writes the end of
+/// the stream but leaves `file` open, as `gzip.GzipFile` does.
+#[pyclass(module = "rs_compressor")]
+struct CompressedWriter {
+    file: Py<PyAny>,
+    encoder: Option<StreamEncoder>,
+}
+
+impl CompressedWriter {
+    fn encoder(&mut self) -> PyResult<&mut StreamEncoder> {
+        self.encoder.as_mut().ok_or_else(|| PyValueError::new_err("I/O operation on closed file"))
+    }
+}
+
+#[pymethods]
+impl CompressedWriter {
+    /// "lz4" can't be streamed; its frames are already blocked.
+    #[new]
+    #[pyo3(signature = (file, algorithm="auto", block_size=DEFAULT_BLOCK_SIZE))]
+    fn new(file: Py<PyAny>, algorithm: &str, block_size: u32) -> PyResult<Self> {
+        let encoder = StreamEncoder::new(parse_algorithm(algorithm)?, block_size).map_err(error)?;
+        Ok(Self { file, encoder: Some(encoder) })
+    }
+
+    /// Compresses `data`, writing out every block it completes.
+    fn write(&mut self, py: Python<'_>, data: PyBuffer<u8>) -> PyResult<usize> {
+        let data = data.to_vec(py)?;
+        let output = self.encoder()?.push(&data).map_err(error)?;
+        if !output.is_empty() {
+            self.file.bind(py).call_method1("write", (PyBytes::new(py, &output),))?;
+        }
+        Ok(data.len())
+    }
+
+    /// Writes the last block and the seek index. Closing twice does nothing.
+    fn close(&mut self, py: Python<'_>) -> PyResult<()> {
+        if let Some(encoder) = self.encoder.take() {
+            let output = encoder.finish().map_err(error)?;
+            self.file.bind(py).call_method1("write", (PyBytes::new(py, &output),))?;
+        }
+        Ok(())
+    }
+
+    #[getter]
+    fn closed(&self) -> bool {
+        self.encoder.is_none()
+    }
+
+    fn writable(&self) -> bool {
+        true
+    }
+
+    fn readable(&self) -> bool {
+        false
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    #[pyo3(signature = (*_args))]
+    fn __exit__(&mut self, py: Python<'_>, _args: &Bound<'_, pyo3::types::PyTuple>) -> PyResult<bool> {
+        self.close(py)?;
+        Ok(false)
+    }
+}
+
+/// Read-only file object that decompresses from `file`, a binary file opened
+/// for reading. Framed files decode a block at a time; any other file is
+/// read whole before the first byte comes out.
+#[pyclass(module = "rs_compressor")]
+struct CompressedReader {
+    file: Py<PyAny>,
+    decoder: Option<StreamDecoder>,
+    buffer: Vec<u8>,
+    closed: bool,
+}
+
+impl CompressedReader {
+    /// Reads and decodes until `buffer` holds `wanted` bytes or the input
+    /// ends; `None` reads to the end.
+    fn fill(&mut self, py: Python<'_>, wanted: Option<usize>) -> PyResult<()> {
+        while wanted.is_none_or(|wanted| self.buffer.len() < wanted) {
+            let Some(decoder) = self.decoder.as_mut() else {
+                break;
+            };
+            let chunk: Vec<u8> = self.file.bind(py).call_method1("read", (READ_SIZE,))?.extract()?;
+            let output = if chunk.is_empty() {
+                self.decoder.take().unwrap().finish()
+            } else {
+                decoder.push(&chunk)
+            };
+            self.buffer.extend(output.map_err(error)?);
+        }
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl CompressedReader {
+    #[new]
+    fn new(file: Py<PyAny>) -> Self {
+        Self { file, decoder: Some(StreamDecoder::new(None)), buffer: Vec::new(), closed: false }
+    }
+
+    /// Returns up to `size` decompressed bytes, or everything left when
+    /// `size` is negative. An empty result means the end of the stream.
+    #[pyo3(signature = (size=-1))]
+    fn read<'py>(&mut self, py: Python<'py>, size: isize) -> PyResult<Bound<'py, PyBytes>> {
+        if self.closed {
+            return Err(PyValueError::new_err("I/O operation on closed file"));
+        }
+        let wanted = usize::try_from(size).ok();
+        self.fill(py, wanted)?;
+        let take = wanted.map_or(self.buffer.len(), |wanted| wanted.min(self.buffer.len()));
+        let data: Vec<u8> = self.buffer.drain(..take).collect();
+        Ok(PyBytes::new(py, &data))
+    }
+
+    /// Leaves `file` open.
+    fn close(&mut self) {
+        self.closed = true;
+        self.decoder = None;
+        self.buffer = Vec::new();
+    }
+
+    #[getter]
+    fn closed(&self) -> bool {
+        self.closed
+    }
+
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    #[pyo3(signature = (*_args))]
+    fn __exit__(&mut self, _args: &Bound<'_, pyo3::types::PyTuple>) -> bool {
+        self.close();
+        false
+    }
+}
+
+#[pymodule]
+fn rs_compressor(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(compress, m)?)?;
+    m.add_function(wrap_pyfunction!(decompress, m)?)?;
+    m.add_class::<CompressedWriter>()?;
+    m.add_class::<CompressedReader>()?;
+    m.add("CompressionError", m.py().get_type::<CompressionError>())?;
+    Ok(())
+}
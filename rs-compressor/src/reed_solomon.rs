@@ -0,0 +1,184 @@
+// Reed–Solomon erasure code over GF(256) in its Cauchy form: parity row j is
+// the sum of data shard i times 1 / (x_j + y_i). Every square submatrix of a
+// Cauchy matrix is invertible, so any `parity` missing data shards can be
+// rebuilt from the same number of intact parity rows. Callers find the
+// missing shards themselves, from checksums.
+
+const POLY: u16 = 0x11D;
+
+const fn tables() -> ([u8; 512], [u8; 256]) {
+    let mut exp = [0; 512];
+    let mut log = [0; 256];
+    let mut x: u16 = 1;
+    let mut i = 0;
+    while i < 255 {
+        exp[i] = x as u8;
+        exp[i + 255] = x as u8;
+        log[x as usize] = i as u8;
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= POLY;
+        }
+        i += 1;
+    }
+    (exp, log)
+}
+
+const TABLES: ([u8; 512], [u8; 256]) = tables();
+const EXP: [u8; 512] = TABLES.0;
+const LOG: [u8; 256] = TABLES.1;
+
+fn mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    EXP[LOG[a as usize] as usize + LOG[b as usize] as usize]
+}
+
+fn inv(a: u8) -> u8 {
+    EXP[255 - LOG[a as usize] as usize]
+}
+
+/// Coefficient of data shard `i` in parity row `j`.
+fn cauchy(j: usize, i: usize, parity: usize) -> u8 {
+    inv(j as u8 ^ (parity + i) as u8)
+}
+
+/// Largest number of data plus parity shards the field allows.
+pub const MAX_SHARDS: usize = 256;
+
+/// Parity rows of `len` bytes for `data`; shorter shards count as
+/// zero-padded to `len`.
+pub fn encode(data: &[Vec<u8>], parity: usize, len: usize) -> Vec<Vec<u8>> {
+    assert!(data.len() + parity <= MAX_SHARDS);
+    (0..parity)
+        .map(|j| {
+            let mut row = vec![0; len];
+            for (i, shard) in data.iter().enumerate() {
+                let coefficient = cauchy(j, i, parity);
+                for (out, &byte) in row.iter_mut().zip(shard) {
+                    *out ^= mul(coefficient, byte);
+                }
+            }
+            row
+        })
+        .collect()
+}
+
+/// Inverts a square matrix over GF(256) by Gauss-Jordan elimination.
+fn invert(mut matrix: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>, &'static str> {
+    let n = matrix.len();
+    let mut inverse: Vec<Vec<u8>> = (0..n).map(|r| (0..n).map(|c| (r == c) as u8).collect()).collect();
+    for col in 0..n {
+        // Cauchy submatrices are invertible, so a pivot exists as long as
+        // the shards fit the field.
+        let pivot = (col..n).find(|&r| matrix[r][col] != 0).ok_or("Parity rows can't rebuild the damaged blocks")?;
+        matrix.swap(col, pivot);
+        inverse.swap(col, pivot);
+        let scale = inv(matrix[col][col]);
+        for c in 0..n {
+            matrix[col][c] = mul(matrix[col][c], scale);
+            inverse[col][c] = mul(inverse[col][c], scale);
+        }
+        for r in (0..n).filter(|&r| r != col) {
+            let factor = matrix[r][col];
+            if factor != 0 {
+                for c in 0..n {
+                    matrix[r][c] ^= mul(factor, matrix[col][c]);
+                    inverse[r][c] ^= mul(factor, inverse[col][c]);
+                }
+            }
+        }
+    }
+    Ok(inverse)
+}
+
+/// Fills in the missing (`None`) data shards, each rebuilt `len` bytes long,
+/// from the intact ones and the intact parity rows.
+pub fn reconstruct(data: &mut [Option<Vec<u8>>], parity: &[Option<Vec<u8>>], len: usize) -> Result<(), &'static str> {
+    if data.len() + parity.len() > MAX_SHARDS {
+        return Err("Too many blocks in a parity group");
+    }
+    let missing: Vec<usize> = (0..data.len()).filter(|&i| data[i].is_none()).collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let rows: Vec<usize> = (0..parity.len()).filter(|&j| parity[j].is_some()).take(missing.len()).collect();
+    if rows.len() < missing.len() {
+        return Err("Too many damaged blocks to repair");
+    }
+
+    // Each intact parity row minus the contribution of the intact data
+    // shards leaves a combination of the missing shards only.
+    let syndromes: Vec<Vec<u8>> = rows
+        .iter()
+        .map(|&j| {
+            let mut row = parity[j].clone().unwrap();
+            row.resize(len, 0);
+            for (i, shard) in data.iter().enumerate() {
+                if let Some(shard) = shard {
+                    let coefficient = cauchy(j, i, parity.len());
+                    for (out, &byte) in row.iter_mut().zip(shard) {
+                        *out ^= mul(coefficient, byte);
+                    }
+                }
+            }
+            row
+        })
+        .collect();
+
+    let matrix = rows
+        .iter()
+        .map(|&j| missing.iter().map(|&i| cauchy(j, i, parity.len())).collect())
+        .collect();
+    let inverse = invert(matrix)?;
+    for (k, &i) in missing.iter().enumerate() {
+        let mut shard = vec![0; len];
+        for (r, syndrome) in syndromes.iter().enumerate() {
+            let coefficient = inverse[k][r];
+            for (out, &byte) in shard.iter_mut().zip(syndrome) {
+                *out ^= mul(coefficient, byte);
+            }
+        }
+        data[i] = Some(shard);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rebuilds_any_missing_shards() {
+        let data: Vec<Vec<u8>> = (0..6u8).map(|i| (0..40u8).map(|b| b.wrapping_mul(7) ^ i.wrapping_mul(31)).collect()).collect();
+        let parity = encode(&data, 3, 40);
+
+        for lost in [vec![0], vec![5], vec![1, 4], vec![0, 2, 5]] {
+            let mut shards: Vec<Option<Vec<u8>>> = data.iter().cloned().map(Some).collect();
+            for &i in &lost {
+                shards[i] = None;
+            }
+            // Losing a parity row as well still leaves enough for one or two.
+            let mut rows: Vec<Option<Vec<u8>>> = parity.iter().cloned().map(Some).collect();
+            if lost.len() < 3 {
+                rows[0] = None;
+            }
+            reconstruct(&mut shards, &rows, 40).unwrap();
+            assert_eq!(shards.into_iter().map(Option::unwrap).collect::<Vec<_>>(), data);
+        }
+
+        let mut shards: Vec<Option<Vec<u8>>> = vec![None; 6];
+        assert!(reconstruct(&mut shards, &parity.into_iter().map(Some).collect::<Vec<_>>(), 40).is_err());
+    }
+
+    #[test]
+    fn test_too_many_shards_is_an_error() {
+        // Past MAX_SHARDS the Cauchy indices wrap and the matrix can be
+        // singular, so this has to fail before any arithmetic.
+        let mut shards: Vec<Option<Vec<u8>>> = vec![Some(vec![1; 8]); 16];
+        shards[3] = None;
+        let rows = vec![Some(vec![0; 8]); MAX_SHARDS - 15];
+        assert_eq!(reconstruct(&mut shards, &rows, 8), Err("Too many blocks in a parity group"));
+    }
+}
@@ -0,0 +1,165 @@
+// Figures about a finished compression run. They are read back from the
+// input and the output rather than gathered along the way, so every mode
+// (plain, framed, deduplicated) reports the same way and callers only need
+// to time the run.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::algos::file_type::{detect_file_type, FileType};
+use crate::algos::lz::{self, TokenStats};
+use crate::header::{read_header, Algorithm};
+use crate::{dedup, framed};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressionStats {
+    pub input_size: u64,
+    pub output_size: u64,
+    pub elapsed: Duration,
+    pub file_type: FileType,
+    /// Independently coded units: framed blocks, distinct dedup chunks, or 1
+    /// for a plain file.
+    pub blocks: usize,
+    /// Match and literal counts, when the codec is LZ.
+    pub lz: Option<TokenStats>,
+}
+
+impl CompressionStats {
+    /// Describes compressing `input` to `output` in `elapsed`.
+    pub fn collect(input: &[u8], output: &[u8], elapsed: Duration) -> Self {
+        let mut stats = CompressionStats {
+            input_size: input.len() as u64,
+            output_size: output.len() as u64,
+            elapsed,
+            file_type: detect_file_type(input),
+            blocks: 1,
+            lz: None,
+        };
+        let Ok(header) = read_header(output) else {
+            return stats;
+        };
+        let body = output.get(header.size()..).unwrap_or_default();
+        let payloads = match header.block_size {
+            Some(_) => framed::block_payloads(body),
+            None if header.deduplicated => dedup::chunk_payloads(body).unwrap_or_default(),
+            None => vec![body],
+        };
+        stats.blocks = payloads.len();
+        if header.algorithm == Algorithm::LZ {
+            let mut tokens = TokenStats::default();
+            payloads.iter().for_each(|payload| tokens.add(lz::token_stats(payload)));
+            stats.lz = Some(tokens);
+        }
+        stats
+    }
+
+    /// Input size over output size; above 1.0 means the data shrank.
+    pub fn ratio(&self) -> f64 {
+        if self.output_size == 0 {
+            return 1.0;
+        }
+        self.input_size as f64 / self.output_size as f64
+    }
+
+    /// One JSON object, for scripts.
+    pub fn to_json(&self) -> String {
+        let lz = match &self.lz {
+            Some(tokens) => format!(
+                "{{\"matches\":{},\"literals\":{},\"average_match_length\":{:.3}}}",
+                tokens.matches,
+                tokens.literals,
+                tokens.average_match_length()
+            ),
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"input_size\":{},\"output_size\":{},\"ratio\":{:.4},\"elapsed_ms\":{:.3},\"file_type\":\"{:?}\",\"blocks\":{},\"lz\":{}}}",
+            self.input_size,
+            self.output_size,
+            self.ratio(),
+            self.elapsed.as_secs_f64() * 1000.0,
+            self.file_type,
+            self.blocks,
+            lz
+        )
+    }
+}
+
+impl fmt::Display for CompressionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Input: {} bytes ({:?})", self.input_size, self.file_type)?;
+        writeln!(f, "Output: {} bytes, ratio {:.2}x", self.output_size, self.ratio())?;
+        write!(f, "Blocks: {}, time: {:.1} ms", self.blocks, self.elapsed.as_secs_f64() * 1000.0)?;
+        if let Some(tokens) = &self.lz {
+            write!(
+                f,
+                "\nLZ: {} matches (average length {:.2}), {} literals",
+                tokens.matches,
+                tokens.average_match_length(),
+                tokens.literals
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Times a run. `Instant` panics on wasm32-unknown-unknown, so the JS clock
+/// stands in there.
+pub struct Stopwatch {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+    #[cfg(target_arch = "wasm32")]
+    start: f64,
+}
+
+impl Stopwatch {
+    pub fn start() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let start = std::time::Instant::now();
+        #[cfg(target_arch = "wasm32")]
+        let start = js_sys::Date::now();
+        Self { start }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.start.elapsed();
+        #[cfg(target_arch = "wasm32")]
+        return Duration::from_secs_f64((js_sys::Date::now() - self.start).max(0.0) / 1000.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algos::file_type::Algorithm as FileAlgorithm;
+    use crate::codec;
+    use crate::framed::FrameOptions;
+    use crate::progress::Progress;
+
+    #[test]
+    fn test_stats_for_each_mode() {
+        let input = b"stats are read back from the output, stats are read back. ".repeat(200);
+
+        let plain = codec::compress(&input, FileAlgorithm::LZ, None).unwrap();
+        let stats = CompressionStats::collect(&input, &plain, Duration::from_millis(5));
+        assert_eq!(stats.file_type, FileType::Text);
+        assert_eq!(stats.blocks, 1);
+        assert_eq!(stats.ratio(), input.len() as f64 / plain.len() as f64);
+        let tokens = stats.lz.unwrap();
+        assert_eq!(tokens.literals + tokens.match_bytes, input.len());
+        assert!(stats.to_json().contains("\"elapsed_ms\":5.000"));
+
+        let options = FrameOptions { block_size: 4096, parity: 1 };
+        let framed = framed::compress(&input, FileAlgorithm::LZ, &[], None, None, &options, &mut Progress::none()).unwrap();
+        let stats = CompressionStats::collect(&input, &framed, Duration::ZERO);
+        assert_eq!(stats.blocks, input.len().div_ceil(4096));
+        let tokens = stats.lz.unwrap();
+        assert_eq!(tokens.literals + tokens.match_bytes, input.len());
+
+        let rc = codec::compress(&input, FileAlgorithm::RC, None).unwrap();
+        let stats = CompressionStats::collect(&input, &rc, Duration::ZERO);
+        assert_eq!(stats.lz, None);
+        assert!(stats.to_json().ends_with("\"lz\":null}"));
+    }
+}
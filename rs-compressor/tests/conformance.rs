@@ -0,0 +1,188 @@
+// Conformance vectors for the RSCMP and JSCMP formats. Each directory under
+// tests/vectors holds one vector:
+//
+//   input       the original file
+//   compressed  what an encoder wrote for it
+//   expected    what decoding `compressed` must produce, byte for byte
+//   dictionary  the dictionary it was compressed with, if any
+//   passphrase  the passphrase it was encrypted with, if any
+//   base        the file a patch applies to, if it is one
+//
+// The RSCMP vectors were written by this crate's CLI, from inside the vector
+// directory:
+//   rle         compress input compressed --rle
+//   rle-binary  compress input compressed --rle
+//   lz          compress input compressed --lz
+//   lz-empty    compress input compressed --lz
+//   packbits    compress input compressed --packbits
+//   bwt         compress input compressed --bwt
+//   rc          compress input compressed --rc
+//   rc-binary   compress input compressed --rc
+//   lz4         compress input compressed --lz4
+//   dictionary-lz  compress input compressed --lz --dict dictionary
+//   dictionary-rc  compress input compressed --rc --dict dictionary
+//   framed      compress input compressed --lz --seekable --block-size 1024
+//   framed-parity  compress input compressed --rc --seekable --block-size 512 --parity 2
+//   metadata    compress input compressed --lz --meta origin=vectors --meta purpose=conformance
+//   dedup       compress input compressed --lz --dedup
+//   filters     compress input compressed --rc --filter delta:4,transpose:4
+//   encrypted   compress input compressed --bwt --encrypt --passphrase-file passphrase
+//   patch       diff base input compressed
+// The dictionaries come from `train-dict dictionary input --size 1024`, and
+// the jscmp-rle and jscmp-lz files from js-compressor:
+//   node cli.js compress input out --rle (or --lz), then out/input.compressed
+//
+// A vector only changes along with the format. If one of these tests fails,
+// either the change broke files already written or the format moved on and
+// needs new vectors and a new header version.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rs_compressor::algos::file_type::Algorithm;
+use rs_compressor::codec;
+use rs_compressor::crypto;
+use rs_compressor::dedup;
+use rs_compressor::framed::{self, FrameOptions};
+use rs_compressor::header::{read_header, Algorithm as HeaderAlgorithm};
+use rs_compressor::jscmp;
+use rs_compressor::progress::Progress;
+
+struct Vector {
+    name: String,
+    input: Vec<u8>,
+    compressed: Vec<u8>,
+    expected: Vec<u8>,
+    dictionary: Option<Vec<u8>>,
+    passphrase: Option<String>,
+    base: Option<Vec<u8>>,
+}
+
+fn optional(dir: &Path, name: &str) -> Option<Vec<u8>> {
+    fs::read(dir.join(name)).ok()
+}
+
+fn vectors() -> Vec<Vector> {
+    let root: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "vectors"].iter().collect();
+    let mut dirs: Vec<PathBuf> = fs::read_dir(&root)
+        .unwrap_or_else(|e| panic!("reading {}: {}", root.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
+    dirs.iter()
+        .map(|dir| {
+            let required = |name: &str| {
+                let path = dir.join(name);
+                fs::read(&path).unwrap_or_else(|e| panic!("reading {}: {}", path.display(), e))
+            };
+            Vector {
+                name: dir.file_name().unwrap().to_string_lossy().into_owned(),
+                input: required("input"),
+                compressed: required("compressed"),
+                expected: required("expected"),
+                dictionary: optional(dir, "dictionary"),
+                passphrase: optional(dir, "passphrase")
+                    .map(|bytes| String::from_utf8(bytes).unwrap().trim_end_matches(['\r', '\n']).to_string()),
+                base: optional(dir, "base"),
+            }
+        })
+        .collect()
+}
+
+fn decode(vector: &Vector, compressed: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let compressed = match &vector.passphrase {
+        Some(passphrase) => crypto::decrypt(compressed, passphrase)?,
+        None => compressed.to_vec(),
+    };
+    match &vector.base {
+        Some(base) => codec::patch(base, &compressed),
+        None => codec::decompress(&compressed, vector.dictionary.as_deref()),
+    }
+}
+
+fn file_algorithm(algorithm: HeaderAlgorithm) -> Algorithm {
+    match algorithm {
+        HeaderAlgorithm::RLE => Algorithm::RLE,
+        HeaderAlgorithm::LZ => Algorithm::LZ,
+        HeaderAlgorithm::PackBits => Algorithm::PackBits,
+        HeaderAlgorithm::BWT => Algorithm::BWT,
+        HeaderAlgorithm::RC => Algorithm::RC,
+        HeaderAlgorithm::LZ4 => Algorithm::LZ4,
+    }
+}
+
+/// Compresses the vector's input again with the settings its header records.
+fn encode(vector: &Vector) -> Result<Vec<u8>, &'static str> {
+    let input = &vector.input;
+    let dictionary = vector.dictionary.as_deref();
+    if jscmp::is_jscmp(&vector.compressed) {
+        let algorithm = if vector.compressed[6] == 1 { Algorithm::RLE } else { Algorithm::LZ };
+        return jscmp::compress(input, algorithm);
+    }
+    if let Some(base) = &vector.base {
+        return Ok(codec::diff(base, input));
+    }
+    let plain = match &vector.passphrase {
+        Some(passphrase) => crypto::decrypt(&vector.compressed, passphrase)?,
+        None => vector.compressed.clone(),
+    };
+    let header = read_header(&plain)?;
+    let algorithm = file_algorithm(header.algorithm);
+    let metadata = header.metadata.as_ref();
+    let encoded = if let Some(block_size) = header.block_size {
+        let options = FrameOptions { block_size, parity: 0 };
+        framed::compress(input, algorithm, &header.filters, dictionary, metadata, &options, &mut Progress::none())?
+    } else if header.deduplicated {
+        dedup::compress(input, algorithm, &header.filters, dictionary, metadata, &mut Progress::none())?.0
+    } else {
+        codec::compress_with_filters(input, algorithm, &header.filters, dictionary, metadata)?
+    };
+    match &vector.passphrase {
+        Some(passphrase) => crypto::encrypt(&encoded, passphrase),
+        None => Ok(encoded),
+    }
+}
+
+#[test]
+fn covers_every_algorithm_and_header_flag() {
+    let vectors = vectors();
+    let headers: Vec<_> = vectors
+        .iter()
+        .filter_map(|vector| {
+            let plain = match &vector.passphrase {
+                Some(passphrase) => crypto::decrypt(&vector.compressed, passphrase).unwrap(),
+                None => vector.compressed.clone(),
+            };
+            read_header(&plain).ok()
+        })
+        .collect();
+    for algorithm in [Algorithm::RLE, Algorithm::LZ, Algorithm::PackBits, Algorithm::BWT, Algorithm::RC, Algorithm::LZ4] {
+        assert!(headers.iter().any(|header| file_algorithm(header.algorithm) == algorithm), "{:?}", algorithm);
+    }
+    assert!(headers.iter().any(|header| header.dictionary_id.is_some()));
+    assert!(headers.iter().any(|header| header.reference_checksum.is_some()));
+    assert!(headers.iter().any(|header| header.block_size.is_some()));
+    assert!(headers.iter().any(|header| header.metadata.is_some()));
+    assert!(headers.iter().any(|header| header.deduplicated));
+    assert!(headers.iter().any(|header| !header.filters.is_empty()));
+    assert!(vectors.iter().any(|vector| crypto::is_encrypted(&vector.compressed)));
+    assert!(vectors.iter().any(|vector| jscmp::is_jscmp(&vector.compressed)));
+}
+
+#[test]
+fn decodes_vectors_byte_exactly() {
+    for vector in vectors() {
+        let decoded = decode(&vector, &vector.compressed).unwrap_or_else(|e| panic!("{}: {}", vector.name, e));
+        assert_eq!(decoded, vector.expected, "{}", vector.name);
+    }
+}
+
+#[test]
+fn encoder_output_decodes_to_the_input() {
+    for vector in vectors() {
+        let encoded = encode(&vector).unwrap_or_else(|e| panic!("{}: {}", vector.name, e));
+        let decoded = decode(&vector, &encoded).unwrap_or_else(|e| panic!("{}: {}", vector.name, e));
+        assert_eq!(decoded, vector.input, "{}", vector.name);
+    }
+}
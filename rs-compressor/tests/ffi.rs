@@ -0,0 +1,47 @@
+// Builds tests/ffi/roundtrip.c against the staticlib and the committed
+// include/rs_compressor.h with the system C compiler (`cc`, or $CC), then
+// runs it.
+
+#![cfg(all(feature = "ffi", not(target_arch = "wasm32")))]
+
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+fn c_program_roundtrips_through_the_c_api() {
+    let manifest: PathBuf = env!("CARGO_MANIFEST_DIR").into();
+    // Test binaries live in target/<profile>/deps; the staticlib is built
+    // alongside the rlib and copied up to target/<profile>.
+    let profile_dir = env::current_exe().unwrap().parent().unwrap().parent().unwrap().to_path_buf();
+    let library = profile_dir.join("librs_compressor.a");
+    assert!(library.exists(), "{} was not built", library.display());
+
+    let program = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("ffi_roundtrip");
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(&compiler)
+        .args(["-std=c99", "-Wall", "-Werror", "-I"])
+        .arg(manifest.join("include"))
+        .arg(manifest.join("tests/ffi/roundtrip.c"))
+        .arg(&library)
+        .args(["-lpthread", "-ldl", "-lm", "-o"])
+        .arg(&program)
+        .status()
+        .unwrap_or_else(|e| panic!("running {}: {}", compiler, e));
+    assert!(status.success(), "compiling roundtrip.c failed");
+
+    let output = Command::new(&program).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "roundtrip.c failed:\n{}", stdout);
+    assert_eq!(stdout.trim(), "ok");
+}
+
+#[test]
+fn committed_header_matches_the_c_api() {
+    let committed = include_str!("../include/rs_compressor.h");
+    let generated = include_str!(concat!(env!("OUT_DIR"), "/rs_compressor.h"));
+    assert!(
+        committed == generated,
+        "include/rs_compressor.h is out of date; rebuild with RS_COMPRESSOR_WRITE_HEADER=1 to update it"
+    );
+}
@@ -0,0 +1,136 @@
+/* Exercises the C API through include/rs_compressor.h; tests/ffi.rs builds
+ * it against the staticlib and runs it. Prints a line per failed check and
+ * exits non-zero if there were any. */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "rs_compressor.h"
+
+static int failures = 0;
+
+#define CHECK(condition)                                                    \
+    do {                                                                    \
+        if (!(condition)) {                                                 \
+            printf("%s:%d: check failed: %s\n", __FILE__, __LINE__, #condition); \
+            failures++;                                                     \
+        }                                                                   \
+    } while (0)
+
+static unsigned char *sample(size_t len) {
+    unsigned char *data = malloc(len);
+    unsigned int state = 1;
+    for (size_t i = 0; i < len; i++) {
+        /* Runs, repeated phrases and noise, so every codec has work to do. */
+        state = state * 1103515245u + 12345u;
+        data[i] = (i / 64) % 3 == 0 ? 'a' + (i % 7) : (i / 64) % 3 == 1 ? 'z' : (unsigned char)(state >> 16);
+    }
+    return data;
+}
+
+static void test_one_shot(const unsigned char *input, size_t len) {
+    const uint32_t algorithms[] = {
+        RSC_ALGORITHM_AUTO, RSC_ALGORITHM_RLE, RSC_ALGORITHM_LZ, RSC_ALGORITHM_PACKBITS,
+        RSC_ALGORITHM_BWT, RSC_ALGORITHM_RC, RSC_ALGORITHM_LZ4,
+    };
+    for (size_t a = 0; a < sizeof algorithms / sizeof algorithms[0]; a++) {
+        size_t capacity = rsc_compress_bound(len);
+        unsigned char *compressed = malloc(capacity);
+        size_t compressed_len = 0;
+        CHECK(rsc_compress(algorithms[a], input, len, compressed, capacity, &compressed_len) == RSC_STATUS_OK);
+        CHECK(compressed_len <= capacity);
+
+        uint8_t *output = NULL;
+        size_t output_len = 0;
+        CHECK(rsc_decompress(compressed, compressed_len, &output, &output_len) == RSC_STATUS_OK);
+        CHECK(output_len == len && memcmp(output, input, len) == 0);
+        rsc_free(output, output_len);
+        free(compressed);
+    }
+}
+
+static void test_errors(const unsigned char *input, size_t len) {
+    size_t capacity = rsc_compress_bound(len);
+    unsigned char *compressed = malloc(capacity);
+    size_t compressed_len = 0;
+
+    CHECK(rsc_compress(99, input, len, compressed, capacity, &compressed_len) == RSC_STATUS_INVALID_ARGUMENT);
+    CHECK(rsc_compress(RSC_ALGORITHM_LZ, NULL, len, compressed, capacity, &compressed_len) == RSC_STATUS_INVALID_ARGUMENT);
+    CHECK(rsc_compress(RSC_ALGORITHM_LZ, input, len, compressed, 4, &compressed_len) == RSC_STATUS_BUFFER_TOO_SMALL);
+    CHECK(compressed_len > 4);
+
+    uint8_t *output = NULL;
+    size_t output_len = 0;
+    const unsigned char garbage[] = "not a compressed file at all";
+    CHECK(rsc_decompress(garbage, sizeof garbage, &output, &output_len) == RSC_STATUS_INVALID_FORMAT);
+
+    /* The range coder checks its output, so a damaged payload is caught. */
+    CHECK(rsc_compress(RSC_ALGORITHM_RC, input, len, compressed, capacity, &compressed_len) == RSC_STATUS_OK);
+    RscStatus status = rsc_decompress(compressed, compressed_len / 2, &output, &output_len);
+    CHECK(status != RSC_STATUS_OK);
+    CHECK(strlen(rsc_status_message(status)) > 0);
+
+    CHECK(rsc_encoder_new(RSC_ALGORITHM_LZ4, 0) == NULL);
+    free(compressed);
+}
+
+static void append(unsigned char **buffer, size_t *len, uint8_t *data, size_t data_len) {
+    *buffer = realloc(*buffer, *len + data_len + 1);
+    memcpy(*buffer + *len, data, data_len);
+    *len += data_len;
+    rsc_free(data, data_len);
+}
+
+static void test_streaming(const unsigned char *input, size_t len) {
+    RscEncoder *encoder = rsc_encoder_new(RSC_ALGORITHM_AUTO, 4096);
+    CHECK(encoder != NULL);
+    unsigned char *compressed = NULL;
+    size_t compressed_len = 0;
+    uint8_t *chunk;
+    size_t chunk_len;
+    for (size_t pos = 0; pos < len; pos += 1000) {
+        size_t piece = len - pos < 1000 ? len - pos : 1000;
+        CHECK(rsc_encoder_push(encoder, input + pos, piece, &chunk, &chunk_len) == RSC_STATUS_OK);
+        append(&compressed, &compressed_len, chunk, chunk_len);
+    }
+    CHECK(rsc_encoder_finish(encoder, &chunk, &chunk_len) == RSC_STATUS_OK);
+    append(&compressed, &compressed_len, chunk, chunk_len);
+
+    RscDecoder *decoder = rsc_decoder_new();
+    unsigned char *output = NULL;
+    size_t output_len = 0;
+    for (size_t pos = 0; pos < compressed_len; pos += 777) {
+        size_t piece = compressed_len - pos < 777 ? compressed_len - pos : 777;
+        CHECK(rsc_decoder_push(decoder, compressed + pos, piece, &chunk, &chunk_len) == RSC_STATUS_OK);
+        append(&output, &output_len, chunk, chunk_len);
+    }
+    CHECK(rsc_decoder_finish(decoder, &chunk, &chunk_len) == RSC_STATUS_OK);
+    append(&output, &output_len, chunk, chunk_len);
+    CHECK(output_len == len && memcmp(output, input, len) == 0);
+
+    /* A stream cut short is reported when it finishes. */
+    decoder = rsc_decoder_new();
+    CHECK(rsc_decoder_push(decoder, compressed, compressed_len / 2, &chunk, &chunk_len) == RSC_STATUS_OK);
+    rsc_free(chunk, chunk_len);
+    CHECK(rsc_decoder_finish(decoder, &chunk, &chunk_len) == RSC_STATUS_TRUNCATED);
+
+    encoder = rsc_encoder_new(RSC_ALGORITHM_LZ, 0);
+    rsc_encoder_free(encoder);
+    free(compressed);
+    free(output);
+}
+
+int main(void) {
+    size_t len = 50000;
+    unsigned char *input = sample(len);
+    test_one_shot(input, len);
+    test_one_shot(input, 0);
+    test_errors(input, len);
+    test_streaming(input, len);
+    free(input);
+    if (failures == 0) {
+        printf("ok\n");
+    }
+    return failures == 0 ? 0 : 1;
+}
@@ -0,0 +1,60 @@
+// Frames and blocks in tests/lz4 were produced by the reference lz4 v1.9.4
+// command line tool:
+//   lz4 access.log access.log.lz4
+//   lz4 -BD -BX -B4 --content-size access.log access.log.linked.lz4
+//   lz4 random.bin random.bin.lz4
+//   lz4 empty.bin empty.bin.lz4
+// access.log.block is the single block carved out of access.log.lz4.
+
+use std::fs;
+use std::path::PathBuf;
+
+use rs_compressor::algos::lz4;
+use rs_compressor::header::{read_header, Algorithm};
+
+fn vector(name: &str) -> Vec<u8> {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "lz4", name].iter().collect();
+    fs::read(&path).unwrap_or_else(|e| panic!("reading {}: {}", path.display(), e))
+}
+
+#[test]
+fn decodes_reference_frames() {
+    for (frame, original) in [
+        ("access.log.lz4", "access.log"),
+        ("access.log.linked.lz4", "access.log"),
+        ("random.bin.lz4", "random.bin"),
+        ("empty.bin.lz4", "empty.bin"),
+    ] {
+        let decoded = lz4::decompress(&vector(frame)).unwrap_or_else(|e| panic!("{}: {}", frame, e));
+        assert_eq!(decoded, vector(original), "{}", frame);
+    }
+}
+
+#[test]
+fn decodes_reference_block() {
+    assert_eq!(lz4::decompress_block(&vector("access.log.block")).unwrap(), vector("access.log"));
+}
+
+#[test]
+fn reference_frames_are_detected_without_rscmp_header() {
+    let header = read_header(&vector("access.log.lz4")).unwrap();
+    assert!(matches!(header.algorithm, Algorithm::LZ4));
+    assert_eq!(header.size(), 0);
+}
+
+#[test]
+fn encoded_frames_roundtrip() {
+    for name in ["access.log", "random.bin", "empty.bin"] {
+        let original = vector(name);
+        let frame = lz4::compress(&original);
+        assert_eq!(lz4::decompress(&frame).unwrap(), original, "{}", name);
+    }
+}
+
+#[test]
+fn rejects_corrupted_reference_frame() {
+    let mut frame = vector("access.log.linked.lz4");
+    let middle = frame.len() / 2;
+    frame[middle] ^= 0x40;
+    assert!(lz4::decompress(&frame).is_err());
+}
@@ -0,0 +1,668 @@
+// The browser API: wasm-bindgen wrappers over rs-compressor, built with
+//   cargo build -p rs-compressor-wasm --target wasm32-unknown-unknown --release
+// and bound with `wasm-bindgen --out-name rs_compressor` (see README).
+#![allow(clippy::upper_case_acronyms)]
+
+use wasm_bindgen::prelude::*;
+use js_sys::{Uint8Array, Array};
+
+use std::fmt;
+
+use rs_compressor::algos::dictionary;
+use rs_compressor::algos::file_type::{explain_selection as explain_file_selection, select_filters, Algorithm as FileAlgorithm};
+use rs_compressor::algos::filters::{self, Filter};
+use rs_compressor::framed;
+use rs_compressor::header::{self, Metadata};
+use rs_compressor::progress::{self, CancellationToken, Progress};
+use rs_compressor::stats::{CompressionStats, Stopwatch};
+use rs_compressor::{codec, crypto, error_code};
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    RLE,
+    LZ,
+    PackBits,
+    BWT,
+    RC,
+    LZ4,
+    Auto,
+}
+
+impl Algorithm {
+    /// The concrete codec, or `None` for `Auto`.
+    fn file_algorithm(&self) -> Option<FileAlgorithm> {
+        match self {
+            Algorithm::RLE => Some(FileAlgorithm::RLE),
+            Algorithm::LZ => Some(FileAlgorithm::LZ),
+            Algorithm::PackBits => Some(FileAlgorithm::PackBits),
+            Algorithm::BWT => Some(FileAlgorithm::BWT),
+            Algorithm::RC => Some(FileAlgorithm::RC),
+            Algorithm::LZ4 => Some(FileAlgorithm::LZ4),
+            Algorithm::Auto => None,
+        }
+    }
+}
+
+impl From<FileAlgorithm> for Algorithm {
+    fn from(algorithm: FileAlgorithm) -> Self {
+        match algorithm {
+            FileAlgorithm::RLE => Algorithm::RLE,
+            FileAlgorithm::LZ => Algorithm::LZ,
+            FileAlgorithm::PackBits => Algorithm::PackBits,
+            FileAlgorithm::BWT => Algorithm::BWT,
+            FileAlgorithm::RC => Algorithm::RC,
+            FileAlgorithm::LZ4 => Algorithm::LZ4,
+        }
+    }
+}
+
+/// Output of `compress`. Each accessor is a getter on the JS object.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct CompressionResult {
+    data: Vec<u8>,
+    algorithm: Algorithm,
+    filename: String,
+    original_size: usize,
+    stats: CompressionStats,
+}
+
+#[wasm_bindgen]
+impl CompressionResult {
+    /// The compressed bytes, header included.
+    #[wasm_bindgen(getter)]
+    pub fn data(&self) -> Uint8Array {
+        Uint8Array::from(&self.data[..])
+    }
+
+    /// The codec that was used; never `Auto`.
+    #[wasm_bindgen(getter)]
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn filename(&self) -> String {
+        self.filename.clone()
+    }
+
+    #[wasm_bindgen(getter = originalSize)]
+    pub fn original_size(&self) -> usize {
+        self.original_size
+    }
+
+    #[wasm_bindgen(getter = compressedSize)]
+    pub fn compressed_size(&self) -> usize {
+        self.data.len()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn stats(&self) -> WasmCompressionStats {
+        WasmCompressionStats { stats: self.stats.clone() }
+    }
+}
+
+/// Figures about a `compress` run, as `result.stats`. Sizes are in bytes.
+#[wasm_bindgen(js_name = CompressionStats)]
+pub struct WasmCompressionStats {
+    stats: CompressionStats,
+}
+
+#[wasm_bindgen(js_class = CompressionStats)]
+impl WasmCompressionStats {
+    #[wasm_bindgen(getter = inputSize)]
+    pub fn input_size(&self) -> f64 {
+        self.stats.input_size as f64
+    }
+
+    #[wasm_bindgen(getter = outputSize)]
+    pub fn output_size(&self) -> f64 {
+        self.stats.output_size as f64
+    }
+
+    /// Input size over output size.
+    #[wasm_bindgen(getter)]
+    pub fn ratio(&self) -> f64 {
+        self.stats.ratio()
+    }
+
+    #[wasm_bindgen(getter = elapsedMs)]
+    pub fn elapsed_ms(&self) -> f64 {
+        self.stats.elapsed.as_secs_f64() * 1000.0
+    }
+
+    /// `"Text"`, `"Binary"`, `"Image"`, `"Executable"` or `"Unknown"`.
+    #[wasm_bindgen(getter = fileType)]
+    pub fn file_type(&self) -> String {
+        format!("{:?}", self.stats.file_type)
+    }
+
+    /// Framed blocks or dedup chunks coded; 1 for a plain file.
+    #[wasm_bindgen(getter)]
+    pub fn blocks(&self) -> usize {
+        self.stats.blocks
+    }
+
+    /// LZ match count; `undefined` for other codecs, as are `literals` and
+    /// `averageMatchLength`.
+    #[wasm_bindgen(getter)]
+    pub fn matches(&self) -> Option<usize> {
+        self.stats.lz.map(|tokens| tokens.matches)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn literals(&self) -> Option<usize> {
+        self.stats.lz.map(|tokens| tokens.literals)
+    }
+
+    #[wasm_bindgen(getter = averageMatchLength)]
+    pub fn average_match_length(&self) -> Option<f64> {
+        self.stats.lz.map(|tokens| tokens.average_match_length())
+    }
+}
+
+/// What the codec functions throw, and what batch results list for the files
+/// that failed.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct CompressionError {
+    code: &'static str,
+    message: String,
+    filename: Option<String>,
+    offset: Option<usize>,
+}
+
+impl CompressionError {
+    /// `fallback` is the code for messages that fit no narrower one.
+    fn new(message: &str, fallback: &'static str) -> Self {
+        let code = error_code(message).unwrap_or(fallback);
+        Self { code, message: message.to_string(), filename: None, offset: None }
+    }
+
+    fn compressing(message: &str) -> Self {
+        Self::new(message, "INVALID_ARGUMENT")
+    }
+
+    fn decompressing(message: &str) -> Self {
+        Self::new(message, "CORRUPT_DATA")
+    }
+
+    fn invalid_input(message: String) -> Self {
+        Self { code: "INVALID_INPUT", message, filename: None, offset: None }
+    }
+
+    fn for_file(mut self, filename: &str) -> Self {
+        self.filename = Some(filename.to_string());
+        self
+    }
+
+    fn at(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+}
+
+#[wasm_bindgen]
+impl CompressionError {
+    /// Stable identifier to branch on, e.g. `"CHECKSUM_MISMATCH"`,
+    /// `"TRUNCATED"` or `"INVALID_FORMAT"`; the message may change.
+    #[wasm_bindgen(getter)]
+    pub fn code(&self) -> String {
+        self.code.to_string()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    /// The file concerned, for batch errors.
+    #[wasm_bindgen(getter)]
+    pub fn filename(&self) -> Option<String> {
+        self.filename.clone()
+    }
+
+    /// Byte offset in the compressed input where decoding failed, as near as
+    /// it can be told: 0 for a bad header, the damaged block of a seekable
+    /// file, otherwise the start of the payload.
+    #[wasm_bindgen(getter)]
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    #[wasm_bindgen(js_name = toString)]
+    pub fn js_to_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for CompressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(filename) = &self.filename {
+            write!(f, "{}: ", filename)?;
+        }
+        write!(f, "{} ({}", self.message, self.code)?;
+        if let Some(offset) = self.offset {
+            write!(f, " at byte {}", offset)?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[wasm_bindgen]
+pub struct BatchResult {
+    results: Vec<CompressionResult>,
+    errors: Vec<CompressionError>,
+}
+
+#[wasm_bindgen]
+impl BatchResult {
+    /// The files that compressed, as `CompressionResult` objects.
+    #[wasm_bindgen(getter)]
+    pub fn results(&self) -> Vec<CompressionResult> {
+        self.results.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn errors(&self) -> Vec<CompressionError> {
+        self.errors.clone()
+    }
+}
+
+/// One file from `decompress_batch`.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct DecompressionResult {
+    filename: String,
+    data: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl DecompressionResult {
+    #[wasm_bindgen(getter)]
+    pub fn filename(&self) -> String {
+        self.filename.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn data(&self) -> Uint8Array {
+        Uint8Array::from(&self.data[..])
+    }
+}
+
+#[wasm_bindgen]
+pub struct DecompressionBatchResult {
+    results: Vec<DecompressionResult>,
+    errors: Vec<CompressionError>,
+}
+
+#[wasm_bindgen]
+impl DecompressionBatchResult {
+    #[wasm_bindgen(getter)]
+    pub fn results(&self) -> Vec<DecompressionResult> {
+        self.results.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn errors(&self) -> Vec<CompressionError> {
+        self.errors.clone()
+    }
+}
+
+#[wasm_bindgen]
+pub fn compress(input: &[u8], algorithm: Algorithm, filename: &str) -> Result<CompressionResult, CompressionError> {
+    compress_with(input, algorithm, filename, None)
+}
+
+#[wasm_bindgen]
+pub fn compress_with_dictionary(input: &[u8], algorithm: Algorithm, filename: &str, dictionary: &[u8]) -> Result<CompressionResult, CompressionError> {
+    compress_with(input, algorithm, filename, Some(dictionary))
+}
+
+/// Compresses with an explicit filter chain such as "delta:2,transpose:4";
+/// an empty string turns off the filters `Auto` would pick.
+#[wasm_bindgen]
+pub fn compress_with_filters(input: &[u8], algorithm: Algorithm, filename: &str, filters: &str) -> Result<CompressionResult, CompressionError> {
+    let filters = match filters {
+        "" => Vec::new(),
+        spec => filters::parse_chain(spec).map_err(CompressionError::compressing)?,
+    };
+    compress_filtered(input, algorithm, filename, None, Some(filters), None)
+}
+
+/// Like `compress`, calling `onProgress(processed, total)` as it goes and
+/// stopping with a `CANCELLED` error once `token` is cancelled (pass a fresh
+/// token to never cancel). The output is the same as `compress` writes.
+#[wasm_bindgen]
+pub fn compress_with_progress(
+    input: &[u8],
+    algorithm: Algorithm,
+    filename: &str,
+    on_progress: &js_sys::Function,
+    token: &WasmCancellationToken,
+) -> Result<CompressionResult, CompressionError> {
+    let mut progress = js_progress(on_progress, token);
+    compress_filtered(input, algorithm, filename, None, None, Some(&mut progress))
+}
+
+/// Stops a `compress_with_progress` or `decompress_with_progress` run at its
+/// next progress report. The run holds the thread, so `cancel` is called from
+/// the progress callback (or from another worker sharing the module memory).
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct WasmCancellationToken {
+    token: CancellationToken,
+}
+
+#[wasm_bindgen]
+impl WasmCancellationToken {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmCancellationToken {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+}
+
+/// Forwards progress to a JS callback. A callback that throws cancels the
+/// run.
+fn js_progress<'a>(callback: &'a js_sys::Function, token: &WasmCancellationToken) -> Progress<'a> {
+    let on_throw = token.token.clone();
+    Progress::new(move |done, total| {
+        let (done, total) = (JsValue::from_f64(done as f64), JsValue::from_f64(total as f64));
+        if callback.call2(&JsValue::NULL, &done, &total).is_err() {
+            on_throw.cancel();
+        }
+    })
+    .with_cancellation(token.token.clone())
+}
+
+fn compress_with(input: &[u8], algorithm: Algorithm, filename: &str, dictionary: Option<&[u8]>) -> Result<CompressionResult, CompressionError> {
+    compress_filtered(input, algorithm, filename, dictionary, None, None)
+}
+
+/// `filters` of `None` leaves the choice to `Auto`: BCJ for executables.
+fn compress_filtered(
+    input: &[u8],
+    algorithm: Algorithm,
+    filename: &str,
+    dictionary: Option<&[u8]>,
+    filters: Option<Vec<Filter>>,
+    progress: Option<&mut Progress>,
+) -> Result<CompressionResult, CompressionError> {
+    let stopwatch = Stopwatch::start();
+    let (selected_algorithm, auto_filters) = match algorithm.file_algorithm() {
+        Some(selected) => (selected, Vec::new()),
+        None => (codec::auto_select(input, dictionary), select_filters(input)),
+    };
+    let filters = filters.unwrap_or(auto_filters);
+
+    // The filename goes into the header so decompressing can restore it. LZ4
+    // frames have no RSCMP header to put it in.
+    let metadata = Metadata { filename: Some(filename.to_string()), ..Metadata::default() };
+    let metadata = Some(&metadata).filter(|_| !filename.is_empty() && selected_algorithm != FileAlgorithm::LZ4);
+    let result = match progress {
+        Some(progress) => codec::compress_with_progress(input, selected_algorithm, &filters, dictionary, metadata, progress),
+        None => codec::compress_with_filters(input, selected_algorithm, &filters, dictionary, metadata),
+    }
+    .map_err(CompressionError::compressing)?;
+
+    let stats = CompressionStats::collect(input, &result, stopwatch.elapsed());
+    Ok(CompressionResult {
+        data: result,
+        algorithm: Algorithm::from(selected_algorithm),
+        filename: filename.to_string(),
+        original_size: input.len(),
+        stats,
+    })
+}
+
+#[wasm_bindgen]
+pub fn train_dictionary(samples: Array, size: usize) -> Result<Uint8Array, JsValue> {
+    let mut buffers = Vec::new();
+    for i in 0..samples.length() {
+        let sample = samples
+            .get(i)
+            .dyn_into::<Uint8Array>()
+            .map_err(|_| JsValue::from_str(&format!("Invalid sample at index {}", i)))?;
+        buffers.push(sample.to_vec());
+    }
+    Ok(Uint8Array::from(&dictionary::train(&buffers, size)[..]))
+}
+
+#[wasm_bindgen]
+pub fn explain_selection(input: &[u8]) -> Result<JsValue, JsValue> {
+    let report = explain_file_selection(input);
+
+    let candidates = js_sys::Object::new();
+    for (algorithm, size) in &report.candidates {
+        js_sys::Reflect::set(&candidates, &JsValue::from_str(&format!("{:?}", algorithm)), &JsValue::from_f64(*size as f64))?;
+    }
+
+    let result = js_sys::Object::new();
+    js_sys::Reflect::set(&result, &JsValue::from_str("fileType"), &JsValue::from_str(&format!("{:?}", report.file_type)))?;
+    js_sys::Reflect::set(&result, &JsValue::from_str("entropy"), &JsValue::from_f64(report.entropy))?;
+    js_sys::Reflect::set(&result, &JsValue::from_str("runRatio"), &JsValue::from_f64(report.run_ratio))?;
+    let filters: Vec<String> = report.filters.iter().map(Filter::to_string).collect();
+    js_sys::Reflect::set(&result, &JsValue::from_str("filters"), &JsValue::from_str(&filters.join(",")))?;
+    js_sys::Reflect::set(&result, &JsValue::from_str("candidates"), &candidates)?;
+    js_sys::Reflect::set(&result, &JsValue::from_str("selected"), &JsValue::from_str(&format!("{:?}", report.selected)))?;
+    Ok(result.into())
+}
+
+/// Reads `{ name, data }` at index `i` of a batch.
+fn batch_file(files: &Array, i: u32) -> Result<(String, Vec<u8>), CompressionError> {
+    let file = files.get(i);
+    let js_file = file
+        .dyn_into::<js_sys::Object>()
+        .map_err(|_| CompressionError::invalid_input(format!("Invalid file object at index {}", i)))?;
+
+    let name = js_sys::Reflect::get(&js_file, &JsValue::from_str("name"))
+        .map_err(|_| CompressionError::invalid_input(format!("Missing name for file at index {}", i)))?
+        .as_string()
+        .unwrap_or_else(|| format!("file_{}", i));
+
+    let data = js_sys::Reflect::get(&js_file, &JsValue::from_str("data"))
+        .map_err(|_| CompressionError::invalid_input(format!("Missing data for file at index {}", i)).for_file(&name))?
+        .dyn_into::<Uint8Array>()
+        .map_err(|_| CompressionError::invalid_input(format!("Invalid data for file at index {}", i)).for_file(&name))?
+        .to_vec();
+    Ok((name, data))
+}
+
+#[wasm_bindgen]
+pub fn compress_batch(files: Array, algorithm: Algorithm) -> BatchResult {
+    let mut results = Vec::new();
+    let mut errors = Vec::new();
+    for i in 0..files.length() {
+        let compressed = batch_file(&files, i)
+            .and_then(|(name, data)| compress(&data, algorithm, &name).map_err(|e| e.for_file(&name)));
+        match compressed {
+            Ok(result) => results.push(result),
+            Err(e) => errors.push(e),
+        }
+    }
+    BatchResult { results, errors }
+}
+
+#[wasm_bindgen]
+pub fn decompress(input: &[u8], algorithm: Algorithm) -> Result<Uint8Array, CompressionError> {
+    decompress_with(input, algorithm, None, &mut Progress::none())
+}
+
+/// Like `decompress`, calling `onProgress(processed, total)` with compressed
+/// bytes after each block of a seekable file (other files report only their
+/// start and end) and stopping once `token` is cancelled.
+#[wasm_bindgen]
+pub fn decompress_with_progress(
+    input: &[u8],
+    algorithm: Algorithm,
+    on_progress: &js_sys::Function,
+    token: &WasmCancellationToken,
+) -> Result<Uint8Array, CompressionError> {
+    decompress_with(input, algorithm, None, &mut js_progress(on_progress, token))
+}
+
+#[wasm_bindgen]
+pub fn decompress_with_dictionary(input: &[u8], algorithm: Algorithm, dictionary: &[u8]) -> Result<Uint8Array, CompressionError> {
+    decompress_with(input, algorithm, Some(dictionary), &mut Progress::none())
+}
+
+/// Encrypts compressed output with a passphrase (Argon2id key derivation,
+/// ChaCha20-Poly1305). LZ4 output has no header and can't be encrypted.
+#[wasm_bindgen]
+pub fn encrypt(compressed: &[u8], passphrase: &str) -> Result<Uint8Array, CompressionError> {
+    let encrypted = crypto::encrypt(compressed, passphrase).map_err(CompressionError::compressing)?;
+    Ok(Uint8Array::from(&encrypted[..]))
+}
+
+/// Turns `encrypt` output back into compressed data for `decompress`. A wrong
+/// passphrase or modified data throws.
+#[wasm_bindgen]
+pub fn decrypt(encrypted: &[u8], passphrase: &str) -> Result<Uint8Array, CompressionError> {
+    let decrypted = crypto::decrypt(encrypted, passphrase).map_err(CompressionError::decompressing)?;
+    Ok(Uint8Array::from(&decrypted[..]))
+}
+
+/// Metadata stored in the header as `{ filename, mtime, mode, fields }`, or
+/// `null` when the file has none.
+#[wasm_bindgen]
+pub fn read_metadata(input: &[u8]) -> Result<JsValue, JsValue> {
+    let Some(metadata) = header::read_header(input).map_err(|e| JsValue::from(CompressionError::decompressing(e).at(0)))?.metadata else {
+        return Ok(JsValue::NULL);
+    };
+
+    let fields = js_sys::Object::new();
+    for (key, value) in &metadata.fields {
+        js_sys::Reflect::set(&fields, &JsValue::from_str(key), &JsValue::from_str(value))?;
+    }
+
+    let result = js_sys::Object::new();
+    let optional = |value: Option<f64>| value.map_or(JsValue::UNDEFINED, JsValue::from_f64);
+    js_sys::Reflect::set(&result, &JsValue::from_str("filename"), &metadata.filename.as_deref().map_or(JsValue::UNDEFINED, JsValue::from_str))?;
+    js_sys::Reflect::set(&result, &JsValue::from_str("mtime"), &optional(metadata.mtime.map(|mtime| mtime as f64)))?;
+    js_sys::Reflect::set(&result, &JsValue::from_str("mode"), &optional(metadata.mode.map(f64::from)))?;
+    js_sys::Reflect::set(&result, &JsValue::from_str("fields"), &fields)?;
+    Ok(result.into())
+}
+
+/// Decompresses `length` bytes starting at `start`. Seekable files only decode
+/// the blocks the range covers, so viewers can page through large logs.
+#[wasm_bindgen]
+pub fn decompress_range(input: &[u8], start: f64, length: usize) -> Result<Uint8Array, CompressionError> {
+    if start.is_nan() || start < 0.0 {
+        return Err(CompressionError::invalid_input("Range start must not be negative".to_string()));
+    }
+    let data = codec::decompress_range(input, start as u64, length, None).map_err(CompressionError::decompressing)?;
+    Ok(Uint8Array::from(&data[..]))
+}
+
+/// Incremental compressor for `TransformStream`s: each `push` returns the
+/// blocks its chunk completed (often none), `finish` the rest. Output is a
+/// seekable framed file, so `decompress_range` works on it too.
+#[wasm_bindgen]
+pub struct WasmCompressor {
+    encoder: Option<framed::StreamEncoder>,
+}
+
+#[wasm_bindgen]
+impl WasmCompressor {
+    /// `blockSize` defaults to 1 MiB. `Algorithm.Auto` picks the codec from
+    /// the first block; LZ4 isn't supported.
+    #[wasm_bindgen(constructor)]
+    pub fn new(algorithm: Algorithm, block_size: Option<u32>) -> Result<WasmCompressor, CompressionError> {
+        let block_size = block_size.unwrap_or(framed::DEFAULT_BLOCK_SIZE);
+        let encoder = framed::StreamEncoder::new(algorithm.file_algorithm(), block_size).map_err(CompressionError::compressing)?;
+        Ok(Self { encoder: Some(encoder) })
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Uint8Array, CompressionError> {
+        let encoder = self.encoder.as_mut().ok_or_else(|| CompressionError::invalid_input("Compressor already finished".to_string()))?;
+        let output = encoder.push(chunk).map_err(CompressionError::compressing)?;
+        Ok(Uint8Array::from(&output[..]))
+    }
+
+    pub fn finish(&mut self) -> Result<Uint8Array, CompressionError> {
+        let encoder = self.encoder.take().ok_or_else(|| CompressionError::invalid_input("Compressor already finished".to_string()))?;
+        let output = encoder.finish().map_err(CompressionError::compressing)?;
+        Ok(Uint8Array::from(&output[..]))
+    }
+}
+
+/// Incremental decompressor: seekable files come out block by block as they
+/// arrive, anything else all at once from `finish`.
+#[wasm_bindgen]
+pub struct WasmDecompressor {
+    decoder: Option<framed::StreamDecoder>,
+}
+
+#[wasm_bindgen]
+impl WasmDecompressor {
+    #[wasm_bindgen(constructor)]
+    pub fn new(dictionary: Option<Vec<u8>>) -> WasmDecompressor {
+        Self { decoder: Some(framed::StreamDecoder::new(dictionary)) }
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Uint8Array, CompressionError> {
+        let decoder = self.decoder.as_mut().ok_or_else(|| CompressionError::invalid_input("Decompressor already finished".to_string()))?;
+        let output = decoder.push(chunk).map_err(CompressionError::decompressing)?;
+        Ok(Uint8Array::from(&output[..]))
+    }
+
+    pub fn finish(&mut self) -> Result<Uint8Array, CompressionError> {
+        let decoder = self.decoder.take().ok_or_else(|| CompressionError::invalid_input("Decompressor already finished".to_string()))?;
+        let output = decoder.finish().map_err(CompressionError::decompressing)?;
+        Ok(Uint8Array::from(&output[..]))
+    }
+}
+
+fn decompress_with(
+    input: &[u8],
+    algorithm: Algorithm,
+    dictionary: Option<&[u8]>,
+    progress: &mut Progress,
+) -> Result<Uint8Array, CompressionError> {
+    let decompressed = match algorithm.file_algorithm() {
+        None => codec::decompress_with_progress(input, dictionary, progress).map(|(output, _)| output).map_err(|e| match e {
+            progress::CANCELLED => CompressionError::decompressing(e),
+            _ => CompressionError::decompressing(e).at(codec::error_offset(input, dictionary)),
+        }),
+        Some(selected) => progress
+            .start(input.len() as u64)
+            .and_then(|_| {
+                let algorithm = codec::header_algorithm(selected);
+                codec::decompress_payload_with_report(input, algorithm, &[], dictionary, &mut progress.report())
+            })
+            .inspect(|_| progress.finish())
+            .map_err(CompressionError::decompressing),
+    }?;
+
+    Ok(Uint8Array::from(&decompressed[..]))
+}
+
+/// Decompresses `{ name, data }` files; the ones that fail are listed in
+/// `errors` rather than failing the batch.
+#[wasm_bindgen]
+pub fn decompress_batch(files: Array, algorithm: Algorithm) -> DecompressionBatchResult {
+    let mut results = Vec::new();
+    let mut errors = Vec::new();
+    for i in 0..files.length() {
+        let decompressed = batch_file(&files, i).and_then(|(name, data)| {
+            let data = decompress(&data, algorithm).map_err(|e| e.for_file(&name))?.to_vec();
+            Ok(DecompressionResult { filename: name, data })
+        });
+        match decompressed {
+            Ok(result) => results.push(result),
+            Err(e) => errors.push(e),
+        }
+    }
+    DecompressionBatchResult { results, errors }
+}
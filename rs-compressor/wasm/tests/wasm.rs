@@ -0,0 +1,173 @@
+// Tests of the JS-facing API. They need a JS engine, so they only build for
+// wasm32 and run in Node:
+//   cargo test -p rs-compressor-wasm --target wasm32-unknown-unknown --test wasm
+// with wasm-bindgen-test-runner as the target runner (see README).
+#![cfg(target_arch = "wasm32")]
+
+use js_sys::{Array, Object, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use rs_compressor_wasm::{
+    compress, compress_batch, compress_with_progress, decompress, decompress_batch, decompress_with_progress, Algorithm,
+    CompressionResult, WasmCancellationToken, WasmCompressor, WasmDecompressor,
+};
+
+fn file(name: &str, data: &[u8]) -> JsValue {
+    let file = Object::new();
+    Reflect::set(&file, &JsValue::from_str("name"), &JsValue::from_str(name)).unwrap();
+    Reflect::set(&file, &JsValue::from_str("data"), &Uint8Array::from(data)).unwrap();
+    file.into()
+}
+
+#[wasm_bindgen_test]
+fn compression_result_getters() {
+    let input = b"the quick brown fox jumps over the quick brown dog".repeat(10);
+    let result = compress(&input, Algorithm::LZ, "fox.txt").unwrap();
+    assert_eq!(result.algorithm(), Algorithm::LZ);
+    assert_eq!(result.filename(), "fox.txt");
+    assert_eq!(result.original_size(), input.len());
+    assert_eq!(result.compressed_size(), result.data().length() as usize);
+    assert!(result.compressed_size() < input.len());
+
+    let stats = result.stats();
+    assert_eq!(stats.input_size(), input.len() as f64);
+    assert_eq!(stats.output_size(), result.compressed_size() as f64);
+    assert_eq!(stats.file_type(), "Text");
+    assert_eq!(stats.blocks(), 1);
+    assert!(stats.ratio() > 1.0 && stats.elapsed_ms() >= 0.0);
+    assert!(stats.matches().unwrap() > 0 && stats.average_match_length().unwrap() >= 3.0);
+
+    let restored = decompress(&result.data().to_vec(), Algorithm::Auto).unwrap();
+    assert_eq!(restored.to_vec(), input);
+}
+
+#[wasm_bindgen_test]
+fn auto_reports_the_codec_it_picked() {
+    let result = compress(&b"a".repeat(1000), Algorithm::Auto, "runs.txt").unwrap();
+    assert_ne!(result.algorithm(), Algorithm::Auto);
+}
+
+#[wasm_bindgen_test]
+fn batch_results_are_compression_results() {
+    let files = Array::new();
+    files.push(&file("one.txt", b"first file, first file"));
+    files.push(&JsValue::from_str("not a file"));
+    files.push(&file("two.txt", b"second file, second file"));
+
+    let batch = compress_batch(files, Algorithm::RC);
+    let results: Vec<CompressionResult> = batch.results();
+    let names: Vec<String> = results.iter().map(CompressionResult::filename).collect();
+    assert_eq!(names, ["one.txt", "two.txt"]);
+    assert!(results.iter().all(|result| result.algorithm() == Algorithm::RC));
+    let errors = batch.errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code(), "INVALID_INPUT");
+
+    let restored = decompress(&results[1].data().to_vec(), Algorithm::Auto).unwrap();
+    assert_eq!(restored.to_vec(), b"second file, second file");
+}
+
+#[wasm_bindgen_test]
+fn streaming_roundtrip() {
+    let input: Vec<u8> = (0..100_000u32).flat_map(|i| format!("line {}\n", i % 977).into_bytes()).collect();
+    let mut compressor = WasmCompressor::new(Algorithm::Auto, Some(64 * 1024)).unwrap();
+    let mut compressed = Vec::new();
+    for chunk in input.chunks(10_000) {
+        compressed.extend(compressor.push(chunk).unwrap().to_vec());
+    }
+    compressed.extend(compressor.finish().unwrap().to_vec());
+    assert!(compressor.push(b"late").is_err());
+    assert_eq!(decompress(&compressed, Algorithm::Auto).unwrap().to_vec(), input);
+
+    let mut decompressor = WasmDecompressor::new(None);
+    let mut restored = Vec::new();
+    for chunk in compressed.chunks(5_000) {
+        restored.extend(decompressor.push(chunk).unwrap().to_vec());
+    }
+    assert!(!restored.is_empty());
+    restored.extend(decompressor.finish().unwrap().to_vec());
+    assert_eq!(restored, input);
+    // A js-compressor RLE file is only decoded once it is complete.
+    let mut decompressor = WasmDecompressor::new(None);
+    assert_eq!(decompressor.push(b"JSCMP\x01\x01\x00").unwrap().length(), 0);
+    assert_eq!(decompressor.push(b"\x03a\x02b").unwrap().length(), 0);
+    assert_eq!(decompressor.finish().unwrap().to_vec(), b"aaabb");
+}
+
+#[wasm_bindgen_test]
+fn streamed_lengths_that_overflow_are_errors() {
+    let mut compressor = WasmCompressor::new(Algorithm::LZ, Some(1024)).unwrap();
+    let mut framed = compressor.push(&[7; 100]).unwrap().to_vec();
+    framed.extend(compressor.finish().unwrap().to_vec());
+    let header = &framed[..framed.windows(4).position(|window| window == b"RSBK").unwrap()];
+
+    // A block and a parity record whose lengths wrap a 32-bit usize.
+    let mut block = b"RSBK".to_vec();
+    block.extend_from_slice(&u32::MAX.to_le_bytes());
+    block.resize(24, 0);
+    let mut parity = b"RSPR".to_vec();
+    parity.extend_from_slice(&1u16.to_le_bytes());
+    parity.extend_from_slice(&2u16.to_le_bytes());
+    parity.extend_from_slice(&u32::MAX.to_le_bytes());
+    for record in [block, parity] {
+        let mut decompressor = WasmDecompressor::new(None);
+        let error = decompressor.push(&[header, &record].concat()).unwrap_err();
+        assert_eq!(error.message(), "Block length out of range");
+    }
+}
+
+#[wasm_bindgen_test]
+fn errors_are_structured() {
+    let error = decompress(b"definitely not compressed", Algorithm::Auto).unwrap_err();
+    assert_eq!(error.code(), "INVALID_FORMAT");
+    assert_eq!(error.message(), "Invalid magic number");
+    assert_eq!(error.offset(), Some(0));
+    assert_eq!(error.filename(), None);
+
+    let good = compress(b"good data, good data, good data", Algorithm::LZ, "good.txt").unwrap().data().to_vec();
+    let mut bad = compress(&b"bad data ".repeat(50), Algorithm::RC, "bad.txt").unwrap().data().to_vec();
+    bad.truncate(bad.len() / 2);
+
+    let files = Array::new();
+    files.push(&file("good.txt.rscmp", &good));
+    files.push(&file("bad.txt.rscmp", &bad));
+    let batch = decompress_batch(files, Algorithm::Auto);
+    let results = batch.results();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].filename(), "good.txt.rscmp");
+    assert_eq!(results[0].data().to_vec(), b"good data, good data, good data");
+
+    let errors = batch.errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].filename().as_deref(), Some("bad.txt.rscmp"));
+    assert!(errors[0].offset().unwrap() > 0);
+    assert!(errors[0].to_string().starts_with("bad.txt.rscmp: "));
+}
+
+#[wasm_bindgen_test]
+fn progress_reaches_the_total_and_cancels() {
+    let input: Vec<u8> = (0..3_000_000u32).map(|i| (i % 251) as u8).collect();
+    let reports = Array::new();
+    let record = Closure::<dyn FnMut(f64, f64)>::new({
+        let reports = reports.clone();
+        move |done: f64, total: f64| {
+            reports.push(&Array::of2(&done.into(), &total.into()));
+        }
+    });
+    let callback = record.as_ref().unchecked_ref();
+    let token = WasmCancellationToken::new();
+    let result = compress_with_progress(&input, Algorithm::LZ, "big.bin", callback, &token).unwrap();
+    // The codec reports as it goes, not just at the start and end.
+    assert!(reports.length() > 10);
+    let last = Array::from(&reports.get(reports.length() - 1));
+    assert_eq!(last.get(0), JsValue::from_f64(input.len() as f64));
+    assert_eq!(result.data().to_vec(), compress(&input, Algorithm::LZ, "big.bin").unwrap().data().to_vec());
+    assert_eq!(decompress(&result.data().to_vec(), Algorithm::Auto).unwrap().to_vec(), input);
+
+    // A callback that throws cancels the run through its token.
+    let throwing = js_sys::Function::new_no_args("throw new Error('stop')");
+    let error = decompress_with_progress(&result.data().to_vec(), Algorithm::Auto, &throwing, &token).unwrap_err();
+    assert_eq!(error.code(), "CANCELLED");
+    assert!(token.cancelled());
+}